@@ -0,0 +1,257 @@
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+
+/// Half floats keep enough precision to average a few hundred samples
+pub const ACCUMULATION_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// Past this the image doesn't visibly change anymore, and the weight of
+/// each new sample gets too small for half floats
+pub const MAX_SAMPLES: u32 = 256;
+
+/// What to render the next sample with
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccumulationSample {
+    /// Sub-pixel offset of the rays, in pixels
+    pub jitter: [f32; 2],
+    /// How much the sample counts in the average. The accumulation pipeline
+    /// blends `sample * weight + accumulated * (1 - weight)`.
+    pub weight: f32,
+}
+
+/// Progressive rendering: every frame one more sample of the scene, with
+/// its rays slightly jittered inside the pixels, is blended into a
+/// persistent texture that holds the running average of all the samples.
+/// The noise and aliasing of a single sample converge away over time,
+/// as long as the camera stays still: any movement starts over.
+pub struct AccumulationRenderer {
+    texture: Tracked<wgpu::Texture>,
+    view: wgpu::TextureView,
+    display_bind_group_layout: wgpu::BindGroupLayout,
+    display_bind_group: wgpu::BindGroup,
+    display_pipeline_layout: wgpu::PipelineLayout,
+    display_pipeline: wgpu::RenderPipeline,
+    sample_count: u32,
+    // The camera the samples were rendered from
+    camera: Option<[[f32; 3]; 2]>,
+}
+
+impl AccumulationRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        size: winit::dpi::PhysicalSize<u32>,
+        output_format: wgpu::TextureFormat,
+        output_sample_count: u32,
+    ) -> Self {
+        let (texture, view) = create_texture(device, memory, size);
+
+        let display_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Accumulation Display Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                }],
+            });
+        let display_bind_group =
+            create_display_bind_group(device, &display_bind_group_layout, &view);
+        let display_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Accumulation Display Pipeline Layout"),
+                bind_group_layouts: &[&display_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let display_pipeline = create_display_pipeline(
+            device,
+            &display_pipeline_layout,
+            output_format,
+            output_sample_count,
+        );
+
+        Self {
+            texture,
+            view,
+            display_bind_group_layout,
+            display_bind_group,
+            display_pipeline_layout,
+            display_pipeline,
+            sample_count: 0,
+            camera: None,
+        }
+    }
+
+    /// The accumulated samples are lost with the old texture
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        (self.texture, self.view) = create_texture(device, memory, size);
+        self.display_bind_group =
+            create_display_bind_group(device, &self.display_bind_group_layout, &self.view);
+        self.reset();
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_display_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        output_sample_count: u32,
+    ) {
+        self.display_pipeline = create_display_pipeline(
+            device,
+            &self.display_pipeline_layout,
+            output_format,
+            output_sample_count,
+        );
+    }
+
+    /// Throw away the samples, the next one starts a new average
+    pub fn reset(&mut self) {
+        self.sample_count = 0;
+        self.camera = None;
+    }
+
+    /// How many samples have been averaged so far
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
+
+    pub fn is_converged(&self) -> bool {
+        self.sample_count >= MAX_SAMPLES
+    }
+
+    /// The next sample to render from 'camera' (its position and target),
+    /// or None once the image has converged.
+    /// Moving the camera starts over.
+    pub fn next_sample(&mut self, camera: [[f32; 3]; 2]) -> Option<AccumulationSample> {
+        if self.camera != Some(camera) {
+            self.reset();
+            self.camera = Some(camera);
+        }
+        if self.is_converged() {
+            return None;
+        }
+
+        // Spread the jitter evenly over the pixel
+        let jitter = [
+            halton(self.sample_count + 1, 2) - 0.5,
+            halton(self.sample_count + 1, 3) - 0.5,
+        ];
+        // The nth sample weighs 1 / n, which keeps the texture at the average
+        self.sample_count += 1;
+        Some(AccumulationSample {
+            jitter,
+            weight: 1.0 / self.sample_count as f32,
+        })
+    }
+
+    /// Start a pass rendering the next sample into the accumulation texture.
+    /// The first sample replaces whatever was there, the next ones blend in.
+    pub fn begin_pass<'a>(&'a self, encoder: &'a mut wgpu::CommandEncoder) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Accumulation Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: if self.sample_count <= 1 {
+                        wgpu::LoadOp::Clear(wgpu::Color::BLACK)
+                    } else {
+                        wgpu::LoadOp::Load
+                    },
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        })
+    }
+
+    /// Show the accumulated image over the whole render target
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.display_pipeline);
+        render_pass.set_bind_group(0, &self.display_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// The 'index'th number of the Halton sequence in 'base', in [0, 1).
+/// Consecutive numbers fill the interval evenly, unlike random ones.
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+    result
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> (Tracked<wgpu::Texture>, wgpu::TextureView) {
+    let texture = memory.create_texture(
+        device,
+        &wgpu::TextureDescriptor {
+            label: Some("Accumulation Texture"),
+            size: wgpu::Extent3d {
+                width: size.width.max(1),
+                height: size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: ACCUMULATION_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    (texture, view)
+}
+
+fn create_display_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Accumulation Display Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(view),
+        }],
+    })
+}
+
+fn create_display_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    output_format: wgpu::TextureFormat,
+    output_sample_count: u32,
+) -> wgpu::RenderPipeline {
+    fullscreen::create_fullscreen_pipeline(
+        device,
+        "Accumulation Display Pipeline",
+        Some(layout),
+        output_format,
+        output_sample_count,
+        include_str!("accumulation.wgsl"),
+        "fs_accumulation",
+    )
+}
@@ -0,0 +1,12 @@
+// Appended to fullscreen.wgsl: shows the average of the samples
+// accumulated so far.
+
+@group(0) @binding(0) var accumulated: texture_2d<f32>;
+
+@fragment
+fn fs_accumulation(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let size = textureDimensions(accumulated);
+    // The accumulation texture has the size of the window, one texel per pixel
+    let texel = min(vec2<u32>(in.uv * vec2<f32>(size)), size - 1u);
+    return vec4<f32>(textureLoad(accumulated, texel, 0).rgb, 1.0);
+}
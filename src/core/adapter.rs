@@ -0,0 +1,85 @@
+/// Every adapter of every backend
+#[cfg(not(target_arch = "wasm32"))]
+fn all_adapters(instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+    instance.enumerate_adapters(wgpu::Backends::all()).collect()
+}
+
+/// A browser doesn't let us enumerate its adapters: it only hands out the
+/// one request_adapter() asks for, which is what we fall back to
+#[cfg(target_arch = "wasm32")]
+fn all_adapters(_instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+    Vec::new()
+}
+
+/// Enumerate every adapter available on this machine,
+/// printing their index, name, backend and device type
+pub fn list_adapters(instance: &wgpu::Instance) -> Vec<wgpu::AdapterInfo> {
+    let infos: Vec<wgpu::AdapterInfo> = all_adapters(instance)
+        .into_iter()
+        .map(|adapter| adapter.get_info())
+        .collect();
+
+    println!("Available adapters:");
+    for (index, info) in infos.iter().enumerate() {
+        println!(
+            "  [{index}] {} ({:?}, {:?})",
+            info.name, info.backend, info.device_type
+        );
+    }
+
+    infos
+}
+
+/// Pick the adapter at 'index' in the list returned by list_adapters(),
+/// as long as it can present to the given surface, if there's one
+pub fn adapter_by_index(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    index: usize,
+) -> Option<wgpu::Adapter> {
+    let Some(adapter) = all_adapters(instance).into_iter().nth(index) else {
+        log::warn!("There is no adapter with index {index}, falling back to automatic selection");
+        return None;
+    };
+
+    if !surface.is_none_or(|surface| adapter.is_surface_supported(surface)) {
+        log::warn!(
+            "Adapter {index} ({}) can't present to this window, falling back to automatic selection",
+            adapter.get_info().name
+        );
+        return None;
+    }
+
+    Some(adapter)
+}
+
+/// Pick the first adapter whose name contains 'name', ignoring case,
+/// among the ones that can present to the given surface, if there's one
+pub fn adapter_by_name(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface>,
+    name: &str,
+) -> Option<wgpu::Adapter> {
+    let name = name.to_lowercase();
+    let matches: Vec<wgpu::Adapter> = all_adapters(instance)
+        .into_iter()
+        .filter(|adapter| adapter.get_info().name.to_lowercase().contains(&name))
+        .collect();
+
+    if matches.is_empty() {
+        log::warn!("There is no adapter named like {name:?}, falling back to automatic selection");
+        return None;
+    }
+
+    let adapter = matches
+        .into_iter()
+        .find(|adapter| surface.is_none_or(|surface| adapter.is_surface_supported(surface)));
+    if adapter.is_none() {
+        log::warn!(
+            "No adapter named like {name:?} can present to this window, \
+             falling back to automatic selection"
+        );
+    }
+
+    adapter
+}
@@ -0,0 +1,432 @@
+use std::time::Duration;
+
+use super::easing::{self, Animation, Lerp};
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+use super::rng::Rng;
+use super::shader::{self, ShaderStage};
+
+/// Side of the square workgroups, must match @workgroup_size in automata.wgsl
+const WORKGROUP_SIZE: u32 = 8;
+
+/// How long it takes to blend from one palette to the next
+const PALETTE_TRANSITION: Duration = Duration::from_millis(500);
+
+/// Alive and dead cell colors, cycled through with `next_palette()`
+const PALETTES: [CellColors; 3] = [
+    CellColors {
+        alive: [0.0, 0.0, 0.0, 1.0],
+        dead: [1.0, 1.0, 1.0, 1.0],
+    },
+    CellColors {
+        alive: [0.1, 0.9, 0.3, 1.0],
+        dead: [0.0, 0.05, 0.0, 1.0],
+    },
+    CellColors {
+        alive: [1.0, 0.6, 0.1, 1.0],
+        dead: [0.1, 0.0, 0.2, 1.0],
+    },
+];
+
+/// Must match the `CellColors` struct in automata_display.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CellColors {
+    alive: [f32; 4],
+    dead: [f32; 4],
+}
+
+impl Lerp for CellColors {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            alive: self.alive.lerp(&other.alive, t),
+            dead: self.dead.lerp(&other.dead, t),
+        }
+    }
+}
+
+/// How the cell colors reach the display shader.
+/// Push constants are the cheapest way to send a few bytes per draw:
+/// they're recorded straight into the command buffer, with no buffer or
+/// bind group to manage. They're an optional feature though, so we fall
+/// back to a good old uniform buffer when the device doesn't have them.
+enum ColorsBinding {
+    PushConstants,
+    Uniform {
+        buffer: Tracked<wgpu::Buffer>,
+        bind_group: wgpu::BindGroup,
+    },
+}
+
+/// Conway's Game of Life running entirely on the GPU.
+/// The state lives in two textures that are 'ping-ponged': each step reads
+/// one and writes the other via a compute shader, then they swap roles.
+pub struct CellularAutomata {
+    width: u32,
+    height: u32,
+    textures: [Tracked<wgpu::Texture>; 2],
+    step_pipeline: wgpu::ComputePipeline,
+    // step_bind_groups[i] reads textures[i] and writes the other one
+    step_bind_groups: [wgpu::BindGroup; 2],
+    display_pipeline_layout: wgpu::PipelineLayout,
+    display_pipeline: wgpu::RenderPipeline,
+    // display_bind_groups[i] shows textures[i]
+    display_bind_groups: [wgpu::BindGroup; 2],
+    colors_binding: ColorsBinding,
+    palette: usize,
+    colors: Animation<CellColors>,
+    // Index of the texture holding the current generation
+    current: usize,
+    step_interval: Duration,
+    // Simulated time that hasn't been turned into steps yet
+    accumulator: Duration,
+    pending_steps: u32,
+}
+
+impl CellularAutomata {
+    /// Every cell starts dead, see randomize()
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        output_format: wgpu::TextureFormat,
+        sample_count: u32,
+        (width, height): (u32, u32),
+        steps_per_second: f32,
+    ) -> Self {
+        let create_texture = |label| {
+            memory.create_texture(
+                device,
+                &wgpu::TextureDescriptor {
+                    label: Some(label),
+                    size: wgpu::Extent3d {
+                        width,
+                        height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    // One u32 per cell, 1 is alive and 0 is dead
+                    format: wgpu::TextureFormat::R32Uint,
+                    usage: wgpu::TextureUsages::TEXTURE_BINDING
+                        | wgpu::TextureUsages::STORAGE_BINDING
+                        | wgpu::TextureUsages::COPY_DST,
+                    view_formats: &[],
+                },
+            )
+        };
+        let textures = [
+            create_texture("Automata Texture A"),
+            create_texture("Automata Texture B"),
+        ];
+        let views = textures
+            .each_ref()
+            .map(|texture| texture.create_view(&wgpu::TextureViewDescriptor::default()));
+
+        let cells_layout_entry = wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::COMPUTE | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Uint,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+
+        // Compute: read the current generation, write the next one
+        let step_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Automata Step Bind Group Layout"),
+            entries: &[
+                cells_layout_entry,
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::R32Uint,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let step_bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Automata Step Bind Group"),
+                layout: &step_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[i]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&views[1 - i]),
+                    },
+                ],
+            })
+        });
+
+        let step_shader = shader::create_shader_module(
+            device,
+            "Automata Step Shader",
+            include_str!("automata.wgsl"),
+            &[("cs_step", ShaderStage::Compute)],
+        );
+        let step_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Automata Step Pipeline Layout"),
+            bind_group_layouts: &[&step_layout],
+            push_constant_ranges: &[],
+        });
+        let step_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Automata Step Pipeline"),
+            layout: Some(&step_pipeline_layout),
+            module: &step_shader,
+            entry_point: "cs_step",
+        });
+
+        // Render: show the current generation on screen
+        let display_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Automata Display Bind Group Layout"),
+            entries: &[cells_layout_entry],
+        });
+        let display_bind_groups = [0, 1].map(|i| {
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Automata Display Bind Group"),
+                layout: &display_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&views[i]),
+                }],
+            })
+        });
+        let push_constants_supported = device.features().contains(wgpu::Features::PUSH_CONSTANTS)
+            && device.limits().max_push_constant_size as usize >= std::mem::size_of::<CellColors>();
+
+        let (colors_binding, display_pipeline_layout) = if push_constants_supported {
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Automata Display Pipeline Layout"),
+                bind_group_layouts: &[&display_layout],
+                push_constant_ranges: &[wgpu::PushConstantRange {
+                    stages: wgpu::ShaderStages::FRAGMENT,
+                    range: 0..std::mem::size_of::<CellColors>() as u32,
+                }],
+            });
+
+            (ColorsBinding::PushConstants, layout)
+        } else {
+            log::info!(
+                "Push constants are not supported, using a uniform buffer for the automata colors"
+            );
+
+            let colors_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Automata Colors Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+            let buffer = memory.create_buffer_init(
+                device,
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Automata Colors Buffer"),
+                    contents: bytemuck::bytes_of(&PALETTES[0]),
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                },
+            );
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Automata Colors Bind Group"),
+                layout: &colors_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Automata Display Pipeline Layout"),
+                bind_group_layouts: &[&display_layout, &colors_layout],
+                push_constant_ranges: &[],
+            });
+
+            (ColorsBinding::Uniform { buffer, bind_group }, layout)
+        };
+        let display_pipeline = create_display_pipeline(
+            device,
+            &display_pipeline_layout,
+            &colors_binding,
+            output_format,
+            sample_count,
+        );
+
+        Self {
+            width,
+            height,
+            textures,
+            step_pipeline,
+            step_bind_groups,
+            display_pipeline_layout,
+            display_pipeline,
+            display_bind_groups,
+            colors_binding,
+            palette: 0,
+            colors: Animation::still(PALETTES[0]),
+            current: 0,
+            step_interval: Duration::from_secs_f32(1.0 / steps_per_second),
+            accumulator: Duration::ZERO,
+            pending_steps: 0,
+        }
+    }
+
+    /// The display pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_display_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.display_pipeline = create_display_pipeline(
+            device,
+            &self.display_pipeline_layout,
+            &self.colors_binding,
+            output_format,
+            sample_count,
+        );
+    }
+
+    /// Start blending to the next set of cell colors
+    pub fn next_palette(&mut self) {
+        self.palette = (self.palette + 1) % PALETTES.len();
+        self.colors = Animation::new(
+            self.colors.value(),
+            PALETTES[self.palette],
+            PALETTE_TRANSITION,
+            easing::ease_in_out_cubic,
+        );
+    }
+
+    /// False while blending between two palettes
+    pub fn is_palette_settled(&self) -> bool {
+        self.colors.is_finished()
+    }
+
+    /// Advance the palette transition, if there's one running
+    pub fn animate(&mut self, queue: &wgpu::Queue, dt: Duration) {
+        if self.colors.is_finished() {
+            return;
+        }
+        self.colors.update(dt);
+
+        // Push constants are sent again on every draw, only the uniform needs updating
+        if let ColorsBinding::Uniform { buffer, .. } = &self.colors_binding {
+            queue.write_buffer(buffer, 0, bytemuck::bytes_of(&self.colors.value()));
+        }
+    }
+
+    /// Fill the current generation with random cells, about 1 in 4 alive
+    pub fn randomize(&self, queue: &wgpu::Queue, rng: &mut Rng) {
+        let cells: Vec<u32> = (0..self.width * self.height)
+            .map(|_| rng.chance(0.25) as u32)
+            .collect();
+
+        queue.write_texture(
+            self.textures[self.current].as_image_copy(),
+            bytemuck::cast_slice(&cells),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(self.width * 4),
+                rows_per_image: Some(self.height),
+            },
+            wgpu::Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Advance the simulation clock, the steps are run on the next encode()
+    pub fn update(&mut self, dt: Duration) {
+        self.accumulator += dt;
+        while self.accumulator >= self.step_interval {
+            self.accumulator -= self.step_interval;
+            self.pending_steps += 1;
+        }
+    }
+
+    /// Record a compute pass running all the pending steps
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if self.pending_steps == 0 {
+            return;
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Automata Compute Pass"),
+        });
+        compute_pass.set_pipeline(&self.step_pipeline);
+
+        // Round up so that the cells at the edges get a workgroup too
+        let workgroups_x = self.width.div_ceil(WORKGROUP_SIZE);
+        let workgroups_y = self.height.div_ceil(WORKGROUP_SIZE);
+
+        for _ in 0..self.pending_steps {
+            compute_pass.insert_debug_marker("Automata Step");
+            compute_pass.set_bind_group(0, &self.step_bind_groups[self.current], &[]);
+            compute_pass.dispatch_workgroups(workgroups_x, workgroups_y, 1);
+            self.current = 1 - self.current;
+        }
+
+        self.pending_steps = 0;
+    }
+
+    /// Draw the current generation over the whole render target
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.display_pipeline);
+        render_pass.set_bind_group(0, &self.display_bind_groups[self.current], &[]);
+        match &self.colors_binding {
+            ColorsBinding::PushConstants => render_pass.set_push_constants(
+                wgpu::ShaderStages::FRAGMENT,
+                0,
+                bytemuck::bytes_of(&self.colors.value()),
+            ),
+            ColorsBinding::Uniform { bind_group, .. } => {
+                render_pass.set_bind_group(1, bind_group, &[])
+            }
+        }
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_display_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    colors_binding: &ColorsBinding,
+    output_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    // The shader is the same either way, only the declaration of 'colors' changes
+    let colors_declaration = match colors_binding {
+        ColorsBinding::PushConstants => "var<push_constant> colors: CellColors;",
+        ColorsBinding::Uniform { .. } => "@group(1) @binding(0) var<uniform> colors: CellColors;",
+    };
+
+    fullscreen::create_fullscreen_pipeline(
+        device,
+        "Automata Display Pipeline",
+        Some(layout),
+        output_format,
+        sample_count,
+        &format!(
+            "{colors_declaration}\n{}",
+            include_str!("automata_display.wgsl")
+        ),
+        "fs_automata",
+    )
+}
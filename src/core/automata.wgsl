@@ -0,0 +1,38 @@
+// Conway's Game of Life: each cell is 1 (alive) or 0 (dead).
+// Every step reads the current generation and writes the next one
+// into the other texture, then the two textures swap roles.
+
+@group(0) @binding(0) var current: texture_2d<u32>;
+@group(0) @binding(1) var next: texture_storage_2d<r32uint, write>;
+
+@compute @workgroup_size(8, 8)
+fn cs_step(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = vec2<i32>(textureDimensions(current));
+    let cell = vec2<i32>(id.xy);
+
+    // The dispatch is rounded up to whole workgroups,
+    // so some invocations fall outside of the grid
+    if (cell.x >= size.x || cell.y >= size.y) {
+        return;
+    }
+
+    var neighbours = 0u;
+    for (var dy = -1; dy <= 1; dy++) {
+        for (var dx = -1; dx <= 1; dx++) {
+            if (dx == 0 && dy == 0) {
+                continue;
+            }
+            // The grid wraps around at the edges
+            let neighbour = (cell + vec2<i32>(dx, dy) + size) % size;
+            neighbours += textureLoad(current, neighbour, 0).r;
+        }
+    }
+
+    let alive = textureLoad(current, cell, 0).r == 1u;
+    var state = 0u;
+    if (neighbours == 3u || (alive && neighbours == 2u)) {
+        state = 1u;
+    }
+
+    textureStore(next, cell, vec4<u32>(state, 0u, 0u, 0u));
+}
@@ -0,0 +1,22 @@
+// Appended to fullscreen.wgsl: shows the current generation of the automata.
+// 'colors' is declared by automata.rs, either as push constants
+// or as a uniform buffer depending on what the device supports.
+
+struct CellColors {
+    alive: vec4<f32>,
+    dead: vec4<f32>,
+};
+
+@group(0) @binding(0) var cells: texture_2d<u32>;
+
+@fragment
+fn fs_automata(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let size = textureDimensions(cells);
+    // Integer textures can't be filtered, so we pick the nearest cell ourselves
+    let cell = min(vec2<u32>(in.uv * vec2<f32>(size)), size - 1u);
+
+    if (textureLoad(cells, cell, 0).r == 1u) {
+        return colors.alive;
+    }
+    return colors.dead;
+}
@@ -0,0 +1,530 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use winit::event::{ElementState, MouseScrollDelta, VirtualKeyCode};
+
+use super::easing::Lerp;
+use super::memory::{MemoryRegistry, Tracked};
+
+/// How far dragging the cursor by a pixel orbits the camera, in radians
+pub const ORBIT_SPEED: f32 = 0.005;
+
+// How much closer a line of scrolling zooms in, as a fraction of the distance
+const ZOOM_PER_LINE: f32 = 0.1;
+// Touchpads scroll by pixels rather than lines
+const PIXELS_PER_LINE: f64 = 40.0;
+// The eye never gets closer to the target than this, let alone through it,
+// and never so far that the target gets clipped by the far plane
+const MIN_DISTANCE: f32 = 0.25;
+const MAX_DISTANCE: f32 = 50.0;
+// Looking straight down or up, the view direction would line up with 'up'
+const MAX_PITCH: f32 = 1.5;
+
+/// A perspective camera looking from 'eye' at 'target'.
+/// It's saved without its aspect ratio, which is the one of the window it's
+/// loaded in: a deserialized camera has an aspect of 0 until it's set.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Camera {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    // Width over height of the render target
+    #[serde(skip)]
+    pub aspect: f32,
+    // Vertical field of view, in degrees
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    /// Two units in front of the origin, looking at it with Y up
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            eye: [0.0, 0.0, 2.0],
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            aspect,
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+
+    /// The matrix taking world positions to clip space, column major like
+    /// WGSL's mat4x4. Right-handed, with a depth range of [0, 1] as wgpu expects.
+    pub fn build_view_projection_matrix(&self) -> [[f32; 4]; 4] {
+        multiply(&self.projection_matrix(), &self.view_matrix())
+    }
+
+    /// How far the eye is from the target
+    pub fn distance(&self) -> f32 {
+        let offset = sub(self.eye, self.target);
+        dot(offset, offset).sqrt()
+    }
+
+    /// Turn the eye around the target, by 'yaw' radians around the Y axis and
+    /// 'pitch' radians up or down, staying at the same distance.
+    /// The pitch stops short of the poles.
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let offset = sub(self.eye, self.target);
+        let distance = dot(offset, offset).sqrt();
+        let yaw = offset[0].atan2(offset[2]) + yaw;
+        let pitch = ((offset[1] / distance).asin() + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+
+        let direction = [
+            pitch.cos() * yaw.sin(),
+            pitch.sin(),
+            pitch.cos() * yaw.cos(),
+        ];
+        self.eye = add(self.target, scale(direction, distance));
+    }
+
+    /// Move the eye toward the target when scrolling up, away when scrolling
+    /// down. The distance is clamped to [MIN_DISTANCE, MAX_DISTANCE].
+    pub fn zoom(&mut self, delta: &MouseScrollDelta) {
+        let lines = scroll_lines(delta);
+        let offset = sub(self.eye, self.target);
+        let distance = dot(offset, offset).sqrt();
+        // Each line covers the same fraction of the way, so zooming slows
+        // down close to the target instead of overshooting it
+        let new_distance =
+            (distance * (1.0 - ZOOM_PER_LINE).powf(lines)).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        self.eye = add(self.target, scale(offset, new_distance / distance));
+    }
+
+    /// The same camera, looking at a sphere of 'radius' around 'center' from
+    /// the same direction, and just far enough for all of it to be in view
+    pub fn framing(&self, center: [f32; 3], radius: f32) -> Self {
+        // The narrowest of the vertical and horizontal fields of view
+        let half_fovy = self.fovy.to_radians() * 0.5;
+        let half_fovx = (half_fovy.tan() * self.aspect).atan();
+        let half_fov = half_fovy.min(half_fovx);
+        // The lines from the eye that graze the sphere are half_fov
+        // away from the one to its center
+        let distance = radius / half_fov.sin();
+
+        let direction = normalize(sub(self.eye, self.target));
+        Self {
+            eye: add(center, scale(direction, distance)),
+            target: center,
+            ..*self
+        }
+    }
+
+    /// The directions of the right and the top of the screen, in world space.
+    /// 'up' is only the same as the camera's when it looks straight ahead.
+    pub fn right_and_up(&self) -> ([f32; 3], [f32; 3]) {
+        let forward = normalize(sub(self.target, self.eye));
+        let right = normalize(cross(forward, self.up));
+
+        (right, cross(right, forward))
+    }
+
+    fn view_matrix(&self) -> [[f32; 4]; 4] {
+        let forward = normalize(sub(self.target, self.eye));
+        let (right, up) = self.right_and_up();
+
+        // The camera looks down its -Z axis
+        [
+            [right[0], up[0], -forward[0], 0.0],
+            [right[1], up[1], -forward[1], 0.0],
+            [right[2], up[2], -forward[2], 0.0],
+            [
+                -dot(right, self.eye),
+                -dot(up, self.eye),
+                dot(forward, self.eye),
+                1.0,
+            ],
+        ]
+    }
+
+    fn projection_matrix(&self) -> [[f32; 4]; 4] {
+        let y_scale = 1.0 / (self.fovy.to_radians() * 0.5).tan();
+        let x_scale = y_scale / self.aspect;
+        // Maps znear to 0 and zfar to 1
+        let z_range = self.zfar / (self.znear - self.zfar);
+
+        [
+            [x_scale, 0.0, 0.0, 0.0],
+            [0.0, y_scale, 0.0, 0.0],
+            [0.0, 0.0, z_range, -1.0],
+            [0.0, 0.0, z_range * self.znear, 0.0],
+        ]
+    }
+}
+
+/// How many lines 'delta' scrolls by, up being positive
+pub fn scroll_lines(delta: &MouseScrollDelta) -> f32 {
+    match delta {
+        MouseScrollDelta::LineDelta(_, lines) => *lines,
+        MouseScrollDelta::PixelDelta(pixels) => (pixels.y / PIXELS_PER_LINE) as f32,
+    }
+}
+
+/// Moves the eye and the target in straight lines, which is good enough for
+/// short transitions between viewpoints
+impl Lerp for Camera {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        Self {
+            eye: self.eye.lerp(&other.eye, t),
+            target: self.target.lerp(&other.target, t),
+            up: self.up.lerp(&other.up, t),
+            aspect: self.aspect.lerp(&other.aspect, t),
+            fovy: self.fovy.lerp(&other.fovy, t),
+            znear: self.znear.lerp(&other.znear, t),
+            zfar: self.zfar.lerp(&other.zfar, t),
+        }
+    }
+}
+
+/// Must match the `CameraUniforms` struct in shader.wgsl.
+/// A mat4x4 is 64 bytes with a 16 byte alignment, so there's no padding.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniforms {
+    view_projection: [[f32; 4]; 4],
+}
+
+impl CameraUniforms {
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            view_projection: camera.build_view_projection_matrix(),
+        }
+    }
+}
+
+/// The bind group layout, uniform buffer and bind group of the camera,
+/// bound at group 2 of the mesh pipeline
+pub fn create_camera_bindings(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    camera: &Camera,
+) -> (
+    wgpu::BindGroupLayout,
+    Tracked<wgpu::Buffer>,
+    wgpu::BindGroup,
+) {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Camera Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<CameraUniforms>() as wgpu::BufferAddress
+                ),
+            },
+            count: None,
+        }],
+    });
+
+    // Written again every frame
+    let buffer = memory.create_buffer_init(
+        device,
+        &wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Uniforms Buffer"),
+            contents: bytemuck::bytes_of(&CameraUniforms::new(camera)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        },
+    );
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Camera Bind Group"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+
+    (bind_group_layout, buffer, bind_group)
+}
+
+/// Flies the camera around while keys are held: the up and down arrows move
+/// it forward and back, left and right strafe, and Space and Left Shift move
+/// it up and down. The target moves along, so the camera keeps looking in
+/// the same direction.
+/// A gamepad adds its sticks and triggers on top, which also orbit it.
+#[derive(Debug, Default)]
+pub struct CameraController {
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    // Analog movement along forward, right and up, from -1 to 1
+    analog_move: [f32; 3],
+    // Analog orbiting to the right and up, from -1 to 1
+    analog_look: [f32; 2],
+}
+
+impl CameraController {
+    // In units per second, so that the speed doesn't depend on the frame rate
+    const SPEED: f32 = 1.5;
+    // In radians per second, with the stick all the way
+    const LOOK_SPEED: f32 = 2.0;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start or stop moving when one of the movement keys is pressed or
+    /// released. Returns false for any other key.
+    pub fn process_key(&mut self, key: VirtualKeyCode, state: ElementState) -> bool {
+        let pressed = state == ElementState::Pressed;
+        let flag = match key {
+            VirtualKeyCode::Up => &mut self.forward,
+            VirtualKeyCode::Down => &mut self.backward,
+            VirtualKeyCode::Left => &mut self.left,
+            VirtualKeyCode::Right => &mut self.right,
+            VirtualKeyCode::Space => &mut self.up,
+            VirtualKeyCode::LShift => &mut self.down,
+            _ => return false,
+        };
+        *flag = pressed;
+        true
+    }
+
+    /// The current position of the sticks and triggers, after the deadzone:
+    /// 'movement' along forward, right and up, and 'look' to orbit right and up
+    #[cfg(feature = "gamepad")]
+    pub fn set_analog(&mut self, movement: [f32; 3], look: [f32; 2]) {
+        self.analog_move = movement;
+        self.analog_look = look;
+    }
+
+    /// Whether a movement key is held or a stick pushed, moving the camera
+    /// every frame
+    pub fn is_moving(&self) -> bool {
+        self.forward
+            || self.backward
+            || self.left
+            || self.right
+            || self.up
+            || self.down
+            || self.analog_move != [0.0; 3]
+            || self.analog_look != [0.0; 2]
+    }
+
+    /// Move the camera by as much as it travels in 'dt'
+    pub fn update_camera(&self, camera: &mut Camera, dt: Duration) {
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+
+        let forward = normalize(sub(camera.target, camera.eye));
+        let right = normalize(cross(forward, camera.up));
+        let up = normalize(camera.up);
+
+        let [analog_forward, analog_right, analog_up] = self.analog_move;
+        let distance = Self::SPEED * dt.as_secs_f32();
+        let offset = [
+            (forward, axis(self.forward, self.backward) + analog_forward),
+            (right, axis(self.right, self.left) + analog_right),
+            (up, axis(self.up, self.down) + analog_up),
+        ]
+        .into_iter()
+        .fold([0.0; 3], |offset, (direction, amount)| {
+            add(offset, scale(direction, amount.clamp(-1.0, 1.0) * distance))
+        });
+
+        camera.eye = add(camera.eye, offset);
+        camera.target = add(camera.target, offset);
+
+        // Pushing the stick orbits like dragging the mouse the same way
+        let [look_right, look_up] = self.analog_look;
+        if look_right != 0.0 || look_up != 0.0 {
+            let angle = Self::LOOK_SPEED * dt.as_secs_f32();
+            camera.orbit(-look_right * angle, -look_up * angle);
+        }
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn scale(a: [f32; 3], factor: f32) -> [f32; 3] {
+    [a[0] * factor, a[1] * factor, a[2] * factor]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    [a[0] / length, a[1] / length, a[2] / length]
+}
+
+/// 'a' * 'b', both column major
+fn multiply(a: &[[f32; 4]; 4], b: &[[f32; 4]; 4]) -> [[f32; 4]; 4] {
+    let mut result = [[0.0; 4]; 4];
+    for (column, b_column) in result.iter_mut().zip(b) {
+        for (row, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EPSILON: f32 = 1e-5;
+
+    // Where 'point' lands in normalized device coordinates
+    fn project(camera: &Camera, point: [f32; 3]) -> [f32; 3] {
+        let matrix = camera.build_view_projection_matrix();
+        let clip: [f32; 4] = std::array::from_fn(|row| {
+            (0..3).map(|k| matrix[k][row] * point[k]).sum::<f32>() + matrix[3][row]
+        });
+        [clip[0] / clip[3], clip[1] / clip[3], clip[2] / clip[3]]
+    }
+
+    #[test]
+    fn target_projects_to_the_center() {
+        let mut camera = Camera::new(16.0 / 9.0);
+        camera.eye = [3.0, 2.0, -4.0];
+        camera.target = [0.5, -1.0, 1.0];
+
+        let [x, y, _] = project(&camera, camera.target);
+        assert!(x.abs() < EPSILON && y.abs() < EPSILON, "({x}, {y})");
+    }
+
+    #[test]
+    fn near_and_far_planes_map_to_depth_0_and_1() {
+        // Looking down -Z from z = 2
+        let camera = Camera::new(1.0);
+
+        let near = project(&camera, [0.0, 0.0, 2.0 - camera.znear])[2];
+        let far = project(&camera, [0.0, 0.0, 2.0 - camera.zfar])[2];
+        assert!(near.abs() < EPSILON, "near depth {near}");
+        assert!((far - 1.0).abs() < EPSILON, "far depth {far}");
+    }
+
+    #[test]
+    fn orbit_keeps_the_distance_and_clamps_the_pitch() {
+        let mut camera = Camera::new(1.0);
+        let start = camera.distance();
+
+        camera.orbit(1.0, 0.3);
+        assert!((camera.distance() - start).abs() < EPSILON);
+
+        // Way past the pole
+        camera.orbit(0.0, 10.0);
+        assert!((camera.distance() - start).abs() < EPSILON);
+        let pitch = (camera.eye[1] / start).asin();
+        assert!((pitch - MAX_PITCH).abs() < EPSILON, "pitch {pitch}");
+
+        camera.orbit(0.0, -20.0);
+        let pitch = (camera.eye[1] / start).asin();
+        assert!((pitch + MAX_PITCH).abs() < EPSILON, "pitch {pitch}");
+    }
+
+    #[test]
+    fn analog_input_adds_up_with_the_keys() {
+        let mut controller = CameraController::new();
+        let dt = Duration::from_secs(1);
+
+        // Half way forward on the stick, looking down -Z
+        controller.analog_move = [0.5, 0.0, 0.0];
+        assert!(controller.is_moving());
+        let mut camera = Camera::new(1.0);
+        controller.update_camera(&mut camera, dt);
+        assert!((camera.eye[2] - (2.0 - CameraController::SPEED * 0.5)).abs() < EPSILON);
+
+        // The key and the stick together don't go faster than the key alone
+        controller.process_key(VirtualKeyCode::Up, ElementState::Pressed);
+        let mut camera = Camera::new(1.0);
+        controller.update_camera(&mut camera, dt);
+        assert!((camera.eye[2] - (2.0 - CameraController::SPEED)).abs() < EPSILON);
+
+        // Looking around orbits the target
+        let mut controller = CameraController::new();
+        controller.analog_look = [1.0, 0.0];
+        let mut camera = Camera::new(1.0);
+        controller.update_camera(&mut camera, Duration::from_millis(100));
+        assert_eq!(camera.target, [0.0; 3]);
+        assert!((camera.distance() - 2.0).abs() < EPSILON);
+        assert!(camera.eye[0] < 0.0, "{:?}", camera.eye);
+    }
+
+    #[test]
+    fn zoom_stays_within_the_distance_limits() {
+        let mut camera = Camera::new(1.0);
+
+        camera.zoom(&MouseScrollDelta::LineDelta(0.0, 1000.0));
+        assert!((camera.distance() - MIN_DISTANCE).abs() < EPSILON);
+
+        camera.zoom(&MouseScrollDelta::LineDelta(0.0, -1000.0));
+        assert!((camera.distance() - MAX_DISTANCE).abs() < 1e-3);
+
+        // Touchpads scroll by pixels
+        camera.zoom(&MouseScrollDelta::PixelDelta(
+            winit::dpi::PhysicalPosition::new(0.0, 1e6),
+        ));
+        assert!((camera.distance() - MIN_DISTANCE).abs() < EPSILON);
+    }
+
+    #[test]
+    fn framing_fits_the_sphere_in_the_narrowest_field_of_view() {
+        // Narrower than tall, so the horizontal field of view is the limit
+        let mut camera = Camera::new(0.5);
+        camera.eye = [0.0, 1.0, 4.0];
+        let (center, radius) = ([1.0, 0.0, -1.0], 2.0);
+
+        let framed = camera.framing(center, radius);
+        assert_eq!(framed.target, center);
+        let [x, y, _] = project(&framed, center);
+        assert!(x.abs() < EPSILON && y.abs() < EPSILON, "({x}, {y})");
+
+        // Seen from the same direction
+        let before = normalize(sub(camera.eye, camera.target));
+        let after = normalize(sub(framed.eye, framed.target));
+        assert!((dot(before, after) - 1.0).abs() < EPSILON);
+
+        // The sideways tangent point sits right on the edge of the screen
+        let right = normalize(cross(scale(after, -1.0), framed.up));
+        let half_fovx = ((framed.fovy.to_radians() * 0.5).tan() * framed.aspect).atan();
+        let tangent = add(
+            center,
+            add(
+                scale(right, radius * half_fovx.cos()),
+                scale(after, radius * half_fovx.sin()),
+            ),
+        );
+        let [x, _, _] = project(&framed, tangent);
+        assert!((x - 1.0).abs() < 1e-4, "x = {x}");
+    }
+
+    #[test]
+    fn saved_cameras_load_without_their_aspect_ratio() {
+        let mut camera = Camera::new(16.0 / 9.0);
+        camera.eye = [1.0, 2.0, 3.0];
+        camera.fovy = 30.0;
+
+        let saved = toml::to_string(&camera).unwrap();
+        assert!(!saved.contains("aspect"), "{saved}");
+        let loaded: Camera = toml::from_str(&saved).unwrap();
+        assert_eq!(loaded.eye, camera.eye);
+        assert_eq!(loaded.target, camera.target);
+        assert_eq!(loaded.fovy, camera.fovy);
+        assert_eq!(loaded.aspect, 0.0);
+    }
+}
@@ -0,0 +1,497 @@
+use std::fmt;
+use std::path::Path;
+
+/// ASTC blocks in the order of their VkFormat values, which go by pairs of
+/// UNORM and SRGB from ASTC_4x4_UNORM_BLOCK
+const ASTC_BLOCKS: [wgpu::AstcBlock; 14] = [
+    wgpu::AstcBlock::B4x4,
+    wgpu::AstcBlock::B5x4,
+    wgpu::AstcBlock::B5x5,
+    wgpu::AstcBlock::B6x5,
+    wgpu::AstcBlock::B6x6,
+    wgpu::AstcBlock::B8x5,
+    wgpu::AstcBlock::B8x6,
+    wgpu::AstcBlock::B8x8,
+    wgpu::AstcBlock::B10x5,
+    wgpu::AstcBlock::B10x6,
+    wgpu::AstcBlock::B10x8,
+    wgpu::AstcBlock::B10x10,
+    wgpu::AstcBlock::B12x10,
+    wgpu::AstcBlock::B12x12,
+];
+
+#[derive(Debug)]
+pub enum CompressedError {
+    Io(std::io::Error),
+    Dds(ddsfile::Error),
+    Ktx2(ktx2::ParseError),
+    /// Neither a .dds nor a .ktx2 file
+    UnknownExtension,
+    /// Only the block-compressed formats wgpu knows are loaded, the others
+    /// (e.g. plain RGBA8) are better stored as PNG
+    UnsupportedFormat(String),
+    /// The file is well formed but can't be a wgpu texture as it is
+    Invalid(String),
+}
+
+impl fmt::Display for CompressedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Failed to read the file: {e}"),
+            Self::Dds(e) => write!(f, "Not a valid DDS file: {e}"),
+            Self::Ktx2(e) => write!(f, "Not a valid KTX2 file: {e}"),
+            Self::UnknownExtension => write!(f, "Expected a .dds or a .ktx2 file"),
+            Self::UnsupportedFormat(format) => {
+                write!(f, "{format} isn't a supported block-compressed format")
+            }
+            Self::Invalid(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CompressedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Dds(e) => Some(e),
+            Self::Ktx2(e) => Some(e),
+            Self::UnknownExtension | Self::UnsupportedFormat(_) | Self::Invalid(_) => None,
+        }
+    }
+}
+
+/// Whether the file at 'path' is one `CompressedImage::open()` reads
+pub fn is_compressed_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("dds") || extension.eq_ignore_ascii_case("ktx2")
+        })
+}
+
+/// Block-compressed texels, ready to be uploaded as they are: the GPU
+/// decodes the blocks when sampling, so they stay compressed in VRAM too
+#[derive(Debug)]
+pub struct CompressedImage {
+    pub format: wgpu::TextureFormat,
+    /// Of the first mip level, in pixels
+    pub size: (u32, u32),
+    /// The mip levels from the largest one, each made of rows of blocks
+    pub levels: Vec<Vec<u8>>,
+}
+
+impl CompressedImage {
+    /// Read a DDS or a KTX2 file, depending on its extension
+    pub fn open(path: &Path) -> Result<Self, CompressedError> {
+        let bytes = std::fs::read(path).map_err(CompressedError::Io)?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("dds") => Self::from_dds(&bytes),
+            Some("ktx2") => Self::from_ktx2(&bytes),
+            _ => Err(CompressedError::UnknownExtension),
+        }
+    }
+
+    /// The first image of the file: the other layers of arrays and cube maps
+    /// are ignored
+    pub fn from_dds(bytes: &[u8]) -> Result<Self, CompressedError> {
+        let dds = ddsfile::Dds::read(bytes).map_err(CompressedError::Dds)?;
+        let format = match (dds.get_dxgi_format(), dds.get_d3d_format()) {
+            (Some(format), _) => dxgi_format(format)?,
+            (None, Some(format)) => d3d_format(format)?,
+            (None, None) => return Err(CompressedError::UnsupportedFormat("Unknown".to_string())),
+        };
+        let size = (dds.get_width(), dds.get_height());
+        check_size(format, size)?;
+
+        // The levels are stored one after the other, from the largest one
+        let mut data = dds.get_data(0).map_err(CompressedError::Dds)?;
+        let mut levels = Vec::new();
+        for level in 0..dds.get_num_mipmap_levels().max(1) {
+            let length = level_layout(format, size, level).byte_length();
+            if data.len() < length {
+                return Err(CompressedError::Invalid(format!(
+                    "Mip level {level} is cut short"
+                )));
+            }
+            let (level_data, rest) = data.split_at(length);
+            levels.push(level_data.to_vec());
+            data = rest;
+        }
+
+        Ok(Self {
+            format,
+            size,
+            levels,
+        })
+    }
+
+    /// The first image of the file, which can't be supercompressed: Basis
+    /// Universal and zstd need a transcoder, which defeats the purpose of
+    /// loading the blocks as they are
+    pub fn from_ktx2(bytes: &[u8]) -> Result<Self, CompressedError> {
+        let reader = ktx2::Reader::new(bytes).map_err(CompressedError::Ktx2)?;
+        let header = reader.header();
+        if let Some(scheme) = header.supercompression_scheme {
+            return Err(CompressedError::Invalid(format!(
+                "{scheme:?} supercompression isn't supported"
+            )));
+        }
+        let format = match header.format {
+            Some(format) => ktx2_format(format)?,
+            None => {
+                return Err(CompressedError::UnsupportedFormat(
+                    "VK_FORMAT_UNDEFINED".to_string(),
+                ))
+            }
+        };
+        let size = (header.pixel_width, header.pixel_height.max(1));
+        check_size(format, size)?;
+
+        // Unlike DDS, every level has its own offset and length in the file,
+        // and holds all the layers and faces
+        let levels = reader
+            .levels()
+            .enumerate()
+            .map(|(level, data)| {
+                let length = level_layout(format, size, level as u32).byte_length();
+                data.data.get(..length).map(<[u8]>::to_vec).ok_or_else(|| {
+                    CompressedError::Invalid(format!("Mip level {level} is cut short"))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            format,
+            size,
+            levels,
+        })
+    }
+}
+
+/// How a mip level is laid out in blocks
+#[derive(Debug, PartialEq)]
+pub struct LevelLayout {
+    /// The size to copy, rounded up to whole blocks: a 2x2 level of a 4x4
+    /// block format still takes one full block
+    pub extent: wgpu::Extent3d,
+    /// Blocks per row times the bytes of a block. Writes from the CPU don't
+    /// need any padding, unlike the 256 bytes of buffer to texture copies.
+    pub bytes_per_row: u32,
+    /// Rows of blocks, not of pixels
+    pub rows: u32,
+}
+
+impl LevelLayout {
+    pub fn byte_length(&self) -> usize {
+        self.bytes_per_row as usize * self.rows as usize
+    }
+}
+
+/// The layout of mip 'level' of a 'size' image in 'format'
+pub fn level_layout(format: wgpu::TextureFormat, size: (u32, u32), level: u32) -> LevelLayout {
+    let (block_width, block_height) = format.block_dimensions();
+    let block_size = format
+        .block_size(None)
+        .expect("compressed formats have a single aspect");
+    let (width, height) = ((size.0 >> level).max(1), (size.1 >> level).max(1));
+    let (columns, rows) = (width.div_ceil(block_width), height.div_ceil(block_height));
+
+    LevelLayout {
+        extent: wgpu::Extent3d {
+            width: columns * block_width,
+            height: rows * block_height,
+            depth_or_array_layers: 1,
+        },
+        bytes_per_row: columns * block_size,
+        rows,
+    }
+}
+
+/// wgpu only creates block-compressed textures made of whole blocks
+fn check_size(
+    format: wgpu::TextureFormat,
+    (width, height): (u32, u32),
+) -> Result<(), CompressedError> {
+    let (block_width, block_height) = format.block_dimensions();
+    if width % block_width != 0 || height % block_height != 0 {
+        return Err(CompressedError::Invalid(format!(
+            "The {width}x{height} image isn't made of whole {block_width}x{block_height} blocks"
+        )));
+    }
+
+    Ok(())
+}
+
+fn dxgi_format(format: ddsfile::DxgiFormat) -> Result<wgpu::TextureFormat, CompressedError> {
+    use ddsfile::DxgiFormat as Dxgi;
+    use wgpu::TextureFormat as Wgpu;
+
+    let format = match format {
+        Dxgi::BC1_UNorm => Wgpu::Bc1RgbaUnorm,
+        Dxgi::BC1_UNorm_sRGB => Wgpu::Bc1RgbaUnormSrgb,
+        Dxgi::BC2_UNorm => Wgpu::Bc2RgbaUnorm,
+        Dxgi::BC2_UNorm_sRGB => Wgpu::Bc2RgbaUnormSrgb,
+        Dxgi::BC3_UNorm => Wgpu::Bc3RgbaUnorm,
+        Dxgi::BC3_UNorm_sRGB => Wgpu::Bc3RgbaUnormSrgb,
+        Dxgi::BC4_UNorm => Wgpu::Bc4RUnorm,
+        Dxgi::BC4_SNorm => Wgpu::Bc4RSnorm,
+        Dxgi::BC5_UNorm => Wgpu::Bc5RgUnorm,
+        Dxgi::BC5_SNorm => Wgpu::Bc5RgSnorm,
+        Dxgi::BC6H_UF16 => Wgpu::Bc6hRgbUfloat,
+        Dxgi::BC6H_SF16 => Wgpu::Bc6hRgbFloat,
+        Dxgi::BC7_UNorm => Wgpu::Bc7RgbaUnorm,
+        Dxgi::BC7_UNorm_sRGB => Wgpu::Bc7RgbaUnormSrgb,
+        other => return Err(CompressedError::UnsupportedFormat(format!("{other:?}"))),
+    };
+
+    Ok(format)
+}
+
+/// The older DDS files only tell the compression, not the color space:
+/// like the PNG files, they're taken to hold sRGB colors
+fn d3d_format(format: ddsfile::D3DFormat) -> Result<wgpu::TextureFormat, CompressedError> {
+    let format = match format {
+        ddsfile::D3DFormat::DXT1 => wgpu::TextureFormat::Bc1RgbaUnormSrgb,
+        ddsfile::D3DFormat::DXT3 => wgpu::TextureFormat::Bc2RgbaUnormSrgb,
+        ddsfile::D3DFormat::DXT5 => wgpu::TextureFormat::Bc3RgbaUnormSrgb,
+        other => return Err(CompressedError::UnsupportedFormat(format!("{other:?}"))),
+    };
+
+    Ok(format)
+}
+
+fn ktx2_format(format: ktx2::Format) -> Result<wgpu::TextureFormat, CompressedError> {
+    use ktx2::Format as Vk;
+    use wgpu::TextureFormat as Wgpu;
+
+    let astc_range = Vk::ASTC_4x4_UNORM_BLOCK.value()..=Vk::ASTC_12x12_SRGB_BLOCK.value();
+    if astc_range.contains(&format.value()) {
+        let offset = (format.value() - astc_range.start()) as usize;
+        let channel = if offset.is_multiple_of(2) {
+            wgpu::AstcChannel::Unorm
+        } else {
+            wgpu::AstcChannel::UnormSrgb
+        };
+        return Ok(Wgpu::Astc {
+            block: ASTC_BLOCKS[offset / 2],
+            channel,
+        });
+    }
+
+    let format = match format {
+        // The RGB variants decode with an opaque alpha, which RGBA does too
+        // for blocks without transparency
+        Vk::BC1_RGB_UNORM_BLOCK | Vk::BC1_RGBA_UNORM_BLOCK => Wgpu::Bc1RgbaUnorm,
+        Vk::BC1_RGB_SRGB_BLOCK | Vk::BC1_RGBA_SRGB_BLOCK => Wgpu::Bc1RgbaUnormSrgb,
+        Vk::BC2_UNORM_BLOCK => Wgpu::Bc2RgbaUnorm,
+        Vk::BC2_SRGB_BLOCK => Wgpu::Bc2RgbaUnormSrgb,
+        Vk::BC3_UNORM_BLOCK => Wgpu::Bc3RgbaUnorm,
+        Vk::BC3_SRGB_BLOCK => Wgpu::Bc3RgbaUnormSrgb,
+        Vk::BC4_UNORM_BLOCK => Wgpu::Bc4RUnorm,
+        Vk::BC4_SNORM_BLOCK => Wgpu::Bc4RSnorm,
+        Vk::BC5_UNORM_BLOCK => Wgpu::Bc5RgUnorm,
+        Vk::BC5_SNORM_BLOCK => Wgpu::Bc5RgSnorm,
+        Vk::BC6H_UFLOAT_BLOCK => Wgpu::Bc6hRgbUfloat,
+        Vk::BC6H_SFLOAT_BLOCK => Wgpu::Bc6hRgbFloat,
+        Vk::BC7_UNORM_BLOCK => Wgpu::Bc7RgbaUnorm,
+        Vk::BC7_SRGB_BLOCK => Wgpu::Bc7RgbaUnormSrgb,
+        Vk::ETC2_R8G8B8_UNORM_BLOCK => Wgpu::Etc2Rgb8Unorm,
+        Vk::ETC2_R8G8B8_SRGB_BLOCK => Wgpu::Etc2Rgb8UnormSrgb,
+        Vk::ETC2_R8G8B8A1_UNORM_BLOCK => Wgpu::Etc2Rgb8A1Unorm,
+        Vk::ETC2_R8G8B8A1_SRGB_BLOCK => Wgpu::Etc2Rgb8A1UnormSrgb,
+        Vk::ETC2_R8G8B8A8_UNORM_BLOCK => Wgpu::Etc2Rgba8Unorm,
+        Vk::ETC2_R8G8B8A8_SRGB_BLOCK => Wgpu::Etc2Rgba8UnormSrgb,
+        Vk::EAC_R11_UNORM_BLOCK => Wgpu::EacR11Unorm,
+        Vk::EAC_R11_SNORM_BLOCK => Wgpu::EacR11Snorm,
+        Vk::EAC_R11G11_UNORM_BLOCK => Wgpu::EacRg11Unorm,
+        Vk::EAC_R11G11_SNORM_BLOCK => Wgpu::EacRg11Snorm,
+        other => return Err(CompressedError::UnsupportedFormat(format!("{other:?}"))),
+    };
+
+    Ok(format)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A KTX2 file without supercompression, key/values or data format
+    /// descriptor blocks
+    fn ktx2_file(format: ktx2::Format, size: (u32, u32), levels: &[&[u8]]) -> Vec<u8> {
+        const HEADER_LENGTH: usize = 80;
+        const LEVEL_INDEX_LENGTH: usize = 24;
+        let dfd_offset = HEADER_LENGTH + LEVEL_INDEX_LENGTH * levels.len();
+        // Just the total length of the descriptor, which is itself
+        let data_offset = dfd_offset + 4;
+
+        let mut file = b"\xABKTX 20\xBB\r\n\x1A\n".to_vec();
+        for value in [
+            format.value(),
+            1,
+            size.0,
+            size.1,
+            0,
+            0,
+            1,
+            levels.len() as u32,
+            0,
+        ] {
+            file.extend(value.to_le_bytes());
+        }
+        for value in [dfd_offset as u32, 4, 0, 0] {
+            file.extend(value.to_le_bytes());
+        }
+        file.extend([0; 16]);
+
+        let mut offset = data_offset;
+        for level in levels {
+            for value in [offset, level.len(), level.len()] {
+                file.extend((value as u64).to_le_bytes());
+            }
+            offset += level.len();
+        }
+        file.extend(4u32.to_le_bytes());
+        for level in levels {
+            file.extend(*level);
+        }
+        // The reader wants the empty sections to start inside of the file
+        file.push(0);
+
+        file
+    }
+
+    #[test]
+    fn levels_are_rounded_up_to_whole_blocks() {
+        // 8 bytes per 4x4 block
+        let format = wgpu::TextureFormat::Bc1RgbaUnorm;
+        let layouts: Vec<_> = (0..4)
+            .map(|level| level_layout(format, (12, 8), level))
+            .collect();
+        assert_eq!(
+            layouts
+                .iter()
+                .map(|layout| (layout.extent.width, layout.extent.height))
+                .collect::<Vec<_>>(),
+            [(12, 8), (8, 4), (4, 4), (4, 4)]
+        );
+        assert_eq!(
+            layouts
+                .iter()
+                .map(LevelLayout::byte_length)
+                .collect::<Vec<_>>(),
+            [48, 16, 8, 8]
+        );
+        assert_eq!(layouts[0].bytes_per_row, 24);
+        assert_eq!(layouts[0].rows, 2);
+
+        // 16 bytes per 6x6 block
+        let format = wgpu::TextureFormat::Astc {
+            block: wgpu::AstcBlock::B6x6,
+            channel: wgpu::AstcChannel::Unorm,
+        };
+        assert_eq!(level_layout(format, (12, 12), 1).byte_length(), 16);
+    }
+
+    #[test]
+    fn dds_levels_are_split() {
+        let mut dds = ddsfile::Dds::new_dxgi(ddsfile::NewDxgiParams {
+            height: 8,
+            width: 8,
+            depth: None,
+            format: ddsfile::DxgiFormat::BC7_UNorm_sRGB,
+            mipmap_levels: Some(4),
+            array_layers: None,
+            caps2: None,
+            is_cubemap: false,
+            resource_dimension: ddsfile::D3D10ResourceDimension::Texture2D,
+            alpha_mode: ddsfile::AlphaMode::Unknown,
+        })
+        .unwrap();
+        // Level 0 is 2x2 blocks of 16 bytes, then 1 block per level
+        let data = dds.get_mut_data(0).unwrap();
+        assert_eq!(data.len(), 64 + 3 * 16);
+        data[64] = 1;
+        let mut bytes = Vec::new();
+        dds.write(&mut bytes).unwrap();
+
+        let image = CompressedImage::from_dds(&bytes).unwrap();
+        assert_eq!(image.format, wgpu::TextureFormat::Bc7RgbaUnormSrgb);
+        assert_eq!(image.size, (8, 8));
+        assert_eq!(
+            image.levels.iter().map(Vec::len).collect::<Vec<_>>(),
+            [64, 16, 16, 16]
+        );
+        assert_eq!(image.levels[1][0], 1);
+
+        assert!(matches!(
+            CompressedImage::from_dds(&bytes[..bytes.len() - 1]),
+            Err(CompressedError::Dds(ddsfile::Error::OutOfBounds))
+        ));
+        assert!(matches!(
+            CompressedImage::from_dds(b"not a dds"),
+            Err(CompressedError::Dds(_))
+        ));
+    }
+
+    #[test]
+    fn ktx2_astc_levels_are_read() {
+        let (level_0, level_1) = ([1; 64], [2; 16]);
+        let bytes = ktx2_file(
+            ktx2::Format::ASTC_4x4_SRGB_BLOCK,
+            (8, 8),
+            &[&level_0, &level_1],
+        );
+
+        let image = CompressedImage::from_ktx2(&bytes).unwrap();
+        assert_eq!(
+            image.format,
+            wgpu::TextureFormat::Astc {
+                block: wgpu::AstcBlock::B4x4,
+                channel: wgpu::AstcChannel::UnormSrgb,
+            }
+        );
+        assert_eq!(image.size, (8, 8));
+        assert_eq!(image.levels, [level_0.to_vec(), level_1.to_vec()]);
+
+        let bytes = ktx2_file(ktx2::Format::ASTC_12x12_UNORM_BLOCK, (12, 12), &[&[0; 16]]);
+        let format = CompressedImage::from_ktx2(&bytes).unwrap().format;
+        assert_eq!(format.block_dimensions(), (12, 12));
+    }
+
+    #[test]
+    fn unusable_ktx2_files_are_rejected() {
+        // Not a compressed format
+        let bytes = ktx2_file(ktx2::Format::R8G8B8A8_SRGB, (4, 4), &[&[0; 64]]);
+        assert!(matches!(
+            CompressedImage::from_ktx2(&bytes),
+            Err(CompressedError::UnsupportedFormat(_))
+        ));
+
+        // Not a multiple of the 4x4 blocks
+        let bytes = ktx2_file(ktx2::Format::BC1_RGBA_UNORM_BLOCK, (6, 4), &[&[0; 16]]);
+        assert!(matches!(
+            CompressedImage::from_ktx2(&bytes),
+            Err(CompressedError::Invalid(_))
+        ));
+
+        // Two blocks are expected
+        let bytes = ktx2_file(ktx2::Format::BC1_RGBA_UNORM_BLOCK, (8, 4), &[&[0; 8]]);
+        assert!(matches!(
+            CompressedImage::from_ktx2(&bytes),
+            Err(CompressedError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn compressed_files_are_told_by_their_extension() {
+        assert!(is_compressed_path(Path::new("assets/bricks.dds")));
+        assert!(is_compressed_path(Path::new("bricks.KTX2")));
+        assert!(!is_compressed_path(Path::new("assets/icon.png")));
+        assert!(!is_compressed_path(Path::new("dds")));
+    }
+}
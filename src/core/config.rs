@@ -0,0 +1,1627 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::dynamic_resolution::DynamicResolution;
+use super::render_policy::RenderPolicy;
+use super::texture::SamplerDesc;
+use super::vertex::MAX_GRID_SIZE;
+use super::views::{self, Viewpoint};
+
+/// Config file read at startup, relative to the current directory
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// Environment variable that can point to a different config file
+const CONFIG_PATH_ENV_VAR: &str = "WEBGPU_101_CONFIG";
+
+/// Options used to set up the app
+#[derive(Debug, Clone)]
+pub struct AppConfig {
+    /// Name shown in the window title and used by the OS to identify the app
+    pub app_name: String,
+    /// PNG used as the window and taskbar icon
+    pub icon_path: Option<PathBuf>,
+    /// Image drawn on the quad, set with `--texture <path>`: a PNG, or a
+    /// block-compressed DDS or KTX2 file. When None, or when it fails to
+    /// load, the quad only shows its corner colors.
+    pub texture_path: Option<PathBuf>,
+    /// How the image on the quad is wrapped and filtered
+    pub texture_sampler: SamplerDesc,
+    /// Number of copies of the quad along each side of the grid they're
+    /// drawn in, up to MAX_GRID_SIZE. Also changed at runtime with + and -.
+    pub instance_grid_size: u32,
+    /// Initial size of the window. When None the OS picks one.
+    pub window_size: Option<winit::dpi::PhysicalSize<u32>>,
+    /// Index (as printed at startup) of the monitor the window opens on.
+    /// When None, or when the index is invalid, the primary monitor is used.
+    pub monitor_index: Option<usize>,
+    /// Index (as printed at startup) of a video mode of the monitor.
+    /// When set the window opens in exclusive fullscreen with that mode,
+    /// or in borderless fullscreen if the index is invalid.
+    pub video_mode_index: Option<usize>,
+    /// Keep the window hidden until a first frame has been presented,
+    /// to avoid flashing uninitialized contents on startup
+    pub hide_until_first_frame: bool,
+    /// Color the screen is cleared to every frame
+    pub clear_color: wgpu::Color,
+    /// Animate the green channel of the clear color, to see the window
+    /// redraw on its own. Also enabled with `--pulse-clear-color`.
+    pub pulse_clear_color: bool,
+    /// Drive the red and blue channels of the clear color with the cursor
+    /// position. Also enabled with `--mouse-clear-color`.
+    pub mouse_clear_color: bool,
+    /// Presentation mode of the surface, falls back to Fifo if unsupported.
+    /// Also set with `--present-mode <mode>`, e.g. to benchmark uncapped.
+    pub present_mode: wgpu::PresentMode,
+    /// Which graphics APIs wgpu is allowed to use.
+    /// The WGPU_BACKEND environment variable overrides it, e.g. "vulkan,gl".
+    pub backends: wgpu::Backends,
+    /// Which kind of adapter wgpu picks when there are several, e.g. the
+    /// integrated GPU of a laptop with LowPower. Also set with
+    /// `--power-preference <preference>` or the WGPU_POWER_PREF environment
+    /// variable ("low" or "high").
+    pub power_preference: wgpu::PowerPreference,
+    /// Only accept a fallback adapter, usually a software renderer, e.g. to
+    /// tell a driver bug apart from a bug of ours.
+    /// Also enabled with `--force-fallback-adapter`.
+    pub force_fallback_adapter: bool,
+    /// Optional device features to request, the unsupported ones are skipped
+    pub features: wgpu::Features,
+    /// Directory wgpu writes an API trace of the device into, for replaying
+    /// it in the wgpu player. Set with `--trace <dir>` or with WGPU_TRACE.
+    /// Only effective if wgpu is built with its `trace` feature.
+    pub trace_path: Option<PathBuf>,
+    /// How much simulated time a single call to `fixed_update()` covers
+    pub fixed_timestep: Duration,
+    /// Max number of fixed updates per frame. If we fall behind more than
+    /// this (e.g. after a debugger pause) the remaining time is dropped
+    /// instead of trying to catch up, which would only make us fall
+    /// further behind (the so called "spiral of death")
+    pub max_substeps: u32,
+    /// When to render a new frame, see RenderPolicy.
+    /// `--power-save` picks OnChange, which sleeps while nothing changes.
+    pub render_policy: RenderPolicy,
+    /// Frames slower than this show up as spikes in the frame time graph
+    pub target_frame_time: Duration,
+    /// Force a specific surface format instead of the first sRGB one, also
+    /// set with `--surface-format <format>`, e.g. "bgra8unorm".
+    /// It's ignored (with a warning) if the surface doesn't support it.
+    pub surface_format: Option<wgpu::TextureFormat>,
+    /// Prefer an Rgba16Float surface over an sRGB one when it's supported,
+    /// which can show colors brighter than white. Also enabled with `--hdr`.
+    pub hdr: bool,
+    /// Index (as printed at startup) of the adapter to use.
+    /// When None, or when the index is invalid, wgpu picks one for us.
+    pub adapter_index: Option<usize>,
+    /// Use the first adapter whose name contains this, ignoring case, e.g.
+    /// "nvidia" to pick the discrete card over the integrated one. Only
+    /// looked at without a valid adapter_index. Also set with
+    /// `--adapter-name <name>` or the WGPU_ADAPTER_NAME environment variable.
+    pub adapter_name: Option<String>,
+    /// The scene is rendered at the size of the window times this, then
+    /// scaled to the window: below 1 to render faster, above 1 to
+    /// supersample. Also cycled at runtime with F3.
+    pub render_scale: f32,
+    /// Adapt the render scale to the frame times, starting from render_scale.
+    /// None keeps it where it is. Also toggled at runtime with F4.
+    pub dynamic_resolution: Option<DynamicResolution>,
+    /// Render at this fixed resolution for pixel art, shown at the largest
+    /// integer scale that fits in the window with nearest filtering and
+    /// letterboxed. It overrides the render scale. Also toggled at runtime
+    /// with Shift+F3.
+    pub pixel_perfect: Option<winit::dpi::PhysicalSize<u32>>,
+    /// Number of MSAA samples, 1 disables MSAA. Falls back
+    /// to the highest supported count below it if unsupported.
+    pub sample_count: u32,
+    /// Use alpha to coverage in the cutout demo, only effective with MSAA
+    pub alpha_to_coverage: bool,
+    /// Which faces of the mesh aren't drawn, None to draw both.
+    /// Also cycled at runtime with X.
+    pub cull_mode: Option<wgpu::Face>,
+    /// Which winding of the triangles of the mesh is their front face.
+    /// Also flipped at runtime with Z.
+    pub front_face: wgpu::FrontFace,
+    /// Depth bias of the decal demo, which keeps the decal from z-fighting
+    /// with the floor it lies on. Negative values bring it closer.
+    pub decal_depth_bias: wgpu::DepthBiasState,
+    /// Narrowest and widest vertical field of view of the camera, in degrees.
+    /// It's changed with Ctrl and the mouse wheel, or with comma and period.
+    pub fov_limits: (f32, f32),
+    /// How far the gamepad sticks and triggers have to be pushed before they
+    /// do anything, from 0 to 1. Only used with the "gamepad" feature.
+    pub gamepad_deadzone: f32,
+    /// Number of cells of the Game of Life grid
+    pub automata_size: (u32, u32),
+    /// How many Game of Life generations are simulated every second
+    pub automata_steps_per_second: f32,
+    /// Render the SDF scene side by side for the left and right eyes.
+    /// Also enabled with `--stereo`.
+    pub stereo: bool,
+    /// Distance between the eyes in the stereo mode, in scene units
+    pub ipd: f32,
+    /// Seed of the random numbers used for procedural content, like the
+    /// initial Game of Life cells. When None a new seed is picked every run.
+    pub random_seed: Option<u64>,
+    /// Render a single frame and exit, so that GPU debuggers like RenderDoc
+    /// can capture a clean frame. Enabled with `--capture-first-frame`.
+    pub capture_first_frame: bool,
+    /// Flash the screen and log every present, see LatencyProbe.
+    /// Enabled with `--latency-probe`.
+    pub latency_probe: bool,
+    /// Upload the per-frame data through a staging belt rather than with
+    /// queue.write_buffer(). Turned off with `--no-staging-belt`, e.g. to
+    /// compare the frame times of both with `--log-timings`.
+    pub staging_belt: bool,
+    /// Write every frame time to this CSV file, set with `--log-timings <path>`
+    pub timings_path: Option<PathBuf>,
+    /// Save the input events to this file, set with `--record <path>`
+    pub record_input: Option<PathBuf>,
+    /// Replay the input events saved with `--record`, set with `--replay <path>`
+    pub replay_input: Option<PathBuf>,
+    /// Where the viewpoints saved with Ctrl and a digit go, one file per slot
+    pub views_dir: PathBuf,
+    /// The cameras Page Up and Page Down cycle through. When empty, those
+    /// keys leave the camera alone.
+    pub viewpoints: Vec<Viewpoint>,
+    /// Render a single frame into this PNG without opening a window, then
+    /// exit. Set with `--screenshot <path>`, the image has the window size.
+    pub screenshot_path: Option<PathBuf>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            app_name: "webgpu-101".to_string(),
+            icon_path: Some(PathBuf::from(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/assets/icon.png"
+            ))),
+            texture_path: None,
+            texture_sampler: SamplerDesc::default(),
+            instance_grid_size: 1,
+            window_size: None,
+            monitor_index: None,
+            video_mode_index: None,
+            hide_until_first_frame: true,
+            clear_color: wgpu::Color::WHITE,
+            pulse_clear_color: false,
+            mouse_clear_color: false,
+            // This caps the display rate at the displays framerate:
+            // which is essentially VSync
+            present_mode: wgpu::PresentMode::Fifo,
+            // Vulkan | Metal | DX12 | Browser WebGPU
+            backends: wgpu::Backends::all(),
+            // Favour performance over battery life
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            trace_path: None,
+            features: wgpu::Features::empty(),
+            fixed_timestep: Duration::from_secs_f64(1.0 / 60.0),
+            max_substeps: 8,
+            render_policy: RenderPolicy::AlwaysRedraw,
+            target_frame_time: Duration::from_secs_f64(1.0 / 60.0),
+            surface_format: None,
+            hdr: false,
+            adapter_index: None,
+            adapter_name: None,
+            render_scale: 1.0,
+            dynamic_resolution: None,
+            pixel_perfect: None,
+            sample_count: 1,
+            alpha_to_coverage: true,
+            cull_mode: Some(wgpu::Face::Back),
+            front_face: wgpu::FrontFace::Ccw,
+            // The slope term covers the floor seen at grazing angles
+            decal_depth_bias: wgpu::DepthBiasState {
+                constant: -8,
+                slope_scale: -2.0,
+                clamp: 0.0,
+            },
+            fov_limits: (15.0, 90.0),
+            gamepad_deadzone: 0.15,
+            automata_size: (256, 256),
+            automata_steps_per_second: 10.0,
+            stereo: false,
+            // About the average interpupillary distance, with 1 unit = 1 meter
+            ipd: 0.064,
+            random_seed: None,
+            capture_first_frame: false,
+            latency_probe: false,
+            staging_belt: true,
+            timings_path: None,
+            record_input: None,
+            replay_input: None,
+            views_dir: PathBuf::from("views"),
+            viewpoints: views::default_viewpoints(),
+            screenshot_path: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Built-in defaults, overridden by the config file (if there is one),
+    /// then by the environment variables and then by the command line flags
+    pub fn load() -> Self {
+        let path = std::env::var_os(CONFIG_PATH_ENV_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_CONFIG_PATH));
+
+        let mut config = match Self::from_file(&path) {
+            Ok(config) => config,
+            Err(e) => {
+                log::error!("{e}, using the default config");
+                Self::default()
+            }
+        };
+        config.apply_env();
+        config.apply_args(std::env::args().skip(1));
+
+        config
+    }
+
+    /// Read the config from a TOML file. A missing file is not an error:
+    /// the defaults are used, just like for any key missing from the file.
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(ConfigError::Io(path.to_path_buf(), e)),
+        };
+
+        let file: ConfigFile =
+            toml::from_str(&contents).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))?;
+
+        let mut config = Self::default();
+        file.apply(&mut config)
+            .map_err(|(key, message)| ConfigError::InvalidValue {
+                path: path.to_path_buf(),
+                key,
+                message,
+            })?;
+
+        Ok(config)
+    }
+
+    /// The variables wgpu's own examples read, so the same ones work here
+    fn apply_env(&mut self) {
+        if let Some(backends) = wgpu::util::backend_bits_from_env() {
+            if backends.is_empty() {
+                log::warn!("Ignoring WGPU_BACKEND, it doesn't name any known backend");
+            } else {
+                self.backends = backends;
+            }
+        }
+        if let Some(power_preference) = wgpu::util::power_preference_from_env() {
+            self.power_preference = power_preference;
+        }
+        if let Some(path) = std::env::var_os("WGPU_TRACE") {
+            self.trace_path = Some(PathBuf::from(path));
+        }
+        if let Ok(name) = std::env::var("WGPU_ADAPTER_NAME") {
+            if name.trim().is_empty() {
+                log::warn!("Ignoring WGPU_ADAPTER_NAME, it's empty");
+            } else {
+                self.adapter_name = Some(name);
+            }
+        }
+    }
+
+    fn apply_args(&mut self, mut args: impl Iterator<Item = String>) {
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--capture-first-frame" => self.capture_first_frame = true,
+                "--power-save" => self.render_policy = RenderPolicy::OnChange,
+                "--stereo" => self.stereo = true,
+                "--hdr" => self.hdr = true,
+                "--pulse-clear-color" => self.pulse_clear_color = true,
+                "--mouse-clear-color" => self.mouse_clear_color = true,
+                "--latency-probe" => self.latency_probe = true,
+                "--no-staging-belt" => self.staging_belt = false,
+                "--force-fallback-adapter" => self.force_fallback_adapter = true,
+                "--power-preference" => {
+                    let Some(name) = args.next() else {
+                        log::warn!("Ignoring {arg} without a power preference");
+                        continue;
+                    };
+                    match parse_power_preference(&name) {
+                        Some(power_preference) => self.power_preference = power_preference,
+                        None => log::warn!("Ignoring unknown power preference {name:?}"),
+                    }
+                }
+                "--adapter-name" => {
+                    let Some(name) = args.next() else {
+                        log::warn!("Ignoring {arg} without an adapter name");
+                        continue;
+                    };
+                    if name.trim().is_empty() {
+                        log::warn!("Ignoring {arg} with an empty adapter name");
+                        continue;
+                    }
+                    self.adapter_name = Some(name);
+                }
+                "--surface-format" => {
+                    let Some(name) = args.next() else {
+                        log::warn!("Ignoring {arg} without a surface format");
+                        continue;
+                    };
+                    match parse_surface_format(&name) {
+                        Some(format) => self.surface_format = Some(format),
+                        None => log::warn!("Ignoring unknown surface format {name:?}"),
+                    }
+                }
+                "--present-mode" => {
+                    let Some(name) = args.next() else {
+                        log::warn!("Ignoring {arg} without a present mode");
+                        continue;
+                    };
+                    match parse_present_mode(&name) {
+                        Some(mode) => self.present_mode = mode,
+                        None => log::warn!("Ignoring unknown present mode {name:?}"),
+                    }
+                }
+                "--record" | "--replay" | "--log-timings" | "--texture" | "--trace"
+                | "--screenshot" => {
+                    let Some(path) = args.next() else {
+                        log::warn!("Ignoring {arg} without a file path");
+                        continue;
+                    };
+                    let path = Some(PathBuf::from(path));
+                    match arg.as_str() {
+                        "--record" => self.record_input = path,
+                        "--replay" => self.replay_input = path,
+                        "--texture" => self.texture_path = path,
+                        "--trace" => self.trace_path = path,
+                        "--screenshot" => self.screenshot_path = path,
+                        _ => self.timings_path = path,
+                    }
+                }
+                _ => log::warn!("Ignoring unknown argument {arg:?}"),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    InvalidValue {
+        path: PathBuf,
+        key: &'static str,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "Failed to read {path:?}: {e}"),
+            // The toml error already points to the offending line and key
+            Self::Parse(path, e) => write!(f, "Failed to parse {path:?}: {e}"),
+            Self::InvalidValue { path, key, message } => {
+                write!(f, "Invalid value for '{key}' in {path:?}: {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// What the config file can contain. Every key is optional, e.g.:
+///
+/// ```toml
+/// title = "My app"
+/// texture = "assets/icon.png"
+/// # repeat, mirror_repeat or clamp_to_edge; linear or nearest
+/// texture_sampler = { wrap_u = "clamp_to_edge", wrap_v = "clamp_to_edge", mag_filter = "nearest" }
+/// instance_grid_size = 10     # 10x10 quads
+/// width = 1280
+/// height = 720
+/// monitor_index = 1
+/// video_mode_index = 0        # exclusive fullscreen
+/// hide_until_first_frame = false
+/// clear_color = [0.1, 0.2, 0.3, 1.0]
+/// pulse_clear_color = true
+/// mouse_clear_color = true
+/// surface_format = "bgra8unorm" # bgra8unorm(_srgb), rgba8unorm(_srgb), rgb10a2unorm, rgba16float
+/// hdr = true                  # Rgba16Float surface, when supported
+/// present_mode = "mailbox"    # fifo, fifo_relaxed, mailbox, immediate, auto_vsync, auto_no_vsync
+/// backends = "vulkan,gl"      # vulkan, metal, dx12, dx11, gl, webgpu, primary, all
+/// power_preference = "low_power" # low_power, high_performance or none
+/// force_fallback_adapter = true
+/// features = ["polygon_mode_line"]
+/// trace = "wgpu-trace"        # directory of the API trace
+/// adapter_index = 1
+/// adapter_name = "nvidia"     # part of the name, used without adapter_index
+/// fixed_timestep = 0.01       # seconds
+/// max_substeps = 4            # fixed updates per frame at most
+/// target_frame_time = 0.033   # seconds, slower frames are spikes in the graph
+/// render_policy = "on_change" # always, on_change or fixed_rate
+/// render_rate = 30.0          # frames per second, for fixed_rate
+/// render_scale = 0.5          # half the resolution of the window
+/// # lower the render scale down to min_scale while frames take longer than
+/// # target_ms, raise it back up to max_scale when they're faster
+/// dynamic_resolution = { target_ms = 16.6, min_scale = 0.5, max_scale = 1.0 }
+/// pixel_perfect = [320, 180]  # base resolution, scaled by whole numbers
+/// sample_count = 4           # 1, 2, 4 or 8
+/// alpha_to_coverage = false
+/// cull_mode = "none"          # none, front or back
+/// front_face = "cw"           # ccw or cw
+/// decal_depth_bias = { constant = -4, slope_scale = -1.0 }
+/// fov_limits = [30.0, 60.0]    # degrees
+/// gamepad_deadzone = 0.2
+/// automata_size = [512, 512]
+/// automata_steps_per_second = 30.0
+/// stereo = true
+/// ipd = 0.064                 # distance between the eyes
+/// random_seed = 42
+/// views_dir = "views"         # saved viewpoints
+///
+/// # Replace the default viewpoints, "up" and "fovy" are optional
+/// [[viewpoints]]
+/// name = "Top"
+/// eye = [0.0, 5.0, 0.1]
+/// target = [0.0, 0.0, 0.0]
+/// fovy = 30.0
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    title: Option<String>,
+    texture: Option<PathBuf>,
+    texture_sampler: Option<SamplerFile>,
+    instance_grid_size: Option<u32>,
+    width: Option<u32>,
+    height: Option<u32>,
+    monitor_index: Option<usize>,
+    video_mode_index: Option<usize>,
+    hide_until_first_frame: Option<bool>,
+    clear_color: Option<[f64; 4]>,
+    pulse_clear_color: Option<bool>,
+    mouse_clear_color: Option<bool>,
+    surface_format: Option<String>,
+    hdr: Option<bool>,
+    present_mode: Option<String>,
+    backends: Option<String>,
+    power_preference: Option<String>,
+    force_fallback_adapter: Option<bool>,
+    features: Option<Vec<String>>,
+    trace: Option<PathBuf>,
+    adapter_index: Option<usize>,
+    adapter_name: Option<String>,
+    fixed_timestep: Option<f64>,
+    max_substeps: Option<u32>,
+    target_frame_time: Option<f64>,
+    render_policy: Option<String>,
+    render_rate: Option<f64>,
+    render_scale: Option<f32>,
+    dynamic_resolution: Option<DynamicResolutionFile>,
+    pixel_perfect: Option<[u32; 2]>,
+    sample_count: Option<u32>,
+    alpha_to_coverage: Option<bool>,
+    cull_mode: Option<String>,
+    front_face: Option<String>,
+    decal_depth_bias: Option<DepthBiasFile>,
+    fov_limits: Option<[f32; 2]>,
+    gamepad_deadzone: Option<f32>,
+    automata_size: Option<[u32; 2]>,
+    automata_steps_per_second: Option<f32>,
+    stereo: Option<bool>,
+    ipd: Option<f32>,
+    random_seed: Option<u64>,
+    views_dir: Option<PathBuf>,
+    viewpoints: Option<Vec<ViewpointFile>>,
+}
+
+/// A depth bias, without the clamp: clamping needs a downlevel flag the GL
+/// backend doesn't always have
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DepthBiasFile {
+    constant: Option<i32>,
+    slope_scale: Option<f32>,
+}
+
+/// The keys that aren't set keep the defaults of DynamicResolution
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct DynamicResolutionFile {
+    target_ms: Option<f32>,
+    min_scale: Option<f32>,
+    max_scale: Option<f32>,
+}
+
+impl DynamicResolutionFile {
+    fn into_dynamic_resolution(self) -> Result<DynamicResolution, String> {
+        let default = DynamicResolution::default();
+        let settings = DynamicResolution {
+            target_ms: self.target_ms.unwrap_or(default.target_ms),
+            min_scale: self.min_scale.unwrap_or(default.min_scale),
+            max_scale: self.max_scale.unwrap_or(default.max_scale),
+        };
+        if !(settings.target_ms > 0.0 && settings.target_ms.is_finite()) {
+            return Err("target_ms must be a positive number".to_string());
+        }
+        if !(settings.min_scale > 0.0
+            && settings.min_scale <= settings.max_scale
+            && settings.max_scale.is_finite())
+        {
+            return Err(
+                "min_scale and max_scale must be positive numbers, the smallest first".to_string(),
+            );
+        }
+
+        Ok(settings)
+    }
+}
+
+/// The keys that aren't set keep the default, linear and repeating
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SamplerFile {
+    wrap_u: Option<String>,
+    wrap_v: Option<String>,
+    mag_filter: Option<String>,
+    min_filter: Option<String>,
+}
+
+impl SamplerFile {
+    fn into_sampler(self) -> Result<SamplerDesc, String> {
+        let mut sampler = SamplerDesc::default();
+        let address_mode = |name: String| {
+            parse_address_mode(&name).ok_or_else(|| format!("unknown wrap mode {name:?}"))
+        };
+        let filter_mode = |name: String| {
+            parse_filter_mode(&name).ok_or_else(|| format!("unknown filter {name:?}"))
+        };
+        if let Some(name) = self.wrap_u {
+            sampler.wrap_u = address_mode(name)?;
+        }
+        if let Some(name) = self.wrap_v {
+            sampler.wrap_v = address_mode(name)?;
+        }
+        if let Some(name) = self.mag_filter {
+            sampler.mag_filter = filter_mode(name)?;
+        }
+        if let Some(name) = self.min_filter {
+            sampler.min_filter = filter_mode(name)?;
+        }
+
+        Ok(sampler)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ViewpointFile {
+    name: String,
+    eye: [f32; 3],
+    target: [f32; 3],
+    up: Option<[f32; 3]>,
+    fovy: Option<f32>,
+}
+
+impl ViewpointFile {
+    fn into_viewpoint(self) -> Result<Viewpoint, String> {
+        if self.eye == self.target {
+            return Err(format!("{:?} looks at its own eye", self.name));
+        }
+        let mut viewpoint = Viewpoint::new(&self.name, self.eye, self.target);
+        if let Some(up) = self.up {
+            viewpoint.camera.up = up;
+        }
+        if let Some(fovy) = self.fovy {
+            if !(fovy > 0.0 && fovy < 180.0) {
+                return Err(format!(
+                    "the fovy of {:?} must be between 0 and 180",
+                    self.name
+                ));
+            }
+            viewpoint.camera.fovy = fovy;
+        }
+
+        Ok(viewpoint)
+    }
+}
+
+impl ConfigFile {
+    /// Validate the values and write them into 'config'.
+    /// On failure, returns the offending key and what's wrong with it.
+    fn apply(self, config: &mut AppConfig) -> Result<(), (&'static str, String)> {
+        if let Some(title) = self.title {
+            config.app_name = title;
+        }
+
+        if self.texture.is_some() {
+            config.texture_path = self.texture;
+        }
+        if let Some(sampler) = self.texture_sampler {
+            config.texture_sampler = sampler
+                .into_sampler()
+                .map_err(|message| ("texture_sampler", message))?;
+        }
+
+        if let Some(size) = self.instance_grid_size {
+            if !(1..=MAX_GRID_SIZE).contains(&size) {
+                return Err((
+                    "instance_grid_size",
+                    format!("must be between 1 and {MAX_GRID_SIZE}"),
+                ));
+            }
+            config.instance_grid_size = size;
+        }
+
+        match (self.width, self.height) {
+            (Some(0), _) => return Err(("width", "must be greater than 0".to_string())),
+            (_, Some(0)) => return Err(("height", "must be greater than 0".to_string())),
+            (Some(width), Some(height)) => {
+                config.window_size = Some(winit::dpi::PhysicalSize::new(width, height))
+            }
+            (None, None) => {}
+            (None, Some(_)) => return Err(("width", "height is set but width is not".to_string())),
+            (Some(_), None) => {
+                return Err(("height", "width is set but height is not".to_string()))
+            }
+        }
+
+        if self.monitor_index.is_some() {
+            config.monitor_index = self.monitor_index;
+        }
+
+        if self.video_mode_index.is_some() {
+            config.video_mode_index = self.video_mode_index;
+        }
+
+        if let Some(hide) = self.hide_until_first_frame {
+            config.hide_until_first_frame = hide;
+        }
+
+        if let Some([r, g, b, a]) = self.clear_color {
+            if [r, g, b, a].iter().any(|c| !(0.0..=1.0).contains(c)) {
+                return Err((
+                    "clear_color",
+                    "components must be in the [0, 1] range".to_string(),
+                ));
+            }
+            config.clear_color = wgpu::Color { r, g, b, a };
+        }
+
+        if let Some(enabled) = self.pulse_clear_color {
+            config.pulse_clear_color = enabled;
+        }
+
+        if let Some(enabled) = self.mouse_clear_color {
+            config.mouse_clear_color = enabled;
+        }
+
+        if let Some(format) = self.surface_format {
+            let format = parse_surface_format(&format).ok_or_else(|| {
+                (
+                    "surface_format",
+                    format!("unknown surface format {format:?}"),
+                )
+            })?;
+            config.surface_format = Some(format);
+        }
+
+        if let Some(enabled) = self.hdr {
+            config.hdr = enabled;
+        }
+
+        if let Some(present_mode) = self.present_mode {
+            config.present_mode = parse_present_mode(&present_mode).ok_or_else(|| {
+                (
+                    "present_mode",
+                    format!("unknown present mode {present_mode:?}"),
+                )
+            })?;
+        }
+
+        if let Some(backends) = self.backends {
+            config.backends = wgpu::util::parse_backends_from_comma_list(&backends);
+            if config.backends.is_empty() {
+                return Err(("backends", format!("no known backend in {backends:?}")));
+            }
+        }
+
+        if let Some(power_preference) = self.power_preference {
+            config.power_preference =
+                parse_power_preference(&power_preference).ok_or_else(|| {
+                    (
+                        "power_preference",
+                        format!("unknown power preference {power_preference:?}"),
+                    )
+                })?;
+        }
+
+        if let Some(enabled) = self.force_fallback_adapter {
+            config.force_fallback_adapter = enabled;
+        }
+
+        if let Some(features) = self.features {
+            for name in features {
+                let feature = wgpu::Features::from_name(&name.to_uppercase())
+                    .ok_or_else(|| ("features", format!("unknown feature {name:?}")))?;
+                config.features |= feature;
+            }
+        }
+
+        if self.trace.is_some() {
+            config.trace_path = self.trace;
+        }
+
+        if self.adapter_index.is_some() {
+            config.adapter_index = self.adapter_index;
+        }
+
+        if let Some(name) = self.adapter_name {
+            if name.trim().is_empty() {
+                return Err(("adapter_name", "must not be empty".to_string()));
+            }
+            config.adapter_name = Some(name);
+        }
+
+        if let Some(seconds) = self.fixed_timestep {
+            if !(seconds > 0.0 && seconds.is_finite()) {
+                return Err((
+                    "fixed_timestep",
+                    "must be a positive number of seconds".to_string(),
+                ));
+            }
+            config.fixed_timestep = Duration::from_secs_f64(seconds);
+        }
+
+        if let Some(max_substeps) = self.max_substeps {
+            if max_substeps == 0 {
+                return Err(("max_substeps", "must be at least 1".to_string()));
+            }
+            config.max_substeps = max_substeps;
+        }
+
+        if let Some(seconds) = self.target_frame_time {
+            if !(seconds > 0.0 && seconds.is_finite()) {
+                return Err((
+                    "target_frame_time",
+                    "must be a positive number of seconds".to_string(),
+                ));
+            }
+            config.target_frame_time = Duration::from_secs_f64(seconds);
+        }
+
+        match (self.render_policy.as_deref(), self.render_rate) {
+            (None, None) => {}
+            (Some("always"), None) => config.render_policy = RenderPolicy::AlwaysRedraw,
+            (Some("on_change"), None) => config.render_policy = RenderPolicy::OnChange,
+            (Some("fixed_rate"), Some(rate)) => {
+                if !(rate > 0.0 && rate.is_finite()) {
+                    return Err(("render_rate", "must be a positive number".to_string()));
+                }
+                config.render_policy = RenderPolicy::FixedRate(Duration::from_secs_f64(1.0 / rate));
+            }
+            (Some("fixed_rate"), None) => {
+                return Err((
+                    "render_rate",
+                    "is required by the fixed_rate policy".to_string(),
+                ))
+            }
+            (Some("always" | "on_change") | None, Some(_)) => {
+                return Err((
+                    "render_rate",
+                    "only applies to the fixed_rate policy".to_string(),
+                ))
+            }
+            (Some(policy), _) => {
+                return Err(("render_policy", format!("unknown render policy {policy:?}")))
+            }
+        }
+
+        if let Some(scale) = self.render_scale {
+            if !(scale > 0.0 && scale.is_finite()) {
+                return Err(("render_scale", "must be a positive number".to_string()));
+            }
+            config.render_scale = scale;
+        }
+
+        if let Some(dynamic_resolution) = self.dynamic_resolution {
+            config.dynamic_resolution = Some(
+                dynamic_resolution
+                    .into_dynamic_resolution()
+                    .map_err(|message| ("dynamic_resolution", message))?,
+            );
+        }
+
+        if let Some([width, height]) = self.pixel_perfect {
+            if width == 0 || height == 0 {
+                return Err(("pixel_perfect", "must be greater than 0".to_string()));
+            }
+            config.pixel_perfect = Some(winit::dpi::PhysicalSize::new(width, height));
+        }
+
+        if let Some(sample_count) = self.sample_count {
+            if ![1, 2, 4, 8].contains(&sample_count) {
+                return Err(("sample_count", "must be 1, 2, 4 or 8".to_string()));
+            }
+            config.sample_count = sample_count;
+        }
+
+        if let Some(enabled) = self.alpha_to_coverage {
+            config.alpha_to_coverage = enabled;
+        }
+
+        if let Some(cull_mode) = self.cull_mode {
+            config.cull_mode = parse_cull_mode(&cull_mode)
+                .ok_or_else(|| ("cull_mode", format!("unknown cull mode {cull_mode:?}")))?;
+        }
+
+        if let Some(front_face) = self.front_face {
+            config.front_face = parse_front_face(&front_face)
+                .ok_or_else(|| ("front_face", format!("unknown front face {front_face:?}")))?;
+        }
+
+        if let Some(bias) = self.decal_depth_bias {
+            if let Some(constant) = bias.constant {
+                config.decal_depth_bias.constant = constant;
+            }
+            if let Some(slope_scale) = bias.slope_scale {
+                if !slope_scale.is_finite() {
+                    return Err((
+                        "decal_depth_bias",
+                        "slope_scale must be a number".to_string(),
+                    ));
+                }
+                config.decal_depth_bias.slope_scale = slope_scale;
+            }
+        }
+
+        if let Some([min, max]) = self.fov_limits {
+            if !(min > 0.0 && min <= max && max < 180.0) {
+                return Err((
+                    "fov_limits",
+                    "must be between 0 and 180 degrees, the smallest first".to_string(),
+                ));
+            }
+            config.fov_limits = (min, max);
+        }
+
+        if let Some(deadzone) = self.gamepad_deadzone {
+            if !(0.0..1.0).contains(&deadzone) {
+                return Err((
+                    "gamepad_deadzone",
+                    "must be at least 0 and less than 1".to_string(),
+                ));
+            }
+            config.gamepad_deadzone = deadzone;
+        }
+
+        if let Some([width, height]) = self.automata_size {
+            if width == 0 || height == 0 {
+                return Err(("automata_size", "must be greater than 0".to_string()));
+            }
+            config.automata_size = (width, height);
+        }
+
+        if let Some(rate) = self.automata_steps_per_second {
+            if !(rate > 0.0 && rate.is_finite()) {
+                return Err((
+                    "automata_steps_per_second",
+                    "must be a positive number".to_string(),
+                ));
+            }
+            config.automata_steps_per_second = rate;
+        }
+
+        if let Some(enabled) = self.stereo {
+            config.stereo = enabled;
+        }
+
+        if let Some(ipd) = self.ipd {
+            if !(ipd >= 0.0 && ipd.is_finite()) {
+                return Err(("ipd", "must be a positive number or 0".to_string()));
+            }
+            config.ipd = ipd;
+        }
+
+        if self.random_seed.is_some() {
+            config.random_seed = self.random_seed;
+        }
+
+        if let Some(views_dir) = self.views_dir {
+            config.views_dir = views_dir;
+        }
+
+        if let Some(viewpoints) = self.viewpoints {
+            config.viewpoints = viewpoints
+                .into_iter()
+                .map(ViewpointFile::into_viewpoint)
+                .collect::<Result<_, _>>()
+                .map_err(|message| ("viewpoints", message))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn parse_present_mode(name: &str) -> Option<wgpu::PresentMode> {
+    let mode = match name.to_lowercase().as_str() {
+        "fifo" => wgpu::PresentMode::Fifo,
+        "fifo_relaxed" => wgpu::PresentMode::FifoRelaxed,
+        "mailbox" => wgpu::PresentMode::Mailbox,
+        "immediate" => wgpu::PresentMode::Immediate,
+        "auto_vsync" => wgpu::PresentMode::AutoVsync,
+        "auto_no_vsync" => wgpu::PresentMode::AutoNoVsync,
+        _ => return None,
+    };
+
+    Some(mode)
+}
+
+/// The formats surfaces usually support, named like in WebGPU
+/// with underscores instead of dashes
+fn parse_surface_format(name: &str) -> Option<wgpu::TextureFormat> {
+    let format = match name.to_lowercase().as_str() {
+        "bgra8unorm" => wgpu::TextureFormat::Bgra8Unorm,
+        "bgra8unorm_srgb" => wgpu::TextureFormat::Bgra8UnormSrgb,
+        "rgba8unorm" => wgpu::TextureFormat::Rgba8Unorm,
+        "rgba8unorm_srgb" => wgpu::TextureFormat::Rgba8UnormSrgb,
+        "rgb10a2unorm" => wgpu::TextureFormat::Rgb10a2Unorm,
+        "rgba16float" => wgpu::TextureFormat::Rgba16Float,
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+/// The faces to cull, Some(None) meaning none of them
+fn parse_cull_mode(name: &str) -> Option<Option<wgpu::Face>> {
+    let cull_mode = match name.to_lowercase().as_str() {
+        "none" => None,
+        "front" => Some(wgpu::Face::Front),
+        "back" => Some(wgpu::Face::Back),
+        _ => return None,
+    };
+
+    Some(cull_mode)
+}
+
+/// ClampToBorder is left out, it needs a device feature
+fn parse_address_mode(name: &str) -> Option<wgpu::AddressMode> {
+    let address_mode = match name.to_lowercase().as_str() {
+        "repeat" => wgpu::AddressMode::Repeat,
+        "mirror_repeat" => wgpu::AddressMode::MirrorRepeat,
+        "clamp_to_edge" => wgpu::AddressMode::ClampToEdge,
+        _ => return None,
+    };
+
+    Some(address_mode)
+}
+
+fn parse_filter_mode(name: &str) -> Option<wgpu::FilterMode> {
+    let filter_mode = match name.to_lowercase().as_str() {
+        "linear" => wgpu::FilterMode::Linear,
+        "nearest" => wgpu::FilterMode::Nearest,
+        _ => return None,
+    };
+
+    Some(filter_mode)
+}
+
+fn parse_front_face(name: &str) -> Option<wgpu::FrontFace> {
+    let front_face = match name.to_lowercase().as_str() {
+        "ccw" => wgpu::FrontFace::Ccw,
+        "cw" => wgpu::FrontFace::Cw,
+        _ => return None,
+    };
+
+    Some(front_face)
+}
+
+fn parse_power_preference(name: &str) -> Option<wgpu::PowerPreference> {
+    let power_preference = match name.to_lowercase().as_str() {
+        "low_power" => wgpu::PowerPreference::LowPower,
+        "high_performance" => wgpu::PowerPreference::HighPerformance,
+        "none" => wgpu::PowerPreference::None,
+        _ => return None,
+    };
+
+    Some(power_preference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The config a file with 'contents' gives, or the key it's rejected for
+    fn from_toml(contents: &str) -> Result<AppConfig, &'static str> {
+        let file: ConfigFile = toml::from_str(contents).expect("valid TOML");
+        let mut config = AppConfig::default();
+        file.apply(&mut config).map_err(|(key, _)| key)?;
+
+        Ok(config)
+    }
+
+    fn from_args(args: &[&str]) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.apply_args(args.iter().map(|arg| arg.to_string()));
+
+        config
+    }
+
+    #[test]
+    fn empty_file_keeps_the_defaults() {
+        let config = from_toml("").unwrap();
+        let default = AppConfig::default();
+        assert_eq!(config.app_name, default.app_name);
+        assert_eq!(config.window_size, default.window_size);
+        assert_eq!(config.present_mode, default.present_mode);
+        assert_eq!(config.render_policy, default.render_policy);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<ConfigFile>("colour = [1.0, 1.0, 1.0, 1.0]").is_err());
+    }
+
+    #[test]
+    fn window_options() {
+        let config = from_toml(
+            r#"
+            title = "My app"
+            texture = "assets/icon.png"
+            width = 1280
+            height = 720
+            monitor_index = 1
+            video_mode_index = 0
+            hide_until_first_frame = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.app_name, "My app");
+        assert_eq!(config.texture_path, Some(PathBuf::from("assets/icon.png")));
+        assert_eq!(
+            config.window_size,
+            Some(winit::dpi::PhysicalSize::new(1280, 720))
+        );
+        assert_eq!(config.monitor_index, Some(1));
+        assert_eq!(config.video_mode_index, Some(0));
+        assert!(!config.hide_until_first_frame);
+
+        assert_eq!(from_toml("width = 0\nheight = 720").unwrap_err(), "width");
+        assert_eq!(from_toml("width = 1280\nheight = 0").unwrap_err(), "height");
+        assert_eq!(from_toml("height = 720").unwrap_err(), "width");
+        assert_eq!(from_toml("width = 1280").unwrap_err(), "height");
+    }
+
+    #[test]
+    fn instance_grid_size() {
+        let config = from_toml("instance_grid_size = 10").unwrap();
+        assert_eq!(config.instance_grid_size, 10);
+
+        assert!(from_toml(&format!("instance_grid_size = {MAX_GRID_SIZE}")).is_ok());
+        for size in [0, MAX_GRID_SIZE + 1] {
+            let contents = format!("instance_grid_size = {size}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "instance_grid_size");
+        }
+    }
+
+    #[test]
+    fn clear_color_options() {
+        let config = from_toml(
+            r#"
+            clear_color = [0.1, 0.2, 0.3, 1.0]
+            pulse_clear_color = true
+            mouse_clear_color = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.clear_color,
+            wgpu::Color {
+                r: 0.1,
+                g: 0.2,
+                b: 0.3,
+                a: 1.0
+            }
+        );
+        assert!(config.pulse_clear_color);
+        assert!(config.mouse_clear_color);
+
+        for color in ["[1.5, 0.0, 0.0, 1.0]", "[0.0, 0.0, 0.0, -1.0]"] {
+            let contents = format!("clear_color = {color}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "clear_color");
+        }
+    }
+
+    #[test]
+    fn surface_options() {
+        let config = from_toml(
+            r#"
+            surface_format = "bgra8unorm"
+            hdr = true
+            present_mode = "mailbox"
+            sample_count = 4
+            alpha_to_coverage = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.surface_format, Some(wgpu::TextureFormat::Bgra8Unorm));
+        assert!(config.hdr);
+        assert_eq!(config.present_mode, wgpu::PresentMode::Mailbox);
+        assert_eq!(config.sample_count, 4);
+        assert!(!config.alpha_to_coverage);
+
+        assert_eq!(
+            from_toml(r#"surface_format = "r8unorm""#).unwrap_err(),
+            "surface_format"
+        );
+        assert_eq!(
+            from_toml(r#"present_mode = "vsync""#).unwrap_err(),
+            "present_mode"
+        );
+        for sample_count in [0, 3, 16] {
+            let contents = format!("sample_count = {sample_count}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "sample_count");
+        }
+    }
+
+    #[test]
+    fn mesh_options() {
+        let config = from_toml(
+            r#"
+            cull_mode = "none"
+            front_face = "cw"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.cull_mode, None);
+        assert_eq!(config.front_face, wgpu::FrontFace::Cw);
+
+        let config = from_toml(r#"cull_mode = "Front""#).unwrap();
+        assert_eq!(config.cull_mode, Some(wgpu::Face::Front));
+
+        assert_eq!(from_toml(r#"cull_mode = "both""#).unwrap_err(), "cull_mode");
+        assert_eq!(
+            from_toml(r#"front_face = "clockwise""#).unwrap_err(),
+            "front_face"
+        );
+    }
+
+    #[test]
+    fn viewpoints() {
+        let config = from_toml(
+            r#"
+            [[viewpoints]]
+            name = "Top"
+            eye = [0.0, 5.0, 0.1]
+            target = [0.0, 0.0, 0.0]
+            fovy = 30.0
+
+            [[viewpoints]]
+            name = "Low"
+            eye = [0.0, -1.0, 2.0]
+            target = [0.0, 0.0, 0.0]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.viewpoints.len(), 2);
+        assert_eq!(config.viewpoints[0].name, "Top");
+        assert_eq!(config.viewpoints[0].camera.fovy, 30.0);
+        assert_eq!(config.viewpoints[1].camera.eye, [0.0, -1.0, 2.0]);
+
+        assert!(from_toml("viewpoints = []").unwrap().viewpoints.is_empty());
+
+        let looking_at_itself = r#"
+            [[viewpoints]]
+            name = "Inside"
+            eye = [1.0, 1.0, 1.0]
+            target = [1.0, 1.0, 1.0]
+        "#;
+        assert_eq!(from_toml(looking_at_itself).unwrap_err(), "viewpoints");
+    }
+
+    #[test]
+    fn fov_limits() {
+        let config = from_toml("fov_limits = [30.0, 60.0]").unwrap();
+        assert_eq!(config.fov_limits, (30.0, 60.0));
+
+        for limits in ["[0.0, 60.0]", "[60.0, 30.0]", "[30.0, 180.0]"] {
+            let contents = format!("fov_limits = {limits}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "fov_limits");
+        }
+    }
+
+    #[test]
+    fn render_scale() {
+        assert_eq!(from_toml("render_scale = 0.5").unwrap().render_scale, 0.5);
+        for scale in ["0.0", "-1.0", "inf"] {
+            let contents = format!("render_scale = {scale}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "render_scale");
+        }
+    }
+
+    #[test]
+    fn dynamic_resolution() {
+        let config =
+            from_toml("dynamic_resolution = { target_ms = 33.3, min_scale = 0.25 }").unwrap();
+        assert_eq!(
+            config.dynamic_resolution,
+            Some(DynamicResolution {
+                target_ms: 33.3,
+                min_scale: 0.25,
+                max_scale: 1.0,
+            })
+        );
+        assert_eq!(from_toml("").unwrap().dynamic_resolution, None);
+
+        for settings in [
+            "{ target_ms = 0.0 }",
+            "{ min_scale = 0.0 }",
+            "{ min_scale = 2.0, max_scale = 1.0 }",
+        ] {
+            let contents = format!("dynamic_resolution = {settings}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "dynamic_resolution");
+        }
+    }
+
+    #[test]
+    fn pixel_perfect() {
+        assert_eq!(
+            from_toml("pixel_perfect = [320, 180]")
+                .unwrap()
+                .pixel_perfect,
+            Some(winit::dpi::PhysicalSize::new(320, 180))
+        );
+        assert_eq!(
+            from_toml("pixel_perfect = [0, 180]").unwrap_err(),
+            "pixel_perfect"
+        );
+    }
+
+    #[test]
+    fn gamepad_deadzone() {
+        assert_eq!(
+            from_toml("gamepad_deadzone = 0.25")
+                .unwrap()
+                .gamepad_deadzone,
+            0.25
+        );
+        for deadzone in ["-0.1", "1.0", "nan"] {
+            let contents = format!("gamepad_deadzone = {deadzone}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "gamepad_deadzone");
+        }
+    }
+
+    #[test]
+    fn texture_sampler() {
+        let config = from_toml(
+            r#"texture_sampler = { wrap_u = "clamp_to_edge", wrap_v = "mirror_repeat", mag_filter = "nearest" }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.texture_sampler,
+            SamplerDesc {
+                wrap_u: wgpu::AddressMode::ClampToEdge,
+                wrap_v: wgpu::AddressMode::MirrorRepeat,
+                mag_filter: wgpu::FilterMode::Nearest,
+                min_filter: wgpu::FilterMode::Linear,
+            }
+        );
+        assert_eq!(AppConfig::default().texture_sampler, SamplerDesc::default());
+
+        for sampler in [
+            r#"{ wrap_u = "clamp_to_border" }"#,
+            r#"{ min_filter = "cubic" }"#,
+        ] {
+            let contents = format!("texture_sampler = {sampler}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "texture_sampler");
+        }
+    }
+
+    #[test]
+    fn decal_depth_bias() {
+        let config = from_toml("decal_depth_bias = { constant = -4, slope_scale = -1.0 }").unwrap();
+        assert_eq!(config.decal_depth_bias.constant, -4);
+        assert_eq!(config.decal_depth_bias.slope_scale, -1.0);
+
+        // The keys that aren't set keep their default
+        let config = from_toml("decal_depth_bias = { constant = 0 }").unwrap();
+        assert_eq!(config.decal_depth_bias.constant, 0);
+        assert_eq!(
+            config.decal_depth_bias.slope_scale,
+            AppConfig::default().decal_depth_bias.slope_scale
+        );
+
+        assert_eq!(
+            from_toml("decal_depth_bias = { slope_scale = inf }").unwrap_err(),
+            "decal_depth_bias"
+        );
+    }
+
+    #[test]
+    fn adapter_options() {
+        let config = from_toml(
+            r#"
+            backends = "vulkan,gl"
+            power_preference = "low_power"
+            force_fallback_adapter = true
+            features = ["polygon_mode_line"]
+            trace = "wgpu-trace"
+            adapter_index = 1
+            adapter_name = "nvidia"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.backends, wgpu::Backends::VULKAN | wgpu::Backends::GL);
+        assert_eq!(config.power_preference, wgpu::PowerPreference::LowPower);
+        assert!(config.force_fallback_adapter);
+        assert_eq!(config.features, wgpu::Features::POLYGON_MODE_LINE);
+        assert_eq!(config.trace_path, Some(PathBuf::from("wgpu-trace")));
+        assert_eq!(config.adapter_index, Some(1));
+        assert_eq!(config.adapter_name.as_deref(), Some("nvidia"));
+
+        assert_eq!(from_toml(r#"backends = "glide""#).unwrap_err(), "backends");
+        assert_eq!(
+            from_toml(r#"power_preference = "max""#).unwrap_err(),
+            "power_preference"
+        );
+        assert_eq!(
+            from_toml(r#"features = ["warp_drive"]"#).unwrap_err(),
+            "features"
+        );
+        for name in ["", "  "] {
+            let contents = format!("adapter_name = {name:?}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "adapter_name");
+        }
+    }
+
+    #[test]
+    fn fixed_timestep() {
+        let config = from_toml("fixed_timestep = 0.01").unwrap();
+        assert_eq!(config.fixed_timestep, Duration::from_secs_f64(0.01));
+
+        for seconds in ["0.0", "-0.01", "inf", "nan"] {
+            let contents = format!("fixed_timestep = {seconds}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "fixed_timestep");
+        }
+    }
+
+    #[test]
+    fn max_substeps() {
+        let config = from_toml("max_substeps = 4").unwrap();
+        assert_eq!(config.max_substeps, 4);
+        assert_eq!(from_toml("max_substeps = 0").unwrap_err(), "max_substeps");
+    }
+
+    #[test]
+    fn target_frame_time() {
+        let config = from_toml("target_frame_time = 0.033").unwrap();
+        assert_eq!(config.target_frame_time, Duration::from_secs_f64(0.033));
+
+        for seconds in ["0.0", "-0.01", "inf", "nan"] {
+            let contents = format!("target_frame_time = {seconds}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "target_frame_time");
+        }
+    }
+
+    #[test]
+    fn render_policy() {
+        let policy = |contents| from_toml(contents).map(|config| config.render_policy);
+        assert_eq!(
+            policy(r#"render_policy = "always""#),
+            Ok(RenderPolicy::AlwaysRedraw)
+        );
+        assert_eq!(
+            policy(r#"render_policy = "on_change""#),
+            Ok(RenderPolicy::OnChange)
+        );
+        assert_eq!(
+            policy("render_policy = \"fixed_rate\"\nrender_rate = 30.0"),
+            Ok(RenderPolicy::FixedRate(Duration::from_secs_f64(1.0 / 30.0)))
+        );
+
+        assert_eq!(
+            policy(r#"render_policy = "fixed_rate""#),
+            Err("render_rate")
+        );
+        assert_eq!(
+            policy("render_policy = \"fixed_rate\"\nrender_rate = 0.0"),
+            Err("render_rate")
+        );
+        assert_eq!(
+            policy("render_policy = \"on_change\"\nrender_rate = 30.0"),
+            Err("render_rate")
+        );
+        assert_eq!(policy("render_rate = 30.0"), Err("render_rate"));
+        assert_eq!(
+            policy(r#"render_policy = "sometimes""#),
+            Err("render_policy")
+        );
+    }
+
+    #[test]
+    fn demo_options() {
+        let config = from_toml(
+            r#"
+            automata_size = [512, 256]
+            automata_steps_per_second = 30.0
+            stereo = true
+            ipd = 0.0
+            random_seed = 42
+            views_dir = "saved"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.automata_size, (512, 256));
+        assert_eq!(config.automata_steps_per_second, 30.0);
+        assert!(config.stereo);
+        assert_eq!(config.ipd, 0.0);
+        assert_eq!(config.random_seed, Some(42));
+        assert_eq!(config.views_dir, PathBuf::from("saved"));
+
+        for size in ["[0, 256]", "[512, 0]"] {
+            let contents = format!("automata_size = {size}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "automata_size");
+        }
+        for rate in ["0.0", "-1.0", "inf"] {
+            let contents = format!("automata_steps_per_second = {rate}");
+            assert_eq!(
+                from_toml(&contents).unwrap_err(),
+                "automata_steps_per_second"
+            );
+        }
+        for ipd in ["-0.064", "nan"] {
+            let contents = format!("ipd = {ipd}");
+            assert_eq!(from_toml(&contents).unwrap_err(), "ipd");
+        }
+    }
+
+    #[test]
+    fn present_mode_names() {
+        assert_eq!(parse_present_mode("fifo"), Some(wgpu::PresentMode::Fifo));
+        assert_eq!(
+            parse_present_mode("fifo_relaxed"),
+            Some(wgpu::PresentMode::FifoRelaxed)
+        );
+        assert_eq!(
+            parse_present_mode("Mailbox"),
+            Some(wgpu::PresentMode::Mailbox)
+        );
+        assert_eq!(
+            parse_present_mode("IMMEDIATE"),
+            Some(wgpu::PresentMode::Immediate)
+        );
+        assert_eq!(
+            parse_present_mode("auto_vsync"),
+            Some(wgpu::PresentMode::AutoVsync)
+        );
+        assert_eq!(
+            parse_present_mode("auto_no_vsync"),
+            Some(wgpu::PresentMode::AutoNoVsync)
+        );
+
+        assert_eq!(parse_present_mode(""), None);
+        assert_eq!(parse_present_mode("vsync"), None);
+        assert_eq!(parse_present_mode("fifo-relaxed"), None);
+    }
+
+    #[test]
+    fn power_preference_names() {
+        assert_eq!(
+            parse_power_preference("low_power"),
+            Some(wgpu::PowerPreference::LowPower)
+        );
+        assert_eq!(
+            parse_power_preference("High_Performance"),
+            Some(wgpu::PowerPreference::HighPerformance)
+        );
+        assert_eq!(
+            parse_power_preference("none"),
+            Some(wgpu::PowerPreference::None)
+        );
+
+        assert_eq!(parse_power_preference(""), None);
+        assert_eq!(parse_power_preference("low"), None);
+        assert_eq!(parse_power_preference("high-performance"), None);
+    }
+
+    #[test]
+    fn flags() {
+        let config = from_args(&[
+            "--capture-first-frame",
+            "--power-save",
+            "--stereo",
+            "--hdr",
+            "--pulse-clear-color",
+            "--mouse-clear-color",
+            "--latency-probe",
+            "--no-staging-belt",
+            "--force-fallback-adapter",
+        ]);
+        assert!(config.capture_first_frame);
+        assert_eq!(config.render_policy, RenderPolicy::OnChange);
+        assert!(config.stereo);
+        assert!(config.hdr);
+        assert!(config.pulse_clear_color);
+        assert!(config.mouse_clear_color);
+        assert!(config.latency_probe);
+        assert!(!config.staging_belt);
+        assert!(config.force_fallback_adapter);
+    }
+
+    #[test]
+    fn flags_with_a_value() {
+        let config = from_args(&[
+            "--power-preference",
+            "low_power",
+            "--adapter-name",
+            "nvidia",
+            "--surface-format",
+            "rgba16float",
+            "--present-mode",
+            "immediate",
+            "--record",
+            "input.log",
+            "--replay",
+            "replay.log",
+            "--log-timings",
+            "timings.csv",
+            "--texture",
+            "image.png",
+            "--trace",
+            "wgpu-trace",
+            "--screenshot",
+            "frame.png",
+        ]);
+        assert_eq!(config.power_preference, wgpu::PowerPreference::LowPower);
+        assert_eq!(config.adapter_name.as_deref(), Some("nvidia"));
+        assert_eq!(
+            config.surface_format,
+            Some(wgpu::TextureFormat::Rgba16Float)
+        );
+        assert_eq!(config.present_mode, wgpu::PresentMode::Immediate);
+        assert_eq!(config.record_input, Some(PathBuf::from("input.log")));
+        assert_eq!(config.replay_input, Some(PathBuf::from("replay.log")));
+        assert_eq!(config.timings_path, Some(PathBuf::from("timings.csv")));
+        assert_eq!(config.texture_path, Some(PathBuf::from("image.png")));
+        assert_eq!(config.trace_path, Some(PathBuf::from("wgpu-trace")));
+        assert_eq!(config.screenshot_path, Some(PathBuf::from("frame.png")));
+    }
+
+    #[test]
+    fn bad_flags_are_ignored() {
+        let default = AppConfig::default();
+
+        let config = from_args(&[
+            "--power-preference",
+            "max",
+            "--adapter-name",
+            "",
+            "--surface-format",
+            "r8unorm",
+            "--present-mode",
+            "vsync",
+            "--unknown-flag",
+        ]);
+        assert_eq!(config.power_preference, default.power_preference);
+        assert_eq!(config.adapter_name, None);
+        assert_eq!(config.surface_format, None);
+        assert_eq!(config.present_mode, default.present_mode);
+
+        // A flag missing its value, at the end of the command line
+        for flag in [
+            "--power-preference",
+            "--adapter-name",
+            "--surface-format",
+            "--present-mode",
+            "--texture",
+        ] {
+            let config = from_args(&[flag]);
+            assert_eq!(config.power_preference, default.power_preference);
+            assert_eq!(config.adapter_name, None);
+            assert_eq!(config.surface_format, None);
+            assert_eq!(config.present_mode, default.present_mode);
+            assert_eq!(config.texture_path, None);
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+use super::readback;
+use super::shader::{self, ShaderStage};
+
+/// Size of each half of the demo texture, in pixels
+const HALF_WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Side by side comparison of standard and conservative rasterization.
+/// Standard rasterization only shades the pixels whose center is inside
+/// a triangle, while conservative rasterization shades every pixel the
+/// triangle touches, even partially. Thin or tiny triangles can't "fall
+/// through the cracks", which is what voxelization and GPU collision
+/// detection rely on.
+/// The same triangles are rendered once into a tiny texture, standard on
+/// the left half and conservative on the right, which is then scaled up
+/// to the whole window so that the individual pixels can be compared.
+pub struct ConservativeRasterDemo {
+    // Only sampled through the bind group, kept for the memory registry
+    _texture: Tracked<wgpu::Texture>,
+    display_pipeline_layout: wgpu::PipelineLayout,
+    display_pipeline: wgpu::RenderPipeline,
+    display_bind_group: wgpu::BindGroup,
+}
+
+impl ConservativeRasterDemo {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        output_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let texture = memory.create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some("Conservative Raster Texture"),
+                size: wgpu::Extent3d {
+                    width: HALF_WIDTH * 2,
+                    height: HEIGHT,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                // COPY_SRC to count the covered pixels
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            },
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // The triangles never change, so they're only rendered once
+        let conservative_drawn = render_triangles(device, queue, &view);
+        print_coverage(device, queue, memory, &texture, conservative_drawn);
+
+        let display_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Conservative Raster Display Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            }],
+        });
+        let display_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Conservative Raster Display Bind Group"),
+            layout: &display_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            }],
+        });
+        let display_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Conservative Raster Display Pipeline Layout"),
+                bind_group_layouts: &[&display_layout],
+                push_constant_ranges: &[],
+            });
+        let display_pipeline = create_display_pipeline(
+            device,
+            &display_pipeline_layout,
+            output_format,
+            sample_count,
+        );
+
+        Self {
+            _texture: texture,
+            display_pipeline_layout,
+            display_pipeline,
+            display_bind_group,
+        }
+    }
+
+    /// The display pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_display_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.display_pipeline = create_display_pipeline(
+            device,
+            &self.display_pipeline_layout,
+            output_format,
+            sample_count,
+        );
+    }
+
+    /// Draw the scaled up comparison over the whole render target
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.display_pipeline);
+        render_pass.set_bind_group(0, &self.display_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Render the test triangles in both halves of 'view'.
+/// Without CONSERVATIVE_RASTERIZATION the right half is left empty
+/// and this returns false.
+fn render_triangles(device: &wgpu::Device, queue: &wgpu::Queue, view: &wgpu::TextureView) -> bool {
+    let shader = shader::create_shader_module(
+        device,
+        "Conservative Raster Shader",
+        include_str!("conservative.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+    // The triangles are hard-coded in the vertex shader
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Conservative Raster Pipeline Layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+    let create_pipeline = |label, conservative| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(FORMAT.into())],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                // This is the only difference between the two pipelines
+                conservative,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        })
+    };
+
+    let standard_pipeline = create_pipeline("Standard Raster Pipeline", false);
+    let conservative_pipeline = if device
+        .features()
+        .contains(wgpu::Features::CONSERVATIVE_RASTERIZATION)
+    {
+        Some(create_pipeline("Conservative Raster Pipeline", true))
+    } else {
+        log::warn!(
+            "Conservative rasterization is not supported by this device, \
+            only the standard rasterization half of the demo will be drawn"
+        );
+        None
+    };
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Conservative Raster Encoder"),
+    });
+    {
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Conservative Raster Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+
+        // Each half gets its own viewport, so the triangles land
+        // on exactly the same pixels in both
+        let halves = [Some(&standard_pipeline), conservative_pipeline.as_ref()];
+        for (i, pipeline) in halves.into_iter().enumerate() {
+            if let Some(pipeline) = pipeline {
+                let x = (i as u32 * HALF_WIDTH) as f32;
+                render_pass.set_viewport(x, 0.0, HALF_WIDTH as f32, HEIGHT as f32, 0.0, 1.0);
+                render_pass.set_pipeline(pipeline);
+                render_pass.draw(0..6, 0..1);
+            }
+        }
+    }
+    queue.submit(Some(encoder.finish()));
+
+    conservative_pipeline.is_some()
+}
+
+/// Print how many pixels each half of the demo texture covers
+fn print_coverage(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    memory: &MemoryRegistry,
+    texture: &wgpu::Texture,
+    conservative_drawn: bool,
+) {
+    let count = |x| {
+        readback::read_texture_region(device, queue, memory, texture, (x, 0, HALF_WIDTH, HEIGHT))
+            // Rgba8: the red channel of each covered pixel is 255
+            .map(|texels| texels.chunks(4).filter(|texel| texel[0] > 0).count())
+    };
+
+    let result = count(0).and_then(|standard| {
+        let conservative = if conservative_drawn {
+            format!("{} with conservative rasterization", count(HALF_WIDTH)?)
+        } else {
+            "conservative rasterization is not supported".to_string()
+        };
+        println!(
+            "Conservative raster demo: {standard} pixels covered with \
+             standard rasterization, {conservative}"
+        );
+        Ok(())
+    });
+    if let Err(e) = result {
+        log::warn!("Failed to read back the demo texture: {e}");
+    }
+}
+
+fn create_display_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    output_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    fullscreen::create_fullscreen_pipeline(
+        device,
+        "Conservative Raster Display Pipeline",
+        Some(layout),
+        output_format,
+        sample_count,
+        include_str!("conservative_display.wgsl"),
+        "fs_conservative",
+    )
+}
@@ -0,0 +1,26 @@
+// Draws the test triangles of the conservative rasterization demo.
+// They're rendered into a tiny texture, so every covered pixel is
+// clearly visible once the texture gets scaled up to the window.
+
+const POSITIONS = array<vec2<f32>, 6>(
+    // A long thin sliver: standard rasterization leaves gaps along it
+    vec2<f32>(-0.9, -0.9),
+    vec2<f32>(0.9, -0.45),
+    vec2<f32>(0.9, -0.35),
+    // A triangle smaller than a pixel that doesn't cover any pixel center
+    vec2<f32>(0.02, 0.52),
+    vec2<f32>(0.08, 0.52),
+    vec2<f32>(0.05, 0.58),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // Constant arrays can only be indexed through a variable in naga
+    var positions = POSITIONS;
+    return vec4<f32>(positions[index], 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.8, 0.0, 1.0);
+}
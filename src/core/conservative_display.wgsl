@@ -0,0 +1,22 @@
+// Appended to fullscreen.wgsl: shows the conservative rasterization demo
+// texture, one big square per texel with a grid in between
+
+@group(0) @binding(0) var pixels: texture_2d<f32>;
+
+const GRID_COLOR = vec4<f32>(0.2, 0.2, 0.2, 1.0);
+const GRID_WIDTH = 0.05;
+
+@fragment
+fn fs_conservative(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(pixels));
+    let texel = in.uv * size;
+
+    let within_texel = fract(texel);
+    if (any(within_texel < vec2<f32>(GRID_WIDTH)) || any(within_texel > vec2<f32>(1.0 - GRID_WIDTH))) {
+        return GRID_COLOR;
+    }
+
+    // No filtering, we want to see the exact pixels
+    let pixel = min(vec2<u32>(texel), vec2<u32>(size) - 1u);
+    return textureLoad(pixels, pixel, 0);
+}
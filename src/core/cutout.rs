@@ -0,0 +1,128 @@
+use super::depth;
+use super::shader::{self, ShaderStage};
+
+/// Side by side comparison of the two ways to draw cutouts like foliage.
+/// Discarding the fragments below an alpha threshold leaves hard, aliased
+/// edges, that MSAA can't help with since it only smooths triangle edges.
+/// Alpha to coverage instead turns the alpha into how many of the MSAA
+/// samples of the pixel are covered, which antialiases the cutout edges
+/// for free and, unlike alpha blending, doesn't need sorting.
+/// The left quad uses discard and the right one alpha to coverage.
+pub struct CutoutDemo {
+    alpha_to_coverage: bool,
+    discard_pipeline: wgpu::RenderPipeline,
+    coverage_pipeline: wgpu::RenderPipeline,
+}
+
+impl CutoutDemo {
+    /// 'alpha_to_coverage' can be turned off to compare with discard on both sides
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        alpha_to_coverage: bool,
+    ) -> Self {
+        let (discard_pipeline, coverage_pipeline) =
+            create_pipelines(device, format, sample_count, alpha_to_coverage);
+
+        Self {
+            alpha_to_coverage,
+            discard_pipeline,
+            coverage_pipeline,
+        }
+    }
+
+    /// The pipelines have to match the render target,
+    /// so they're rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipelines(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        (self.discard_pipeline, self.coverage_pipeline) =
+            create_pipelines(device, format, sample_count, self.alpha_to_coverage);
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.discard_pipeline);
+        render_pass.draw(0..6, 0..1);
+        render_pass.set_pipeline(&self.coverage_pipeline);
+        render_pass.draw(0..6, 1..2);
+    }
+}
+
+/// The discard pipeline and the alpha to coverage one.
+/// Without MSAA there are no samples to cover, so the second one
+/// falls back to discard too.
+fn create_pipelines(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    alpha_to_coverage: bool,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let alpha_to_coverage = if alpha_to_coverage && sample_count == 1 {
+        log::info!("Alpha to coverage needs MSAA, the cutout demo uses discard on both sides");
+        false
+    } else {
+        alpha_to_coverage
+    };
+
+    let shader = shader::create_shader_module(
+        device,
+        "Cutout Shader",
+        include_str!("cutout.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_discard", ShaderStage::Fragment),
+            ("fs_coverage", ShaderStage::Fragment),
+        ],
+    );
+    // The quads are generated in the vertex shader, there's nothing to bind
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Cutout Pipeline Layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+    let create_pipeline = |label, fragment_entry, alpha_to_coverage_enabled| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fragment_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: depth::depth_ignored(),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                alpha_to_coverage_enabled,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+    };
+
+    let discard_pipeline = create_pipeline("Cutout Discard Pipeline", "fs_discard", false);
+    let coverage_pipeline = if alpha_to_coverage {
+        create_pipeline("Cutout Coverage Pipeline", "fs_coverage", true)
+    } else {
+        create_pipeline("Cutout Coverage Pipeline", "fs_discard", false)
+    };
+
+    (discard_pipeline, coverage_pipeline)
+}
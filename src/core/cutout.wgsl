@@ -0,0 +1,70 @@
+// Draws two quads covered in procedural grass blades, to compare the two
+// ways of doing cutout transparency: the left one discards the fragments
+// below an alpha threshold, the right one relies on alpha to coverage.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+);
+
+const BLADES = 7.0;
+const BLADE_COLOR = vec3<f32>(0.15, 0.55, 0.1);
+const ALPHA_THRESHOLD = 0.5;
+
+// The first instance is the left quad, the second one the right quad
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    // Constant arrays can only be indexed through a variable in naga
+    var corners = CORNERS;
+    let corner = corners[vertex_index];
+    let left = -0.9 + f32(instance_index) * 0.95;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(left + corner.x * 0.85, -0.6 + corner.y * 1.2, 0.0, 1.0);
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    return out;
+}
+
+// Thin triangular blades, getting narrower towards the top.
+// Returns the distance to the closest blade edge, positive inside a blade.
+fn blade_edge(uv: vec2<f32>) -> f32 {
+    let distance = abs(fract(uv.x * BLADES) - 0.5);
+    let half_width = uv.y * 0.35;
+    return half_width - distance;
+}
+
+// Gives the edges a one pixel wide alpha ramp, which is what alpha to
+// coverage turns into partially covered pixels.
+// 'edge_width' is fwidth(edge): derivatives are only allowed in the fragment
+// entry points, since the GL backend puts helper functions in every stage.
+fn blade_alpha(edge: f32, edge_width: f32) -> f32 {
+    return clamp(edge / edge_width + 0.5, 0.0, 1.0);
+}
+
+@fragment
+fn fs_discard(in: VertexOutput) -> @location(0) vec4<f32> {
+    let edge = blade_edge(in.uv);
+    if (blade_alpha(edge, fwidth(edge)) < ALPHA_THRESHOLD) {
+        discard;
+    }
+    return vec4<f32>(BLADE_COLOR, 1.0);
+}
+
+@fragment
+fn fs_coverage(in: VertexOutput) -> @location(0) vec4<f32> {
+    let edge = blade_edge(in.uv);
+    // With alpha to coverage the alpha picks how many MSAA samples get written
+    return vec4<f32>(BLADE_COLOR, blade_alpha(edge, fwidth(edge)));
+}
@@ -0,0 +1,42 @@
+/// Anything that can record debug groups. These show up as named,
+/// nested regions in GPU debuggers like RenderDoc or Xcode.
+pub trait DebugGroups {
+    fn push_debug_group(&mut self, label: &str);
+    fn pop_debug_group(&mut self);
+}
+
+impl DebugGroups for wgpu::CommandEncoder {
+    fn push_debug_group(&mut self, label: &str) {
+        wgpu::CommandEncoder::push_debug_group(self, label);
+    }
+    fn pop_debug_group(&mut self) {
+        wgpu::CommandEncoder::pop_debug_group(self);
+    }
+}
+
+impl DebugGroups for wgpu::RenderPass<'_> {
+    fn push_debug_group(&mut self, label: &str) {
+        wgpu::RenderPass::push_debug_group(self, label);
+    }
+    fn pop_debug_group(&mut self) {
+        wgpu::RenderPass::pop_debug_group(self);
+    }
+}
+
+impl DebugGroups for wgpu::ComputePass<'_> {
+    fn push_debug_group(&mut self, label: &str) {
+        wgpu::ComputePass::push_debug_group(self, label);
+    }
+    fn pop_debug_group(&mut self) {
+        wgpu::ComputePass::pop_debug_group(self);
+    }
+}
+
+/// Run 'f' inside a debug group named 'label', making sure
+/// that every push is matched by a pop
+pub fn debug_group<P: DebugGroups, R>(pass: &mut P, label: &str, f: impl FnOnce(&mut P) -> R) -> R {
+    pass.push_debug_group(label);
+    let result = f(pass);
+    pass.pop_debug_group();
+    result
+}
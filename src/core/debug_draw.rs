@@ -0,0 +1,409 @@
+use super::depth;
+use super::memory::{MemoryRegistry, Tracked};
+use super::shader::{self, ShaderStage};
+use super::upload::BufferWriter;
+
+/// Straight pieces of each of the three circles of a sphere
+const SPHERE_SEGMENTS: usize = 24;
+
+/// Relative to the length of the arrow, for its head
+const ARROW_HEAD_LENGTH: f32 = 0.2;
+const ARROW_HEAD_RADIUS: f32 = 0.07;
+
+/// Room for a few hundred lines before the vertex buffer has to grow
+const INITIAL_VERTEX_CAPACITY: usize = 1024;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+struct LineVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+
+/// An axis-aligned box, from its smallest corner to its largest one
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: [f32; 3],
+    pub max: [f32; 3],
+}
+
+/// The lines queued for one of the depth modes of `DebugDraw`,
+/// in world space
+#[derive(Debug, Default)]
+pub struct DebugLines {
+    // Two per line
+    vertices: Vec<LineVertex>,
+}
+
+impl DebugLines {
+    pub fn line(&mut self, a: [f32; 3], b: [f32; 3], color: [f32; 4]) {
+        self.vertices.extend_from_slice(&[
+            LineVertex { position: a, color },
+            LineVertex { position: b, color },
+        ]);
+    }
+
+    /// Three circles, one around each axis
+    pub fn sphere(&mut self, center: [f32; 3], radius: f32, color: [f32; 4]) {
+        for axis in 0..3 {
+            let point = |segment: usize| {
+                let angle = segment as f32 / SPHERE_SEGMENTS as f32 * std::f32::consts::TAU;
+                let (sin, cos) = angle.sin_cos();
+                let mut point = center;
+                point[(axis + 1) % 3] += cos * radius;
+                point[(axis + 2) % 3] += sin * radius;
+                point
+            };
+            for segment in 0..SPHERE_SEGMENTS {
+                self.line(point(segment), point(segment + 1), color);
+            }
+        }
+    }
+
+    /// The 12 edges of 'aabb'
+    pub fn aabb(&mut self, aabb: Aabb, color: [f32; 4]) {
+        // Bit i of a corner index picks the max side along axis i
+        let corner = |index: usize| -> [f32; 3] {
+            std::array::from_fn(|i| {
+                if index & (1 << i) == 0 {
+                    aabb.min[i]
+                } else {
+                    aabb.max[i]
+                }
+            })
+        };
+        for index in 0..8 {
+            for axis in 0..3 {
+                // Each edge once, from the corner on its min side
+                if index & (1 << axis) == 0 {
+                    self.line(corner(index), corner(index | (1 << axis)), color);
+                }
+            }
+        }
+    }
+
+    /// A line from 'from' to 'to', with a four-sided head at 'to'
+    pub fn arrow(&mut self, from: [f32; 3], to: [f32; 3], color: [f32; 4]) {
+        self.line(from, to, color);
+
+        let direction = sub(to, from);
+        let length = dot(direction, direction).sqrt();
+        if length == 0.0 {
+            return;
+        }
+        let direction = scale(direction, 1.0 / length);
+        // Any vector that isn't parallel to the arrow gives a perpendicular one
+        let other = if direction[0].abs() < 0.9 {
+            [1.0, 0.0, 0.0]
+        } else {
+            [0.0, 1.0, 0.0]
+        };
+        let side = normalize(cross(direction, other));
+        let up = cross(direction, side);
+
+        let base = sub(to, scale(direction, length * ARROW_HEAD_LENGTH));
+        let radius = length * ARROW_HEAD_RADIUS;
+        for offset in [side, up, scale(side, -1.0), scale(up, -1.0)] {
+            self.line(to, add(base, scale(offset, radius)), color);
+        }
+    }
+}
+
+/// Immediate mode debug drawing, like in game engines: lines, spheres, boxes
+/// and arrows are queued from anywhere during the frame, uploaded and drawn
+/// by one draw call per depth mode, then forgotten. Nothing is kept from a
+/// frame to the next, what's still needed has to be queued again.
+/// The depth-tested lines are hidden by the scene in front of them, the
+/// on-top ones are drawn over everything.
+pub struct DebugDraw {
+    depth_tested: DebugLines,
+    on_top: DebugLines,
+    pipeline_layout: wgpu::PipelineLayout,
+    depth_tested_pipeline: wgpu::RenderPipeline,
+    on_top_pipeline: wgpu::RenderPipeline,
+    // The depth-tested lines first, then the on-top ones
+    vertex_buffer: Tracked<wgpu::Buffer>,
+    // Where the vertex buffer is created again when it has to grow
+    memory: MemoryRegistry,
+    depth_tested_count: u32,
+    on_top_count: u32,
+}
+
+impl DebugDraw {
+    /// The lines are seen through the camera bound with 'camera_bind_group_layout'
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Debug Draw Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let (depth_tested_pipeline, on_top_pipeline) =
+            create_pipelines(device, &pipeline_layout, format, sample_count);
+
+        Self {
+            depth_tested: DebugLines::default(),
+            on_top: DebugLines::default(),
+            pipeline_layout,
+            depth_tested_pipeline,
+            on_top_pipeline,
+            vertex_buffer: create_vertex_buffer(
+                device,
+                memory,
+                INITIAL_VERTEX_CAPACITY * std::mem::size_of::<LineVertex>(),
+            ),
+            memory: memory.clone(),
+            depth_tested_count: 0,
+            on_top_count: 0,
+        }
+    }
+
+    /// The pipelines have to match the render target,
+    /// so they're rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipelines(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        (self.depth_tested_pipeline, self.on_top_pipeline) =
+            create_pipelines(device, &self.pipeline_layout, format, sample_count);
+    }
+
+    /// What's queued here is hidden behind the scene
+    pub fn depth_tested(&mut self) -> &mut DebugLines {
+        &mut self.depth_tested
+    }
+
+    /// What's queued here is always visible
+    pub fn on_top(&mut self) -> &mut DebugLines {
+        &mut self.on_top
+    }
+
+    /// Upload the lines queued this frame, and start over for the next one
+    pub fn prepare(&mut self, writer: &mut BufferWriter) {
+        self.depth_tested_count = self.depth_tested.vertices.len() as u32;
+        self.on_top_count = self.on_top.vertices.len() as u32;
+        let vertices = [
+            self.depth_tested.vertices.as_slice(),
+            self.on_top.vertices.as_slice(),
+        ]
+        .concat();
+
+        let bytes: &[u8] = bytemuck::cast_slice(&vertices);
+        if bytes.len() as wgpu::BufferAddress > self.vertex_buffer.size() {
+            // Grow in powers of 2, so that it doesn't get recreated every frame
+            self.vertex_buffer =
+                create_vertex_buffer(writer.device, &self.memory, bytes.len().next_power_of_two());
+        }
+        writer.write(&self.vertex_buffer, 0, bytes);
+
+        self.depth_tested.vertices.clear();
+        self.on_top.vertices.clear();
+    }
+
+    /// Expects the camera bind group at group 0. Goes after the rest of the
+    /// scene, for the on-top lines to be drawn over it.
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if self.depth_tested_count + self.on_top_count == 0 {
+            return;
+        }
+
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        if self.depth_tested_count > 0 {
+            render_pass.set_pipeline(&self.depth_tested_pipeline);
+            render_pass.draw(0..self.depth_tested_count, 0..1);
+        }
+        if self.on_top_count > 0 {
+            render_pass.set_pipeline(&self.on_top_pipeline);
+            render_pass.draw(
+                self.depth_tested_count..self.depth_tested_count + self.on_top_count,
+                0..1,
+            );
+        }
+    }
+}
+
+fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] + b[i])
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    std::array::from_fn(|i| a[i] - b[i])
+}
+
+fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    a.map(|value| value * s)
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn normalize(a: [f32; 3]) -> [f32; 3] {
+    scale(a, 1.0 / dot(a, a).sqrt())
+}
+
+fn create_vertex_buffer(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    size: usize,
+) -> Tracked<wgpu::Buffer> {
+    memory.create_buffer(
+        device,
+        &wgpu::BufferDescriptor {
+            label: Some("Debug Draw Vertex Buffer"),
+            size: size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        },
+    )
+}
+
+/// The depth-tested pipeline and the on-top one, which only differ by their
+/// depth state
+fn create_pipelines(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let shader = shader::create_shader_module(
+        device,
+        "Debug Draw Shader",
+        include_str!("debug_draw.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+    let vertex_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<LineVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4],
+    };
+    let create_pipeline = |label, depth_stencil| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: std::slice::from_ref(&vertex_layout),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // One pixel wide whatever the distance, the only width all the
+            // backends can draw lines with
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil,
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+    };
+
+    (
+        create_pipeline("Debug Draw Depth Tested Pipeline", depth::depth_tested()),
+        create_pipeline("Debug Draw On Top Pipeline", depth::depth_ignored()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &DebugLines) -> Vec<([f32; 3], [f32; 3])> {
+        lines
+            .vertices
+            .chunks(2)
+            .map(|pair| (pair[0].position, pair[1].position))
+            .collect()
+    }
+
+    #[test]
+    fn boxes_have_twelve_edges_along_the_axes() {
+        let mut debug_lines = DebugLines::default();
+        debug_lines.aabb(
+            Aabb {
+                min: [-1.0, -2.0, -3.0],
+                max: [1.0, 2.0, 3.0],
+            },
+            [1.0; 4],
+        );
+
+        let edges = lines(&debug_lines);
+        assert_eq!(edges.len(), 12);
+        for (a, b) in &edges {
+            let changed = (0..3).filter(|&i| a[i] != b[i]).count();
+            assert_eq!(changed, 1, "{a:?} to {b:?}");
+        }
+        // No edge twice
+        for (i, edge) in edges.iter().enumerate() {
+            assert!(!edges[i + 1..].contains(edge));
+        }
+    }
+
+    #[test]
+    fn spheres_and_arrows_stay_within_their_size() {
+        let mut debug_lines = DebugLines::default();
+        debug_lines.sphere([1.0, 0.0, 0.0], 2.0, [1.0; 4]);
+        assert_eq!(lines(&debug_lines).len(), 3 * SPHERE_SEGMENTS);
+        for vertex in &debug_lines.vertices {
+            let distance = dot(
+                sub(vertex.position, [1.0, 0.0, 0.0]),
+                sub(vertex.position, [1.0, 0.0, 0.0]),
+            )
+            .sqrt();
+            assert!((distance - 2.0).abs() < 1e-5);
+        }
+
+        let mut debug_lines = DebugLines::default();
+        debug_lines.arrow([0.0; 3], [0.0, 0.0, 2.0], [1.0; 4]);
+        let arrow = lines(&debug_lines);
+        assert_eq!(arrow.len(), 5);
+        assert_eq!(arrow[0], ([0.0; 3], [0.0, 0.0, 2.0]));
+        // The head goes back from the tip, around the shaft
+        for (tip, end) in &arrow[1..] {
+            assert_eq!(*tip, [0.0, 0.0, 2.0]);
+            assert!((end[2] - 1.6).abs() < 1e-5);
+            let radius = (end[0] * end[0] + end[1] * end[1]).sqrt();
+            assert!((radius - 0.14).abs() < 1e-5);
+        }
+
+        // Nothing to point at
+        let mut debug_lines = DebugLines::default();
+        debug_lines.arrow([1.0; 3], [1.0; 3], [1.0; 4]);
+        assert_eq!(lines(&debug_lines).len(), 1);
+    }
+}
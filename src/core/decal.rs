@@ -0,0 +1,159 @@
+use super::depth;
+use super::shader::{self, ShaderStage};
+
+/// A decal lying exactly on a floor, to show how a depth bias fixes the
+/// z-fighting of coplanar geometry. The floor is drawn first, then the decal
+/// with its depth pulled towards the camera by 'bias', so that it always wins
+/// the depth test against the floor it's on.
+/// The bias can be turned off to see the two flicker through each other.
+pub struct DecalDemo {
+    bias: wgpu::DepthBiasState,
+    biased: bool,
+    floor_pipeline: wgpu::RenderPipeline,
+    decal_pipeline: wgpu::RenderPipeline,
+}
+
+impl DecalDemo {
+    /// The quads are seen through the camera bound with 'camera_bind_group_layout'
+    pub fn new(
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        bias: wgpu::DepthBiasState,
+    ) -> Self {
+        let (floor_pipeline, decal_pipeline) =
+            create_pipelines(device, camera_bind_group_layout, format, sample_count, bias);
+
+        Self {
+            bias,
+            biased: true,
+            floor_pipeline,
+            decal_pipeline,
+        }
+    }
+
+    /// The pipelines have to match the render target,
+    /// so they're rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipelines(
+        &mut self,
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        (self.floor_pipeline, self.decal_pipeline) = create_pipelines(
+            device,
+            camera_bind_group_layout,
+            format,
+            sample_count,
+            self.current_bias(),
+        );
+    }
+
+    pub fn is_biased(&self) -> bool {
+        self.biased
+    }
+
+    /// The bias is part of the pipeline, changing it means rebuilding them
+    pub fn set_biased(
+        &mut self,
+        biased: bool,
+        device: &wgpu::Device,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.biased = biased;
+        self.rebuild_pipelines(device, camera_bind_group_layout, format, sample_count);
+    }
+
+    fn current_bias(&self) -> wgpu::DepthBiasState {
+        if self.biased {
+            self.bias
+        } else {
+            wgpu::DepthBiasState::default()
+        }
+    }
+
+    /// Expects the camera bind group at group 0
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_pipeline(&self.floor_pipeline);
+        render_pass.draw(0..6, 0..1);
+        render_pass.set_pipeline(&self.decal_pipeline);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+/// The floor pipeline and the decal one, which only differ by their depth bias
+fn create_pipelines(
+    device: &wgpu::Device,
+    camera_bind_group_layout: &wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    decal_bias: wgpu::DepthBiasState,
+) -> (wgpu::RenderPipeline, wgpu::RenderPipeline) {
+    let shader = shader::create_shader_module(
+        device,
+        "Decal Shader",
+        include_str!("decal.wgsl"),
+        &[
+            ("vs_floor", ShaderStage::Vertex),
+            ("vs_decal", ShaderStage::Vertex),
+            ("fs_floor", ShaderStage::Fragment),
+            ("fs_decal", ShaderStage::Fragment),
+        ],
+    );
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Decal Pipeline Layout"),
+        bind_group_layouts: &[camera_bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let create_pipeline = |label, vertex_entry, fragment_entry, bias| {
+        device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(label),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: vertex_entry,
+                buffers: &[],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: fragment_entry,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            // Seen from above and below when orbiting around
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                cull_mode: None,
+                ..Default::default()
+            },
+            depth_stencil: depth::depth_tested_with_bias(bias),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+    };
+
+    let floor_pipeline = create_pipeline(
+        "Floor Pipeline",
+        "vs_floor",
+        "fs_floor",
+        wgpu::DepthBiasState::default(),
+    );
+    let decal_pipeline = create_pipeline("Decal Pipeline", "vs_decal", "fs_decal", decal_bias);
+
+    (floor_pipeline, decal_pipeline)
+}
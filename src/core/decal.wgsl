@@ -0,0 +1,81 @@
+// A checkered floor under the mesh, with a round decal lying exactly on it.
+// Both are quads generated here, the decal being turned by 45 degrees so its
+// triangles don't line up with the ones of the floor: their depths are then
+// interpolated differently, and without a depth bias the two fight over
+// which one is closest.
+
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniforms;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // From -1 to 1 across the quad
+    @location(0) uv: vec2<f32>,
+};
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+);
+
+// Below the mesh, which spins around the origin in the XY plane
+const FLOOR_HEIGHT = -0.75;
+const FLOOR_HALF_SIZE = 3.0;
+const FLOOR_SQUARES = 6.0;
+const DECAL_HALF_SIZE = 0.8;
+const DECAL_COLOR = vec3<f32>(0.85, 0.2, 0.1);
+
+// Turns 'corner' by 'angle' radians and lays it flat at the floor height
+fn on_floor(corner: vec2<f32>, half_size: f32, angle: f32) -> vec4<f32> {
+    let rotation = mat2x2<f32>(cos(angle), sin(angle), -sin(angle), cos(angle));
+    let xz = rotation * corner * half_size;
+    return camera.view_projection * vec4<f32>(xz.x, FLOOR_HEIGHT, xz.y, 1.0);
+}
+
+@vertex
+fn vs_floor(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Constant arrays can only be indexed through a variable in naga
+    var corners = CORNERS;
+    let corner = corners[vertex_index];
+
+    var out: VertexOutput;
+    out.position = on_floor(corner, FLOOR_HALF_SIZE, 0.0);
+    out.uv = corner;
+    return out;
+}
+
+@vertex
+fn vs_decal(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var corners = CORNERS;
+    let corner = corners[vertex_index];
+
+    var out: VertexOutput;
+    out.position = on_floor(corner, DECAL_HALF_SIZE, 0.785398);
+    out.uv = corner;
+    return out;
+}
+
+@fragment
+fn fs_floor(in: VertexOutput) -> @location(0) vec4<f32> {
+    let square = floor((in.uv + 1.0) * 0.5 * FLOOR_SQUARES);
+    let shade = select(0.35, 0.5, (square.x + square.y) % 2.0 == 0.0);
+    return vec4<f32>(vec3<f32>(shade), 1.0);
+}
+
+// A ring around a dot, the rest of the quad is cut out
+@fragment
+fn fs_decal(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.uv);
+    if (distance > 1.0 || (distance > 0.3 && distance < 0.6)) {
+        discard;
+    }
+    return vec4<f32>(DECAL_COLOR, 1.0);
+}
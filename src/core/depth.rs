@@ -0,0 +1,82 @@
+use super::memory::{MemoryRegistry, Tracked};
+
+/// Format of the depth buffer of the main pass
+pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+/// Create a depth buffer. It must match the color target it's used with:
+/// same size, and same sample count.
+/// When 'sampled', it can also be bound as a texture after the pass.
+pub fn create_depth_texture(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    width: u32,
+    height: u32,
+    sample_count: u32,
+    sampled: bool,
+) -> (Tracked<wgpu::Texture>, wgpu::TextureView) {
+    let usage = if sampled {
+        wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+    } else {
+        wgpu::TextureUsages::RENDER_ATTACHMENT
+    };
+    let texture = memory.create_texture(
+        device,
+        &wgpu::TextureDescriptor {
+            label: Some("Depth Texture"),
+            size: wgpu::Extent3d {
+                width: width.max(1),
+                height: height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage,
+            view_formats: &[],
+        },
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    (texture, view)
+}
+
+/// Whether a depth buffer with 'sample_count' samples can be sampled.
+/// The GL backend gives multisampled textures the target of plain ones,
+/// which leaves them empty when sampled: they're only rendered into there.
+pub fn is_sampleable(adapter: &wgpu::Adapter, sample_count: u32) -> bool {
+    sample_count == 1 || adapter.get_info().backend != wgpu::Backend::Gl
+}
+
+/// For geometry that hides what's behind it: the closest fragment wins
+pub fn depth_tested() -> Option<wgpu::DepthStencilState> {
+    depth_tested_with_bias(wgpu::DepthBiasState::default())
+}
+
+/// Same as depth_tested(), with the depth of every fragment offset by 'bias'.
+/// Negative values bring geometry closer to the camera, which keeps what's
+/// coplanar with a surface, like decals, from z-fighting with it.
+/// 'slope_scale' scales with how steep the triangle is seen from the camera,
+/// and 'constant' is in units of the smallest depth difference.
+pub fn depth_tested_with_bias(bias: wgpu::DepthBiasState) -> Option<wgpu::DepthStencilState> {
+    Some(wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: true,
+        depth_compare: wgpu::CompareFunction::Less,
+        stencil: wgpu::StencilState::default(),
+        bias,
+    })
+}
+
+/// For what's drawn in the main pass without taking part in depth testing,
+/// like fullscreen backgrounds and overlays. They still need a depth state:
+/// every pipeline used in a pass must match its depth attachment.
+pub fn depth_ignored() -> Option<wgpu::DepthStencilState> {
+    Some(wgpu::DepthStencilState {
+        format: DEPTH_FORMAT,
+        depth_write_enabled: false,
+        depth_compare: wgpu::CompareFunction::Always,
+        stencil: wgpu::StencilState::default(),
+        bias: wgpu::DepthBiasState::default(),
+    })
+}
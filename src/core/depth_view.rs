@@ -0,0 +1,180 @@
+use super::camera::Camera;
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+use super::upload::BufferWriter;
+
+/// Must match the `DepthViewUniforms` struct in depth_view.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct DepthViewUniforms {
+    near: f32,
+    far: f32,
+    white_distance: f32,
+    _padding: f32,
+}
+
+/// Debug view of the depth buffer of the main pass, linearized and shown in
+/// grayscale over the whole frame. It's drawn in a pass of its own after the
+/// main one, since the depth buffer can't be sampled while it's attached.
+pub struct DepthView {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    uniforms_buffer: Tracked<wgpu::Buffer>,
+}
+
+impl DepthView {
+    /// 'sample_count' is the one of the depth buffer: the view itself is
+    /// drawn into the resolved frame
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let uniforms_buffer = memory.create_buffer(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("Depth View Uniforms Buffer"),
+                size: std::mem::size_of::<DepthViewUniforms>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        );
+        let (bind_group_layout, pipeline) = create_pipeline(device, format, sample_count);
+
+        Self {
+            bind_group_layout,
+            pipeline,
+            uniforms_buffer,
+        }
+    }
+
+    /// The depth buffer is declared multisampled or not in the shader,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        (self.bind_group_layout, self.pipeline) = create_pipeline(device, format, sample_count);
+    }
+
+    /// Upload the planes of the 'camera' the depth is rendered with.
+    /// White is twice as far as its target, to show the depth around it.
+    pub fn prepare(&self, writer: &mut BufferWriter, camera: &Camera) {
+        let uniforms = DepthViewUniforms {
+            near: camera.znear,
+            far: camera.zfar,
+            white_distance: (camera.distance() * 2.0).min(camera.zfar),
+            _padding: 0.0,
+        };
+        writer.write(&self.uniforms_buffer, 0, bytemuck::bytes_of(&uniforms));
+    }
+
+    /// Draw 'depth_view' into 'target', which it covers entirely.
+    /// The bind group is created here, since the depth buffer is recreated
+    /// with every resize.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        target: &wgpu::TextureView,
+        depth_view: &wgpu::TextureView,
+    ) {
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Depth View Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(depth_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: self.uniforms_buffer.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth View Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> (wgpu::BindGroupLayout, wgpu::RenderPipeline) {
+    let multisampled = sample_count > 1;
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Depth View Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                // Read with textureLoad(), as a float texture: the GL backend
+                // can't load from depth textures
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: wgpu::BufferSize::new(
+                        std::mem::size_of::<DepthViewUniforms>() as wgpu::BufferAddress,
+                    ),
+                },
+                count: None,
+            },
+        ],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Depth View Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+
+    // The shader is the same either way, only the declaration of 'depth' changes
+    let depth_declaration = if multisampled {
+        "@group(0) @binding(0) var depth: texture_multisampled_2d<f32>;"
+    } else {
+        "@group(0) @binding(0) var depth: texture_2d<f32>;"
+    };
+    let pipeline = fullscreen::create_fullscreen_pipeline_with_target(
+        device,
+        "Depth View Pipeline",
+        Some(&layout),
+        wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        },
+        1,
+        &format!("{depth_declaration}\n{}", include_str!("depth_view.wgsl")),
+        "fs_depth_view",
+    );
+
+    (bind_group_layout, pipeline)
+}
@@ -0,0 +1,38 @@
+// Appended to fullscreen.wgsl: shows the depth buffer in grayscale, from
+// black at the near plane to white at 'white_distance' and beyond.
+// 'depth' is declared by depth_view.rs, as a multisampled texture when
+// the depth buffer is one.
+
+// Padded to 16 bytes on the CPU side
+struct DepthViewUniforms {
+    // The near and far planes of the camera the depth was rendered with
+    near: f32,
+    far: f32,
+    // Distance from the eye shown as white
+    white_distance: f32,
+};
+
+@group(0) @binding(1)
+var<uniform> uniforms: DepthViewUniforms;
+
+// The depth buffer stores z / w, which puts most of its precision close
+// to the near plane. Undoing the projection gets back the distance along
+// the view direction.
+fn linearize_depth(depth: f32) -> f32 {
+    let near = uniforms.near;
+    let far = uniforms.far;
+    return near * far / (far - depth * (far - near));
+}
+
+@fragment
+fn fs_depth_view(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    let size = vec2<f32>(textureDimensions(depth));
+    let texel = min(vec2<u32>(in.uv * size), vec2<u32>(size) - 1u);
+    // The level of a plain texture and the sample of a multisampled one,
+    // the first sample is as good as any for debugging
+    let distance = linearize_depth(textureLoad(depth, texel, 0).r);
+
+    let near = uniforms.near;
+    let shade = clamp((distance - near) / (uniforms.white_distance - near), 0.0, 1.0);
+    return vec4<f32>(vec3<f32>(shade), 1.0);
+}
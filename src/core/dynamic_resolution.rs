@@ -0,0 +1,144 @@
+use std::time::Duration;
+
+/// Frames averaged into the frame time the scale is picked from
+const SMOOTHING: f32 = 0.1;
+/// Frames to wait after a change, for the frame times to reflect it
+const COOLDOWN_FRAMES: u32 = 30;
+/// The fraction of the target the frames aim for, so that small variations
+/// don't keep changing the scale
+const HEADROOM: f32 = 0.85;
+/// The scale changes by multiples of this
+const SCALE_STEP: f32 = 0.05;
+
+/// How to adapt the render scale to the frame times
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DynamicResolution {
+    /// The frame time to stay under, in milliseconds
+    pub target_ms: f32,
+    /// The render scale never goes below this...
+    pub min_scale: f32,
+    /// ...nor above this, e.g. 1 for up to the resolution of the window
+    pub max_scale: f32,
+}
+
+impl Default for DynamicResolution {
+    fn default() -> Self {
+        Self {
+            target_ms: 1000.0 / 60.0,
+            min_scale: 0.5,
+            max_scale: 1.0,
+        }
+    }
+}
+
+/// Lowers the render scale when the frames take longer than the target,
+/// and raises it back when there's time to spare.
+/// The time it takes to render a frame goes with its number of pixels,
+/// which goes with the square of the scale: the new scale is the one that
+/// should bring the frame time just under the target.
+#[derive(Debug)]
+pub struct ResolutionController {
+    settings: DynamicResolution,
+    // Moving average of the frame times since the last change
+    average_ms: Option<f32>,
+    cooldown: u32,
+}
+
+impl ResolutionController {
+    pub fn new(settings: DynamicResolution) -> Self {
+        Self {
+            settings,
+            average_ms: None,
+            cooldown: 0,
+        }
+    }
+
+    /// Take the time of the last frame, rendered at 'scale', into account.
+    /// Returns the scale to render at when it should change.
+    pub fn update(&mut self, frame_time: Duration, scale: f32) -> Option<f32> {
+        let ms = frame_time.as_secs_f32() * 1000.0;
+        let average = match self.average_ms {
+            Some(average) => average + (ms - average) * SMOOTHING,
+            None => ms,
+        };
+        self.average_ms = Some(average);
+        if self.cooldown > 0 {
+            self.cooldown -= 1;
+            return None;
+        }
+
+        let DynamicResolution {
+            target_ms,
+            min_scale,
+            max_scale,
+        } = self.settings;
+        if average > HEADROOM * target_ms && average <= target_ms {
+            return None;
+        }
+        let wanted = scale * (HEADROOM * target_ms / average.max(f32::EPSILON)).sqrt();
+        let new_scale = ((wanted / SCALE_STEP).floor() * SCALE_STEP).clamp(min_scale, max_scale);
+        if (new_scale - scale).abs() < SCALE_STEP / 2.0 {
+            return None;
+        }
+
+        // The frames rendered at the old scale don't tell anything anymore
+        self.average_ms = None;
+        self.cooldown = COOLDOWN_FRAMES;
+        Some(new_scale)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The scale 'controller' settles on, starting from 'scale' with frames
+    /// that take 'ms_at_native' milliseconds at a scale of 1
+    fn settle(controller: &mut ResolutionController, mut scale: f32, ms_at_native: f32) -> f32 {
+        for _ in 0..1000 {
+            let ms = ms_at_native * scale * scale;
+            if let Some(new_scale) = controller.update(Duration::from_secs_f32(ms / 1000.0), scale)
+            {
+                scale = new_scale;
+            }
+        }
+
+        scale
+    }
+
+    #[test]
+    fn scale_follows_the_frame_time_within_its_range() {
+        let settings = DynamicResolution {
+            target_ms: 16.0,
+            min_scale: 0.25,
+            max_scale: 1.5,
+        };
+        let mut controller = ResolutionController::new(settings);
+
+        // 32 ms at native: about 0.65 brings it just under 16 ms
+        let scale = settle(&mut controller, 1.0, 32.0);
+        let ms = 32.0 * scale * scale;
+        assert!(
+            ms <= 16.0 && ms > 16.0 * HEADROOM * 0.8,
+            "{scale} for {ms} ms"
+        );
+
+        // Back up once the frames get cheaper, up to the max
+        assert_eq!(settle(&mut controller, scale, 2.0), 1.5);
+        // Never below the min, however slow
+        assert_eq!(settle(&mut controller, 1.0, 1000.0), 0.25);
+    }
+
+    #[test]
+    fn scale_waits_for_the_frames_to_reflect_a_change() {
+        let mut controller = ResolutionController::new(DynamicResolution::default());
+        let slow = Duration::from_millis(40);
+
+        let lowered = controller.update(slow, 1.0).unwrap();
+        assert!(lowered < 1.0);
+        for _ in 0..COOLDOWN_FRAMES {
+            assert_eq!(controller.update(slow, lowered), None);
+        }
+        assert!(controller.update(slow, lowered).unwrap() < lowered);
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::Duration;
+
+/// Maps the progress of an animation, from 0 to 1, to how far along the
+/// value should be. Anything but `linear` makes the motion feel less robotic.
+pub type Easing = fn(f32) -> f32;
+
+pub fn linear(t: f32) -> f32 {
+    t
+}
+
+/// Starts slow and speeds up
+pub fn ease_in_cubic(t: f32) -> f32 {
+    t * t * t
+}
+
+/// Starts fast and slows down, good for things reacting to the user
+pub fn ease_out_cubic(t: f32) -> f32 {
+    1.0 - (1.0 - t).powi(3)
+}
+
+/// Slow at both ends, good for camera moves
+pub fn ease_in_out_cubic(t: f32) -> f32 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+/// Values that can be linearly interpolated
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl<const N: usize> Lerp for [f32; N] {
+    fn lerp(&self, other: &Self, t: f32) -> Self {
+        std::array::from_fn(|i| self[i].lerp(&other[i], t))
+    }
+}
+
+/// A value going from 'start' to 'end' over 'duration', driven by the frame time
+#[derive(Debug, Clone)]
+pub struct Animation<T> {
+    start: T,
+    end: T,
+    duration: Duration,
+    elapsed: Duration,
+    easing: Easing,
+}
+
+impl<T: Lerp> Animation<T> {
+    pub fn new(start: T, end: T, duration: Duration, easing: Easing) -> Self {
+        Self {
+            start,
+            end,
+            duration,
+            elapsed: Duration::ZERO,
+            easing,
+        }
+    }
+
+    /// An animation that's already finished, sitting at 'value'
+    pub fn still(value: T) -> Self
+    where
+        T: Clone,
+    {
+        Self::new(value.clone(), value, Duration::ZERO, linear)
+    }
+
+    /// Move the animation forward, returns true once it has finished
+    pub fn update(&mut self, dt: Duration) -> bool {
+        self.elapsed = (self.elapsed + dt).min(self.duration);
+        self.is_finished()
+    }
+
+    /// Where the animation is headed
+    pub fn end(&self) -> &T {
+        &self.end
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+
+    pub fn value(&self) -> T {
+        // A zero duration would divide by zero, it just means "already there"
+        let t = if self.duration.is_zero() {
+            1.0
+        } else {
+            self.elapsed.as_secs_f32() / self.duration.as_secs_f32()
+        };
+
+        self.start.lerp(&self.end, (self.easing)(t))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easings_start_at_0_and_end_at_1() {
+        let easings: [(&str, Easing); 4] = [
+            ("linear", linear),
+            ("ease_in_cubic", ease_in_cubic),
+            ("ease_out_cubic", ease_out_cubic),
+            ("ease_in_out_cubic", ease_in_out_cubic),
+        ];
+
+        for (name, easing) in easings {
+            assert!(easing(0.0).abs() < 1e-6, "{name}(0) = {}", easing(0.0));
+            assert!(
+                (easing(1.0) - 1.0).abs() < 1e-6,
+                "{name}(1) = {}",
+                easing(1.0)
+            );
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::time::Duration;
+
+use super::easing::{self, Animation};
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+use super::upload::BufferWriter;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct FadeUniforms {
+    color: [f32; 4],
+}
+
+/// Fades the frame in from black, e.g. to hide the frames of the wrong size
+/// while the window switches to fullscreen and back.
+/// The fade is drawn over the finished frame, after the scene is scaled to it.
+pub struct Fade {
+    bind_group: wgpu::BindGroup,
+    uniforms_buffer: Tracked<wgpu::Buffer>,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    // How much of the frame is hidden, None when nothing is
+    opacity: Option<Animation<f32>>,
+}
+
+impl Fade {
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let uniforms_buffer = memory.create_buffer(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("Fade Uniforms Buffer"),
+                size: std::mem::size_of::<FadeUniforms>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        );
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Fade Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Fade Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Fade Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            pipeline: create_pipeline(device, &pipeline_layout, format),
+            bind_group,
+            uniforms_buffer,
+            pipeline_layout,
+            opacity: None,
+        }
+    }
+
+    /// The pipeline has to match the format of the frame
+    pub fn rebuild_pipeline(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, format);
+    }
+
+    /// Start from a black frame, which clears over 'duration'
+    pub fn fade_in(&mut self, duration: Duration) {
+        // Stays dark while the window settles, then clears quickly
+        self.opacity = Some(Animation::new(1.0, 0.0, duration, easing::ease_in_cubic));
+    }
+
+    pub fn is_fading(&self) -> bool {
+        self.opacity.is_some()
+    }
+
+    /// How much of the frame is hidden, from 0 to 1
+    pub fn opacity(&self) -> f32 {
+        self.opacity.as_ref().map_or(0.0, Animation::value)
+    }
+
+    pub fn update(&mut self, dt: Duration) {
+        if let Some(opacity) = &mut self.opacity {
+            if opacity.update(dt) {
+                self.opacity = None;
+            }
+        }
+    }
+
+    pub fn prepare(&self, writer: &mut BufferWriter) {
+        if self.is_fading() {
+            let uniforms = FadeUniforms {
+                color: [0.0, 0.0, 0.0, self.opacity()],
+            };
+            writer.write(&self.uniforms_buffer, 0, bytemuck::bytes_of(&uniforms));
+        }
+    }
+
+    /// Draw over what's already in 'target'
+    pub fn encode(&self, encoder: &mut wgpu::CommandEncoder, target: &wgpu::TextureView) {
+        if !self.is_fading() {
+            return;
+        }
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Fade Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    fullscreen::create_fullscreen_pipeline_with_target(
+        device,
+        "Fade Pipeline",
+        Some(layout),
+        wgpu::ColorTargetState {
+            format,
+            blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+            write_mask: wgpu::ColorWrites::ALL,
+        },
+        1,
+        include_str!("fade.wgsl"),
+        "fs_fade",
+    )
+}
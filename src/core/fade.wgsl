@@ -0,0 +1,15 @@
+// Appended to fullscreen.wgsl: covers the frame with black, from not at all
+// at an opacity of 0 to completely at 1
+
+struct FadeUniforms {
+    // Only the alpha is used, a vec4 keeps the struct 16 bytes for WebGL
+    color: vec4<f32>,
+}
+
+@group(0) @binding(0)
+var<uniform> fade: FadeUniforms;
+
+@fragment
+fn fs_fade(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return fade.color;
+}
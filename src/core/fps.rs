@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+/// How often the counter reports a new value
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How much the latest frame counts in the average. The lower, the smoother:
+/// at 0.05 a single spike barely shows, but a lasting change takes a few
+/// dozen frames to come through.
+const SMOOTHING: f64 = 0.05;
+
+/// Frames per second, averaged over the recent frames
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FpsReport {
+    pub fps: f64,
+    pub frame_time: Duration,
+}
+
+/// Keeps an exponential moving average of the frame times, and reports it
+/// every REPORT_INTERVAL rather than every frame, which would be unreadable
+#[derive(Debug, Default)]
+pub struct FpsCounter {
+    // In seconds, None until the first frame
+    average: Option<f64>,
+    since_report: Duration,
+}
+
+impl FpsCounter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add the duration of the last frame, returning the average once the
+    /// report interval has passed
+    pub fn push(&mut self, frame_time: Duration) -> Option<FpsReport> {
+        let seconds = frame_time.as_secs_f64();
+        let average = match self.average {
+            Some(average) => average + (seconds - average) * SMOOTHING,
+            None => seconds,
+        };
+        self.average = Some(average);
+
+        self.since_report += frame_time;
+        if self.since_report < REPORT_INTERVAL || average <= 0.0 {
+            return None;
+        }
+        self.since_report = Duration::ZERO;
+
+        Some(FpsReport {
+            fps: 1.0 / average,
+            frame_time: Duration::from_secs_f64(average),
+        })
+    }
+}
@@ -0,0 +1,64 @@
+/// Collects the command buffers recorded for a frame so that they can be
+/// submitted with a single `queue.submit()`.
+/// The GPU executes them in the order they were added, so a pass that
+/// depends on the output of another must be added after it.
+#[derive(Default)]
+pub struct FrameBuilder {
+    command_buffers: Vec<wgpu::CommandBuffer>,
+}
+
+impl FrameBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a finished encoder, e.g. `frame.add_encoder(encoder.finish())`
+    pub fn add_encoder(&mut self, command_buffer: wgpu::CommandBuffer) {
+        self.command_buffers.push(command_buffer);
+    }
+
+    /// Submit everything that was recorded, in order
+    pub fn submit(self, queue: &wgpu::Queue) -> wgpu::SubmissionIndex {
+        queue.submit(self.command_buffers)
+    }
+}
+
+/// What the render pass starts from in each attachment: cleared to a color,
+/// or loaded with what the previous frame left there, e.g. to accumulate
+/// trails or progressive rendering over several frames.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClearConfig {
+    pub color: wgpu::LoadOp<wgpu::Color>,
+    pub depth: wgpu::LoadOp<f32>,
+}
+
+impl ClearConfig {
+    /// Clear the color to 'color', and the depth to the far plane
+    pub fn clear(color: wgpu::Color) -> Self {
+        Self {
+            color: wgpu::LoadOp::Clear(color),
+            depth: wgpu::LoadOp::Clear(1.0),
+        }
+    }
+
+    /// The load op to use for the color attachment.
+    /// The swapchain hands out its images in turn and doesn't keep their
+    /// contents once presented, so loading only makes sense when 'preserved',
+    /// i.e. when rendering into a texture of our own like the MSAA target.
+    /// Otherwise this clears to 'fallback'.
+    pub fn color_load_op(
+        &self,
+        preserved: bool,
+        fallback: wgpu::Color,
+    ) -> wgpu::LoadOp<wgpu::Color> {
+        match self.color {
+            wgpu::LoadOp::Load if !preserved => wgpu::LoadOp::Clear(fallback),
+            load_op => load_op,
+        }
+    }
+
+    /// Whether the depth has to be stored for the next frame to load it
+    pub fn store_depth(&self) -> bool {
+        self.depth == wgpu::LoadOp::Load
+    }
+}
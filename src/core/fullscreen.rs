@@ -0,0 +1,107 @@
+use super::depth;
+use super::shader::{self, ShaderStage};
+
+/// WGSL source of the fullscreen triangle vertex shader.
+/// Fragment shaders receive a `FullscreenOutput` with the screen UV at location 0.
+pub const FULLSCREEN_VERTEX_SHADER: &str = include_str!("fullscreen.wgsl");
+
+/// Build a pipeline that draws a fullscreen triangle into a 'format' target
+/// with 'sample_count' samples, in the main pass (it ignores the depth buffer),
+/// shading it with 'fragment_entry' from 'fragment_source'.
+/// The fragment source is appended to the fullscreen vertex shader, so it can
+/// use the `FullscreenOutput` struct as its input.
+pub fn create_fullscreen_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: Option<&wgpu::PipelineLayout>,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    fragment_source: &str,
+    fragment_entry: &str,
+) -> wgpu::RenderPipeline {
+    create_pipeline(
+        device,
+        label,
+        layout,
+        (fragment_source, fragment_entry),
+        wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        },
+        depth::depth_ignored(),
+        sample_count,
+    )
+}
+
+/// Same as `create_fullscreen_pipeline()`, for a 'target' that isn't simply
+/// overwritten, e.g. to blend the fullscreen triangle with what's there.
+/// It's drawn outside of the main pass, without a depth buffer.
+pub fn create_fullscreen_pipeline_with_target(
+    device: &wgpu::Device,
+    label: &str,
+    layout: Option<&wgpu::PipelineLayout>,
+    target: wgpu::ColorTargetState,
+    sample_count: u32,
+    fragment_source: &str,
+    fragment_entry: &str,
+) -> wgpu::RenderPipeline {
+    create_pipeline(
+        device,
+        label,
+        layout,
+        (fragment_source, fragment_entry),
+        target,
+        None,
+        sample_count,
+    )
+}
+
+/// 'fragment' is the source and the entry point of the fragment shader
+fn create_pipeline(
+    device: &wgpu::Device,
+    label: &str,
+    layout: Option<&wgpu::PipelineLayout>,
+    (fragment_source, fragment_entry): (&str, &str),
+    target: wgpu::ColorTargetState,
+    depth_stencil: Option<wgpu::DepthStencilState>,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = shader::create_shader_module(
+        device,
+        label,
+        &format!("{FULLSCREEN_VERTEX_SHADER}\n{fragment_source}"),
+        &[
+            ("vs_fullscreen", ShaderStage::Vertex),
+            (fragment_entry, ShaderStage::Fragment),
+        ],
+    );
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout,
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_fullscreen",
+            // The vertices are generated in the shader
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: fragment_entry,
+            targets: &[Some(target)],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            // A single triangle never needs culling
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil,
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,29 @@
+// A single triangle that covers the whole screen, generated from the
+// vertex index alone so no vertex buffer is needed. Draw it with draw(0..3, 0..1).
+//
+//  (-1, 3)
+//     |\
+//     |  \
+//     |----\  <- the visible part is the [-1, 1] square
+//     |    | \
+//     |____|___\
+//  (-1,-1)     (3,-1)
+
+struct FullscreenOutput {
+    @builtin(position) position: vec4<f32>,
+    // (0, 0) is the top left corner of the screen, (1, 1) the bottom right,
+    // which matches how textures are addressed
+    @location(0) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_fullscreen(@builtin(vertex_index) vertex_index: u32) -> FullscreenOutput {
+    // (0, 0), (2, 0), (0, 2)
+    let uv = vec2<f32>(f32((vertex_index << 1u) & 2u), f32(vertex_index & 2u));
+
+    var out: FullscreenOutput;
+    // Flip Y, since in clip space +Y points up
+    out.position = vec4<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.0, 1.0);
+    out.uv = uv;
+    return out;
+}
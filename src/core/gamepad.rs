@@ -0,0 +1,122 @@
+use gilrs::{Axis, Button, GamepadId, Gilrs};
+
+use super::camera::CameraController;
+
+/// A change of gamepad, to tell the user about
+pub enum Connection {
+    Connected(String),
+    Disconnected(String),
+}
+
+/// Drives the camera with the first connected gamepad: the left stick moves
+/// it, the right stick orbits it, and the triggers move it up and down.
+/// gilrs has its own event queue, separate from the one of winit, which is
+/// drained once per frame.
+pub struct GamepadInput {
+    gilrs: Gilrs,
+    // The gamepad the camera follows, None until one is connected
+    active: Option<GamepadId>,
+    deadzone: f32,
+}
+
+impl GamepadInput {
+    /// None when gamepads aren't supported on this platform.
+    /// Stick positions closer to the center than 'deadzone' count as 0.
+    pub fn new(deadzone: f32) -> Option<Self> {
+        // Our own deadzone replaces the one of the default filters
+        let gilrs = match gilrs::GilrsBuilder::new()
+            .with_default_filters(false)
+            .build()
+        {
+            Ok(gilrs) => gilrs,
+            Err(e) => {
+                log::warn!("Gamepads are not available: {e}");
+                return None;
+            }
+        };
+        // Already plugged in before the app started
+        let active = gilrs.gamepads().next().map(|(id, gamepad)| {
+            println!("Gamepad: {}", gamepad.name());
+            id
+        });
+
+        Some(Self {
+            gilrs,
+            active,
+            deadzone,
+        })
+    }
+
+    /// Process the pending events and feed the state of the active gamepad
+    /// into 'controller'. Returns the last connection or disconnection.
+    pub fn poll(&mut self, controller: &mut CameraController) -> Option<Connection> {
+        let mut connection = None;
+        while let Some(event) = self.gilrs.next_event() {
+            let name = || self.gilrs.gamepad(event.id).name().to_string();
+            match event.event {
+                gilrs::EventType::Connected if self.active.is_none() => {
+                    self.active = Some(event.id);
+                    connection = Some(Connection::Connected(name()));
+                }
+                gilrs::EventType::Disconnected if self.active == Some(event.id) => {
+                    connection = Some(Connection::Disconnected(name()));
+                    // Carry on with another one, if there's any left
+                    self.active = self
+                        .gilrs
+                        .gamepads()
+                        .map(|(id, _)| id)
+                        .find(|&id| id != event.id);
+                }
+                _ => {}
+            }
+        }
+
+        let Some(id) = self.active else {
+            controller.set_analog([0.0; 3], [0.0; 2]);
+            return connection;
+        };
+        let gamepad = self.gilrs.gamepad(id);
+        let axis = |axis| apply_deadzone(gamepad.value(axis), self.deadzone);
+        let trigger = |button| {
+            gamepad
+                .button_data(button)
+                .map_or(0.0, |data| apply_deadzone(data.value(), self.deadzone))
+        };
+        controller.set_analog(
+            [
+                axis(Axis::LeftStickY),
+                axis(Axis::LeftStickX),
+                trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2),
+            ],
+            [axis(Axis::RightStickX), axis(Axis::RightStickY)],
+        );
+
+        connection
+    }
+}
+
+/// 0 within 'deadzone' of the center, so that a stick at rest doesn't
+/// drift, then rescaled so that the output still goes smoothly from 0 to 1
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= deadzone {
+        return 0.0;
+    }
+
+    value.signum() * ((magnitude - deadzone) / (1.0 - deadzone)).min(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deadzone_is_rescaled_from_0() {
+        assert_eq!(apply_deadzone(0.1, 0.2), 0.0);
+        assert_eq!(apply_deadzone(-0.2, 0.2), 0.0);
+        assert!((apply_deadzone(0.6, 0.2) - 0.5).abs() < 1e-6);
+        assert!((apply_deadzone(-0.6, 0.2) + 0.5).abs() < 1e-6);
+        assert_eq!(apply_deadzone(1.0, 0.2), 1.0);
+        assert_eq!(apply_deadzone(0.5, 0.0), 0.5);
+    }
+}
@@ -0,0 +1,149 @@
+use std::path::Path;
+
+use super::adapter;
+use super::config::AppConfig;
+use super::limits;
+use super::CoreError;
+
+/// Everything needed to talk to the GPU, independently of the window
+pub struct GpuContext {
+    // Nothing is created from it after the setup,
+    // but the adapter and the surface came from it
+    #[allow(dead_code)]
+    pub instance: wgpu::Instance,
+    pub adapter: wgpu::Adapter,
+    pub device: wgpu::Device,
+    pub queue: wgpu::Queue,
+}
+
+impl GpuContext {
+    /// Pick an adapter that can present to 'surface' (any adapter without
+    /// one), then create the device with the features of 'config' that the
+    /// adapter supports
+    pub async fn new(
+        instance: wgpu::Instance,
+        surface: Option<&wgpu::Surface>,
+        config: &AppConfig,
+    ) -> Result<Self, CoreError> {
+        let adapter_options = wgpu::RequestAdapterOptions {
+            // HighPerformance (the default) will favour performance over battery life
+            power_preference: config.power_preference,
+            // This tells wgpu to find an adapter that can present
+            // to the supplied surface
+            compatible_surface: surface,
+            // Forces wgpu to pick an adapter that will work on all hardware
+            // This might mean that the rendering backend will be software instead
+            // of hardware accelerated on the GPU
+            force_fallback_adapter: config.force_fallback_adapter,
+        };
+
+        adapter::list_adapters(&instance);
+
+        let adapter = match config
+            .adapter_index
+            .and_then(|index| adapter::adapter_by_index(&instance, surface, index))
+            .or_else(|| {
+                config
+                    .adapter_name
+                    .as_deref()
+                    .and_then(|name| adapter::adapter_by_name(&instance, surface, name))
+            }) {
+            Some(adapter) => adapter,
+            None => instance
+                .request_adapter(&adapter_options)
+                .await
+                .ok_or(CoreError::NoAdapter)?,
+        };
+        println!("Adapter: {adapter:?}");
+
+        // Only ask for the features the adapter actually has,
+        // otherwise request_device() would fail
+        let unsupported_features = config.features - adapter.features();
+        if !unsupported_features.is_empty() {
+            log::warn!("Skipping features not supported by the adapter: {unsupported_features:?}");
+        }
+
+        // Without this feature only 1x and 4x MSAA are allowed,
+        // even if the adapter supports other sample counts
+        let msaa_features =
+            adapter.features() & wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+
+        // Only needed by the conservative rasterization demo,
+        // which falls back to standard rasterization without it
+        let conservative_features = adapter.features() & wgpu::Features::CONSERVATIVE_RASTERIZATION;
+
+        // Only needed by the wireframe toggle, which is a no-op without it
+        let wireframe_features = adapter.features() & wgpu::Features::POLYGON_MODE_LINE;
+
+        // Only needed to load DDS and KTX2 textures, which fall back to PNG
+        // files without them
+        let compression_features = adapter.features()
+            & (wgpu::Features::TEXTURE_COMPRESSION_BC
+                | wgpu::Features::TEXTURE_COMPRESSION_ETC2
+                | wgpu::Features::TEXTURE_COMPRESSION_ASTC);
+
+        // Only needed to time the frames on the GPU, see GpuTimer
+        let timestamp_features = adapter.features() & wgpu::Features::TIMESTAMP_QUERY;
+
+        // Used for the small per-draw data when available,
+        // the limit defaults to 0 so it has to be raised too
+        let push_constant_features = adapter.features() & wgpu::Features::PUSH_CONSTANTS;
+        let max_push_constant_size = if push_constant_features.is_empty() {
+            0
+        } else {
+            adapter.limits().max_push_constant_size.min(128)
+        };
+
+        let device_description = wgpu::DeviceDescriptor {
+            // This allows you to choose extra features you might want
+            features: (config.features & adapter.features())
+                | msaa_features
+                | push_constant_features
+                | conservative_features
+                | wireframe_features
+                | compression_features
+                | timestamp_features,
+            // More about limits: https://docs.rs/wgpu/latest/wgpu/struct.Limits.html
+            limits: wgpu::Limits {
+                max_push_constant_size,
+                ..Default::default()
+            },
+            label: Some("Device"),
+        };
+        let trace_path = config.trace_path.as_deref().and_then(prepare_trace_dir);
+
+        let (device, queue) = adapter
+            .request_device(&device_description, trace_path)
+            .await?;
+
+        limits::print_limits(&device.limits());
+        limits::install_error_handler(&device);
+
+        Ok(Self {
+            instance,
+            adapter,
+            device,
+            queue,
+        })
+    }
+}
+
+// wgpu can't be asked whether it was built with its trace feature, and
+// Cargo.toml doesn't enable it: this has to be changed along with it
+const TRACE_COMPILED_IN: bool = false;
+
+/// Create the directory of the API trace if it's missing.
+/// Returns None, with a warning, when it can't be created.
+fn prepare_trace_dir(path: &Path) -> Option<&Path> {
+    if let Err(e) = std::fs::create_dir_all(path) {
+        log::warn!("Not writing an API trace, failed to create {path:?}: {e}");
+        return None;
+    }
+
+    if TRACE_COMPILED_IN {
+        log::info!("Writing an API trace to {path:?}");
+    } else {
+        log::warn!("wgpu is built without its trace feature, nothing will be written to {path:?}");
+    }
+    Some(path)
+}
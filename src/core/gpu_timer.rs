@@ -0,0 +1,164 @@
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::memory::{MemoryRegistry, Tracked};
+
+// A timestamp before and one after the pass
+const QUERY_COUNT: u32 = 2;
+const QUERY_BYTES: wgpu::BufferAddress = QUERY_COUNT as wgpu::BufferAddress * 8;
+
+/// How long the last frames took to render
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderStats {
+    /// Time between the last two frames, on the CPU
+    pub frame_time: Duration,
+    /// How long the main render pass took on the GPU. None when the adapter
+    /// doesn't support timestamp queries, or until the first one comes back.
+    pub gpu_pass_time: Option<Duration>,
+}
+
+/// Times a pass on the GPU with a timestamp query on each side of it.
+/// The results are read back without waiting for the GPU: they come in a
+/// frame or two late, and frames are only timed while no readback is pending.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    // Where the queries are resolved to, which can't be mapped
+    resolve_buffer: Tracked<wgpu::Buffer>,
+    readback_buffer: Tracked<wgpu::Buffer>,
+    // Nanoseconds per timestamp tick
+    period: f32,
+    // The current frame writes its timestamps, to be read back after the submit
+    timing: bool,
+    mapping: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    last_pass_time: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// None without TIMESTAMP_QUERY, which not every adapter supports
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+    ) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            log::info!(
+                "The adapter doesn't support timestamp queries, GPU times won't be measured"
+            );
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("GPU Timer Query Set"),
+            ty: wgpu::QueryType::Timestamp,
+            count: QUERY_COUNT,
+        });
+        let resolve_buffer = memory.create_buffer(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("GPU Timer Resolve Buffer"),
+                size: QUERY_BYTES,
+                usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            },
+        );
+        let readback_buffer = memory.create_buffer(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("GPU Timer Readback Buffer"),
+                size: QUERY_BYTES,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            },
+        );
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period: queue.get_timestamp_period(),
+            timing: false,
+            mapping: None,
+            last_pass_time: None,
+        })
+    }
+
+    /// Record the timestamp before the pass, unless the previous results
+    /// are still being read back
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.timing = self.mapping.is_none();
+        if self.timing {
+            encoder.write_timestamp(&self.query_set, 0);
+        }
+    }
+
+    /// Record the timestamp after the pass, and copy both where they can be
+    /// read back from
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.timing {
+            return;
+        }
+
+        encoder.write_timestamp(&self.query_set, 1);
+        encoder.resolve_query_set(&self.query_set, 0..QUERY_COUNT, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            QUERY_BYTES,
+        );
+    }
+
+    /// Start reading the timestamps back, once the encoder given to end()
+    /// has been submitted
+    pub fn after_submit(&mut self) {
+        if !std::mem::take(&mut self.timing) {
+            return;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.mapping = Some(receiver);
+    }
+
+    /// The duration of the last pass that was read back. Doesn't block:
+    /// a pending readback is only picked up if the GPU is already done.
+    pub fn pass_time(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        if let Some(receiver) = &self.mapping {
+            device.poll(wgpu::Maintain::Poll);
+            match receiver.try_recv() {
+                Ok(Ok(())) => {
+                    self.last_pass_time = Some(self.read_pass_time());
+                    self.readback_buffer.unmap();
+                    self.mapping = None;
+                }
+                Ok(Err(e)) => {
+                    log::warn!("Failed to read the GPU timestamps back: {e}");
+                    self.mapping = None;
+                }
+                Err(mpsc::TryRecvError::Empty) => {}
+                Err(mpsc::TryRecvError::Disconnected) => self.mapping = None,
+            }
+        }
+
+        self.last_pass_time
+    }
+
+    fn read_pass_time(&self) -> Duration {
+        let timestamps: Vec<u64> = self
+            .readback_buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks_exact(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        // The counter can wrap around, or be reset between passes on some drivers
+        let ticks = timestamps[1].saturating_sub(timestamps[0]);
+
+        Duration::from_nanos((ticks as f64 * self.period as f64) as u64)
+    }
+}
@@ -0,0 +1,346 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use instant::Instant;
+use serde::{Deserialize, Serialize};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{
+    DeviceId, ElementState, KeyboardInput, ModifiersState, MouseButton, MouseScrollDelta,
+    TouchPhase, VirtualKeyCode, WindowEvent,
+};
+
+use super::config::AppConfig;
+use super::rng;
+use super::AppState;
+
+/// The parts of a window event that affect the app, in a form that can be saved
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum InputEvent {
+    Key {
+        key: VirtualKeyCode,
+        state: ElementState,
+    },
+    CursorMoved {
+        x: f64,
+        y: f64,
+    },
+    MouseInput {
+        button: MouseButton,
+        state: ElementState,
+    },
+    MouseWheel {
+        delta: MouseScrollDelta,
+    },
+    /// Which of Shift, Ctrl, Alt and Logo are held, e.g. for Ctrl and a digit
+    Modifiers {
+        state: ModifiersState,
+    },
+    Focused {
+        focused: bool,
+    },
+    Occluded {
+        occluded: bool,
+    },
+    CloseRequested,
+    /// UserEvent::Quit, posted from another thread
+    Quit,
+    Resized {
+        width: u32,
+        height: u32,
+    },
+    /// A frame was rendered, 'dt' seconds after the previous one
+    Frame {
+        dt: f64,
+    },
+}
+
+impl InputEvent {
+    fn from_window_event(event: &WindowEvent) -> Option<Self> {
+        let event = match event {
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state,
+                        ..
+                    },
+                ..
+            } => Self::Key {
+                key: *key,
+                state: *state,
+            },
+            WindowEvent::CursorMoved { position, .. } => Self::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            WindowEvent::MouseInput { button, state, .. } => Self::MouseInput {
+                button: *button,
+                state: *state,
+            },
+            WindowEvent::MouseWheel { delta, .. } => Self::MouseWheel { delta: *delta },
+            WindowEvent::ModifiersChanged(state) => Self::Modifiers { state: *state },
+            WindowEvent::Focused(focused) => Self::Focused { focused: *focused },
+            WindowEvent::Occluded(occluded) => Self::Occluded {
+                occluded: *occluded,
+            },
+            WindowEvent::CloseRequested => Self::CloseRequested,
+            WindowEvent::Resized(size) => Self::Resized {
+                width: size.width,
+                height: size.height,
+            },
+            _ => return None,
+        };
+
+        Some(event)
+    }
+
+    /// Rebuild the window event, for the events that map to one
+    #[allow(deprecated)] // The 'modifiers' fields are deprecated but still required
+    pub(super) fn to_window_event(&self) -> Option<WindowEvent<'static>> {
+        // Safe as long as the id is never compared with a real device
+        let device_id = unsafe { DeviceId::dummy() };
+
+        let event = match *self {
+            Self::Key { key, state } => WindowEvent::KeyboardInput {
+                device_id,
+                input: KeyboardInput {
+                    scancode: 0,
+                    state,
+                    virtual_keycode: Some(key),
+                    modifiers: ModifiersState::empty(),
+                },
+                is_synthetic: true,
+            },
+            Self::CursorMoved { x, y } => WindowEvent::CursorMoved {
+                device_id,
+                position: PhysicalPosition::new(x, y),
+                modifiers: ModifiersState::empty(),
+            },
+            Self::MouseInput { button, state } => WindowEvent::MouseInput {
+                device_id,
+                state,
+                button,
+                modifiers: ModifiersState::empty(),
+            },
+            Self::MouseWheel { delta } => WindowEvent::MouseWheel {
+                device_id,
+                delta,
+                phase: TouchPhase::Moved,
+                modifiers: ModifiersState::empty(),
+            },
+            Self::Modifiers { state } => WindowEvent::ModifiersChanged(state),
+            Self::Focused { focused } => WindowEvent::Focused(focused),
+            Self::Occluded { occluded } => WindowEvent::Occluded(occluded),
+            Self::CloseRequested => WindowEvent::CloseRequested,
+            Self::Quit | Self::Resized { .. } | Self::Frame { .. } => return None,
+        };
+
+        Some(event)
+    }
+}
+
+/// The first line of the log file, with what the events alone don't reproduce
+#[derive(Debug, Serialize, Deserialize)]
+struct LogHeader {
+    /// The procedural content has to be the same as during the recording
+    random_seed: u64,
+}
+
+/// One line of the log file after the header
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordedEvent {
+    /// Seconds since the recording started, to make the log easier to read
+    time: f64,
+    event: InputEvent,
+}
+
+/// Records the input to a file (`--record events.log`) or replays it
+/// (`--replay events.log`), to make bugs reproducible.
+/// The log starts with a header holding the random seed, followed by one
+/// JSON event per line, frames included: the replay feeds the events
+/// recorded during a frame to the same handler as the live ones, and then
+/// runs the update with the recorded frame time, so the app goes through
+/// exactly the same states.
+pub enum InputLog {
+    Off,
+    Record {
+        writer: BufWriter<File>,
+        start: Instant,
+    },
+    Replay {
+        events: VecDeque<RecordedEvent>,
+    },
+}
+
+impl InputLog {
+    /// Has to be called before the AppState is created: a replay sets the
+    /// random seed of 'config' to the recorded one, and a recording picks
+    /// the seed if the config doesn't have one, to save it.
+    pub fn from_config(config: &mut AppConfig) -> Self {
+        if let Some(path) = &config.replay_input {
+            match read_log(path) {
+                Ok((header, events)) => {
+                    println!("Replaying {} events from {path:?}", events.len());
+                    config.random_seed = Some(header.random_seed);
+                    return Self::Replay { events };
+                }
+                Err(e) => log::error!("Failed to read the input log {path:?}: {e}"),
+            }
+        } else if let Some(path) = &config.record_input {
+            let header = LogHeader {
+                random_seed: *config.random_seed.get_or_insert_with(rng::clock_seed),
+            };
+            match create_log(path, &header) {
+                Ok(writer) => {
+                    println!("Recording the input to {path:?}");
+                    return Self::Record {
+                        writer,
+                        start: Instant::now(),
+                    };
+                }
+                Err(e) => log::error!("Failed to create the input log {path:?}: {e}"),
+            }
+        }
+
+        Self::Off
+    }
+
+    pub fn is_replaying(&self) -> bool {
+        matches!(self, Self::Replay { .. })
+    }
+
+    /// Called with every event coming from the window.
+    /// Returns true if the event has to be ignored: during a replay the live
+    /// input would make the app diverge from the recording.
+    pub fn filter_live_event(&mut self, event: &WindowEvent) -> bool {
+        match self {
+            Self::Off => false,
+            Self::Record { .. } => {
+                if let Some(event) = InputEvent::from_window_event(event) {
+                    self.record(event);
+                }
+                false
+            }
+            // The real window still gets resized, but by the replay,
+            // and it can still be closed
+            Self::Replay { .. } => {
+                !matches!(
+                    event,
+                    WindowEvent::Resized(_)
+                        | WindowEvent::ScaleFactorChanged { .. }
+                        | WindowEvent::CloseRequested
+                ) && InputEvent::from_window_event(event).is_some()
+            }
+        }
+    }
+
+    /// Called when UserEvent::Quit is received, which exits the app
+    pub fn quit(&mut self) {
+        self.record(InputEvent::Quit);
+    }
+
+    /// Called before updating a frame, with the time it measured.
+    /// Returns the frame time to use, or None when the app has to exit:
+    /// the replay is over, or it replayed a way out such as Escape.
+    pub fn frame(&mut self, app_state: &mut AppState, measured: Duration) -> Option<Duration> {
+        match self {
+            Self::Off => Some(measured),
+            Self::Record { .. } => {
+                self.record(InputEvent::Frame {
+                    dt: measured.as_secs_f64(),
+                });
+                Some(measured)
+            }
+            Self::Replay { events } => {
+                while let Some(RecordedEvent { event, .. }) = events.pop_front() {
+                    let exit = match event {
+                        InputEvent::Frame { dt } => return Some(Duration::from_secs_f64(dt)),
+                        InputEvent::Quit => true,
+                        // Resize the window itself, the Resized event that follows
+                        // reconfigures the surface like it would outside a replay
+                        InputEvent::Resized { width, height } => {
+                            let size = PhysicalSize::new(width, height);
+                            match app_state.window() {
+                                Some(window) => {
+                                    window.set_inner_size(size);
+                                    false
+                                }
+                                None => super::handle_window_event(
+                                    app_state,
+                                    &WindowEvent::Resized(size),
+                                ),
+                            }
+                        }
+                        event => event.to_window_event().is_some_and(|window_event| {
+                            super::handle_window_event(app_state, &window_event)
+                        }),
+                    };
+                    if exit {
+                        println!("Replay finished, the recording exits here");
+                        return None;
+                    }
+                }
+
+                println!("Replay finished");
+                None
+            }
+        }
+    }
+
+    /// Make sure the whole recording reaches the disk before exiting
+    pub fn finish(&mut self) {
+        if let Self::Record { writer, .. } = self {
+            if let Err(e) = writer.flush() {
+                log::error!("Failed to write the input log: {e}");
+            }
+        }
+    }
+
+    fn record(&mut self, event: InputEvent) {
+        let Self::Record { writer, start } = self else {
+            return;
+        };
+
+        let recorded = RecordedEvent {
+            time: start.elapsed().as_secs_f64(),
+            event,
+        };
+        let result = serde_json::to_string(&recorded)
+            .map_err(std::io::Error::from)
+            .and_then(|line| writeln!(writer, "{line}"));
+        if let Err(e) = result {
+            log::error!("Failed to write the input log, stopping the recording: {e}");
+            *self = Self::Off;
+        }
+    }
+}
+
+fn create_log(path: &Path, header: &LogHeader) -> std::io::Result<BufWriter<File>> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writeln!(writer, "{}", serde_json::to_string(header)?)?;
+
+    Ok(writer)
+}
+
+fn read_log(path: &Path) -> std::io::Result<(LogHeader, VecDeque<RecordedEvent>)> {
+    let mut lines = BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()));
+    let header = match lines.next() {
+        Some(line) => serde_json::from_str(&line?)?,
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "the log is empty",
+            ))
+        }
+    };
+    let events = lines
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect::<std::io::Result<_>>()?;
+
+    Ok((header, events))
+}
@@ -0,0 +1,73 @@
+use instant::Instant;
+
+use winit::event::{ElementState, KeyboardInput, WindowEvent};
+
+/// Helps measuring input to photon latency, enabled with `--latency-probe`.
+/// Every frame the screen alternates between black and white, so that a
+/// photodiode or a high speed camera pointed at it can see exactly when a
+/// frame reaches the display. Every present is also logged along with the
+/// time since the last key or button press that hadn't been presented yet,
+/// which is the first frame that could react to it.
+/// Note that present() only hands the frame over to the compositor: the time
+/// it takes to reach the screen is what the external measurement adds.
+pub struct LatencyProbe {
+    start: Instant,
+    frame: u64,
+    pending_input: Option<Instant>,
+}
+
+impl LatencyProbe {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            frame: 0,
+            pending_input: None,
+        }
+    }
+
+    /// Timestamp the presses, only the first one counts until the next present
+    pub fn on_input(&mut self, event: &WindowEvent) {
+        let pressed = matches!(
+            event,
+            WindowEvent::KeyboardInput {
+                input: KeyboardInput {
+                    state: ElementState::Pressed,
+                    ..
+                },
+                ..
+            } | WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                ..
+            }
+        );
+        if pressed && self.pending_input.is_none() {
+            self.pending_input = Some(Instant::now());
+        }
+    }
+
+    /// What to clear the screen to this frame
+    pub fn flash_color(&self) -> wgpu::Color {
+        if self.frame.is_multiple_of(2) {
+            wgpu::Color::BLACK
+        } else {
+            wgpu::Color::WHITE
+        }
+    }
+
+    /// Call right after present()
+    pub fn on_present(&mut self) {
+        let now = Instant::now();
+        let timestamp_ms = (now - self.start).as_secs_f64() * 1000.0;
+
+        match self.pending_input.take() {
+            Some(input) => println!(
+                "Frame {} presented at {timestamp_ms:.3} ms, {:.3} ms after the input",
+                self.frame,
+                (now - input).as_secs_f64() * 1000.0
+            ),
+            None => println!("Frame {} presented at {timestamp_ms:.3} ms", self.frame),
+        }
+
+        self.frame += 1;
+    }
+}
@@ -0,0 +1,89 @@
+/// Device limits that shaders and pipelines most often run into.
+/// The values are printed at startup, and validation errors caused by
+/// one of them get a hint naming it, since wgpu's messages don't always
+/// say which field of `wgpu::Limits` they're about.
+const LIMITS: &[Limit] = &[
+    Limit {
+        name: "max_bind_groups",
+        error_fragment: "bind group limit",
+        value: |limits| limits.max_bind_groups,
+    },
+    Limit {
+        name: "max_vertex_buffers",
+        error_fragment: "number of vertex buffers",
+        value: |limits| limits.max_vertex_buffers,
+    },
+    Limit {
+        name: "max_vertex_attributes",
+        error_fragment: "number of vertex attributes",
+        value: |limits| limits.max_vertex_attributes,
+    },
+    Limit {
+        name: "max_vertex_buffer_array_stride",
+        error_fragment: " stride ",
+        value: |limits| limits.max_vertex_buffer_array_stride,
+    },
+    Limit {
+        name: "max_uniform_buffer_binding_size",
+        error_fragment: "buffer_binding_size",
+        value: |limits| limits.max_uniform_buffer_binding_size,
+    },
+    Limit {
+        name: "max_storage_buffer_binding_size",
+        error_fragment: "buffer_binding_size",
+        value: |limits| limits.max_storage_buffer_binding_size,
+    },
+    Limit {
+        name: "max_texture_dimension_2d",
+        error_fragment: "exceeds the limit of",
+        value: |limits| limits.max_texture_dimension_2d,
+    },
+    Limit {
+        name: "max_push_constant_size",
+        error_fragment: "push constant size limit",
+        value: |limits| limits.max_push_constant_size,
+    },
+    Limit {
+        name: "max_inter_stage_shader_components",
+        error_fragment: "inter-stage components",
+        value: |limits| limits.max_inter_stage_shader_components,
+    },
+];
+
+struct Limit {
+    name: &'static str,
+    // Part of the wgpu validation error message when this limit is exceeded
+    error_fragment: &'static str,
+    value: fn(&wgpu::Limits) -> u32,
+}
+
+pub fn print_limits(limits: &wgpu::Limits) {
+    println!("Device limits:");
+    for limit in LIMITS {
+        println!("  {:<34} {}", limit.name, (limit.value)(limits));
+    }
+}
+
+/// Replace the default error handler, which panics with just the wgpu message,
+/// with one that also points to the limit that was exceeded (if any)
+pub fn install_error_handler(device: &wgpu::Device) {
+    let limits = device.limits();
+
+    device.on_uncaptured_error(Box::new(move |error| {
+        let message = error.to_string();
+        let hints: String = LIMITS
+            .iter()
+            .filter(|limit| message.contains(limit.error_fragment))
+            .map(|limit| {
+                format!(
+                    "\nThis is probably the `{}` limit, which is {} on this device. \
+                     It can be raised in the DeviceDescriptor, up to what the adapter supports.",
+                    limit.name,
+                    (limit.value)(&limits)
+                )
+            })
+            .collect();
+
+        panic!("wgpu error: {message}{hints}");
+    }));
+}
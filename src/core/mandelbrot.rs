@@ -0,0 +1,289 @@
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+use super::shader::{self, ShaderStage};
+
+/// Side of the square workgroups, must match @workgroup_size in mandelbrot.wgsl
+const WORKGROUP_SIZE: u32 = 8;
+
+/// Written by the compute shader, so it has to be a storage format:
+/// the sRGB formats aren't
+const PATTERN_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// The Mandelbrot set computed on the GPU: a compute pass writes it into a
+/// storage texture of the size of the window, before the render pass shows
+/// it by sampling that texture. It's only computed again when the texture
+/// is recreated, i.e. when the window is resized.
+pub struct MandelbrotDemo {
+    texture: Tracked<wgpu::Texture>,
+    compute_bind_group_layout: wgpu::BindGroupLayout,
+    compute_bind_group: wgpu::BindGroup,
+    compute_pipeline: wgpu::ComputePipeline,
+    display_bind_group_layout: wgpu::BindGroupLayout,
+    display_bind_group: wgpu::BindGroup,
+    display_pipeline_layout: wgpu::PipelineLayout,
+    display_pipeline: wgpu::RenderPipeline,
+    sampler: wgpu::Sampler,
+    // The texture doesn't hold the pattern yet
+    needs_dispatch: bool,
+}
+
+impl MandelbrotDemo {
+    /// None when the device can't run workgroups of the size the compute
+    /// shader needs
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        size: winit::dpi::PhysicalSize<u32>,
+        output_format: wgpu::TextureFormat,
+        output_sample_count: u32,
+    ) -> Option<Self> {
+        let limits = device.limits();
+        if WORKGROUP_SIZE > limits.max_compute_workgroup_size_x
+            || WORKGROUP_SIZE > limits.max_compute_workgroup_size_y
+            || WORKGROUP_SIZE * WORKGROUP_SIZE > limits.max_compute_invocations_per_workgroup
+        {
+            log::warn!(
+                "The device can't run {WORKGROUP_SIZE}x{WORKGROUP_SIZE} workgroups, \
+                 the Mandelbrot demo is disabled"
+            );
+            return None;
+        }
+
+        let texture = create_texture(device, memory, size);
+
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Mandelbrot Compute Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: PATTERN_FORMAT,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                }],
+            });
+        let compute_shader = shader::create_shader_module(
+            device,
+            "Mandelbrot Compute Shader",
+            include_str!("mandelbrot.wgsl"),
+            &[("cs_mandelbrot", ShaderStage::Compute)],
+        );
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Mandelbrot Compute Pipeline Layout"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Mandelbrot Compute Pipeline"),
+            layout: Some(&compute_pipeline_layout),
+            module: &compute_shader,
+            entry_point: "cs_mandelbrot",
+        });
+
+        let display_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Mandelbrot Display Bind Group Layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                ],
+            });
+        // One texel per pixel, so the filtering barely matters
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Mandelbrot Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let display_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Mandelbrot Display Pipeline Layout"),
+                bind_group_layouts: &[&display_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let display_pipeline = create_display_pipeline(
+            device,
+            &display_pipeline_layout,
+            output_format,
+            output_sample_count,
+        );
+
+        let (compute_bind_group, display_bind_group) = create_bind_groups(
+            device,
+            &texture,
+            (&compute_bind_group_layout, &display_bind_group_layout),
+            &sampler,
+        );
+
+        Some(Self {
+            texture,
+            compute_bind_group_layout,
+            compute_bind_group,
+            compute_pipeline,
+            display_bind_group_layout,
+            display_bind_group,
+            display_pipeline_layout,
+            display_pipeline,
+            sampler,
+            needs_dispatch: true,
+        })
+    }
+
+    /// Follow the size of the window, the pattern is computed again
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        self.texture = create_texture(device, memory, size);
+        (self.compute_bind_group, self.display_bind_group) = create_bind_groups(
+            device,
+            &self.texture,
+            (
+                &self.compute_bind_group_layout,
+                &self.display_bind_group_layout,
+            ),
+            &self.sampler,
+        );
+        self.needs_dispatch = true;
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_display_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        output_format: wgpu::TextureFormat,
+        output_sample_count: u32,
+    ) {
+        self.display_pipeline = create_display_pipeline(
+            device,
+            &self.display_pipeline_layout,
+            output_format,
+            output_sample_count,
+        );
+    }
+
+    /// Record the compute pass writing the pattern, if the texture doesn't
+    /// hold it yet. It has to be submitted before the render pass drawing it.
+    pub fn encode(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !std::mem::take(&mut self.needs_dispatch) {
+            return;
+        }
+
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Mandelbrot Compute Pass"),
+        });
+        compute_pass.set_pipeline(&self.compute_pipeline);
+        compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+        // Round up so that the texels at the edges get a workgroup too
+        compute_pass.dispatch_workgroups(
+            self.texture.width().div_ceil(WORKGROUP_SIZE),
+            self.texture.height().div_ceil(WORKGROUP_SIZE),
+            1,
+        );
+    }
+
+    /// Show the pattern over the whole render target
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.display_pipeline);
+        render_pass.set_bind_group(0, &self.display_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_texture(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> Tracked<wgpu::Texture> {
+    memory.create_texture(
+        device,
+        &wgpu::TextureDescriptor {
+            label: Some("Mandelbrot Texture"),
+            size: wgpu::Extent3d {
+                width: size.width.max(1),
+                height: size.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: PATTERN_FORMAT,
+            // Written by the compute pass, sampled by the render pass
+            usage: wgpu::TextureUsages::STORAGE_BINDING | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+    )
+}
+
+/// The compute bind group writing 'texture' and the display one sampling it
+fn create_bind_groups(
+    device: &wgpu::Device,
+    texture: &wgpu::Texture,
+    (compute_layout, display_layout): (&wgpu::BindGroupLayout, &wgpu::BindGroupLayout),
+    sampler: &wgpu::Sampler,
+) -> (wgpu::BindGroup, wgpu::BindGroup) {
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Mandelbrot Compute Bind Group"),
+        layout: compute_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(&view),
+        }],
+    });
+    let display_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Mandelbrot Display Bind Group"),
+        layout: display_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(&view),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(sampler),
+            },
+        ],
+    });
+
+    (compute_bind_group, display_bind_group)
+}
+
+fn create_display_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    output_format: wgpu::TextureFormat,
+    output_sample_count: u32,
+) -> wgpu::RenderPipeline {
+    fullscreen::create_fullscreen_pipeline(
+        device,
+        "Mandelbrot Display Pipeline",
+        Some(layout),
+        output_format,
+        output_sample_count,
+        include_str!("mandelbrot_display.wgsl"),
+        "fs_mandelbrot",
+    )
+}
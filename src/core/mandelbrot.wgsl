@@ -0,0 +1,40 @@
+// The Mandelbrot set, one invocation per texel of the output texture.
+// How fast each point escapes picks its color, the set itself is black.
+
+@group(0) @binding(0) var output: texture_storage_2d<rgba8unorm, write>;
+
+const MAX_ITERATIONS: u32 = 256u;
+// The whole set fits in this many units of the complex plane, vertically
+const VIEW_HEIGHT: f32 = 2.5;
+const CENTER: vec2<f32> = vec2<f32>(-0.75, 0.0);
+
+@compute @workgroup_size(8, 8)
+fn cs_mandelbrot(@builtin(global_invocation_id) id: vec3<u32>) {
+    let size = textureDimensions(output);
+
+    // The dispatch is rounded up to whole workgroups,
+    // so some invocations fall outside of the texture
+    if (id.x >= size.x || id.y >= size.y) {
+        return;
+    }
+
+    // Square pixels whatever the aspect ratio, with Y up
+    let pixel = vec2<f32>(id.xy) + 0.5 - vec2<f32>(size) * 0.5;
+    let c = CENTER + vec2<f32>(pixel.x, -pixel.y) * (VIEW_HEIGHT / f32(size.y));
+
+    var z = vec2<f32>(0.0, 0.0);
+    var iterations = 0u;
+    while (iterations < MAX_ITERATIONS && dot(z, z) <= 4.0) {
+        z = vec2<f32>(z.x * z.x - z.y * z.y, 2.0 * z.x * z.y) + c;
+        iterations++;
+    }
+
+    var color = vec3<f32>(0.0, 0.0, 0.0);
+    if (iterations < MAX_ITERATIONS) {
+        let t = f32(iterations) / f32(MAX_ITERATIONS);
+        // A cheap palette: cosine waves shifted for each channel
+        color = 0.5 + 0.5 * cos(6.2831 * (sqrt(t) * 3.0 + vec3<f32>(0.0, 0.33, 0.67)));
+    }
+
+    textureStore(output, vec2<i32>(id.xy), vec4<f32>(color, 1.0));
+}
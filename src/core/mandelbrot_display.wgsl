@@ -0,0 +1,9 @@
+// Appended to fullscreen.wgsl: shows the texture written by mandelbrot.wgsl
+
+@group(0) @binding(0) var pattern: texture_2d<f32>;
+@group(0) @binding(1) var pattern_sampler: sampler;
+
+@fragment
+fn fs_mandelbrot(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return textureSample(pattern, pattern_sampler, in.uv);
+}
@@ -0,0 +1,313 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+use wgpu::util::DeviceExt;
+
+/// The kind of GPU allocation, used to break down the memory report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MemoryCategory {
+    Buffer,
+    Texture,
+    Depth,
+    RenderTarget,
+}
+
+impl MemoryCategory {
+    /// Depth textures and render targets are told apart from the others by
+    /// their format and usage
+    fn of_texture(format: wgpu::TextureFormat, usage: wgpu::TextureUsages) -> Self {
+        if format.has_depth_aspect() {
+            Self::Depth
+        } else if usage.contains(wgpu::TextureUsages::RENDER_ATTACHMENT) {
+            Self::RenderTarget
+        } else {
+            Self::Texture
+        }
+    }
+}
+
+/// Approximate GPU memory usage in bytes, broken down by category
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub buffers: u64,
+    pub textures: u64,
+    pub depth: u64,
+    pub render_targets: u64,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> u64 {
+        self.buffers + self.textures + self.depth + self.render_targets
+    }
+}
+
+impl fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mib = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+
+        writeln!(f, "GPU memory (approximate):")?;
+        writeln!(f, "  buffers:        {:>8.2} MiB", mib(self.buffers))?;
+        writeln!(f, "  textures:       {:>8.2} MiB", mib(self.textures))?;
+        writeln!(f, "  depth:          {:>8.2} MiB", mib(self.depth))?;
+        writeln!(f, "  render targets: {:>8.2} MiB", mib(self.render_targets))?;
+        write!(f, "  total:          {:>8.2} MiB", mib(self.total()))
+    }
+}
+
+/// How a tracked resource can be used, as declared when it was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceUsage {
+    Buffer(wgpu::BufferUsages),
+    Texture(wgpu::TextureUsages),
+}
+
+/// Everything the registry knows about a single buffer or texture
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    pub label: String,
+    pub category: MemoryCategory,
+    pub bytes: u64,
+    pub usage: ResourceUsage,
+    // Only textures have a format
+    pub format: Option<wgpu::TextureFormat>,
+}
+
+impl Allocation {
+    fn of_buffer(buffer: &wgpu::Buffer, label: Option<&str>) -> Self {
+        Self {
+            label: label.unwrap_or("Unlabeled Buffer").to_string(),
+            category: MemoryCategory::Buffer,
+            bytes: buffer.size(),
+            usage: ResourceUsage::Buffer(buffer.usage()),
+            format: None,
+        }
+    }
+
+    fn of_texture(texture: &wgpu::Texture, label: Option<&str>) -> Self {
+        Self {
+            label: label.unwrap_or("Unlabeled Texture").to_string(),
+            category: MemoryCategory::of_texture(texture.format(), texture.usage()),
+            bytes: texture_bytes(
+                texture.format(),
+                texture.size(),
+                texture.mip_level_count(),
+                texture.sample_count(),
+            ),
+            usage: ResourceUsage::Texture(texture.usage()),
+            format: Some(texture.format()),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Allocations {
+    next_id: u64,
+    live: HashMap<u64, Allocation>,
+    // Allocated by the driver rather than through the registry
+    surface: Option<Allocation>,
+}
+
+/// Keeps track of the byte size of every buffer and texture created through
+/// it. They come back wrapped in a Tracked handle, which removes them from
+/// the registry when it's dropped: only live resources are counted, and a
+/// resource that's recreated without the old one being freed (e.g. on
+/// resize) shows up as a second allocation.
+///
+/// Cloning it gives another handle to the same registry.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryRegistry {
+    allocations: Rc<RefCell<Allocations>>,
+}
+
+impl MemoryRegistry {
+    pub fn create_buffer(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::BufferDescriptor,
+    ) -> Tracked<wgpu::Buffer> {
+        let buffer = device.create_buffer(descriptor);
+        let allocation = Allocation::of_buffer(&buffer, descriptor.label);
+        self.track(buffer, allocation)
+    }
+
+    pub fn create_buffer_init(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::util::BufferInitDescriptor,
+    ) -> Tracked<wgpu::Buffer> {
+        let buffer = device.create_buffer_init(descriptor);
+        let allocation = Allocation::of_buffer(&buffer, descriptor.label);
+        self.track(buffer, allocation)
+    }
+
+    pub fn create_texture(
+        &self,
+        device: &wgpu::Device,
+        descriptor: &wgpu::TextureDescriptor,
+    ) -> Tracked<wgpu::Texture> {
+        let texture = device.create_texture(descriptor);
+        let allocation = Allocation::of_texture(&texture, descriptor.label);
+        self.track(texture, allocation)
+    }
+
+    /// The swapchain images are allocated by the driver, but we can still
+    /// estimate their size from the surface configuration and how many
+    /// 'image_count' images it's made of
+    pub fn set_surface(&self, surface_config: &wgpu::SurfaceConfiguration, image_count: u32) {
+        let size = wgpu::Extent3d {
+            width: surface_config.width,
+            height: surface_config.height,
+            depth_or_array_layers: 1,
+        };
+        self.allocations.borrow_mut().surface = Some(Allocation {
+            label: "Surface".to_string(),
+            category: MemoryCategory::RenderTarget,
+            bytes: texture_bytes(surface_config.format, size, 1, 1) * image_count as u64,
+            usage: ResourceUsage::Texture(surface_config.usage),
+            format: Some(surface_config.format),
+        });
+    }
+
+    fn track<T>(&self, resource: T, allocation: Allocation) -> Tracked<T> {
+        let mut allocations = self.allocations.borrow_mut();
+        let id = allocations.next_id;
+        allocations.next_id += 1;
+        allocations.live.insert(id, allocation);
+
+        Tracked {
+            resource,
+            id,
+            registry: self.clone(),
+        }
+    }
+
+    /// How many buffers and textures created through the registry are alive
+    pub fn live_count(&self) -> usize {
+        self.allocations.borrow().live.len()
+    }
+
+    pub fn report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+
+        let allocations = self.allocations.borrow();
+        for allocation in allocations.live.values().chain(&allocations.surface) {
+            let bytes = allocation.bytes;
+            match allocation.category {
+                MemoryCategory::Buffer => report.buffers += bytes,
+                MemoryCategory::Texture => report.textures += bytes,
+                MemoryCategory::Depth => report.depth += bytes,
+                MemoryCategory::RenderTarget => report.render_targets += bytes,
+            }
+        }
+
+        report
+    }
+
+    /// Every live resource, biggest first
+    pub fn resources(&self) -> ResourceTable {
+        let allocations = self.allocations.borrow();
+        let mut rows: Vec<Allocation> = allocations
+            .live
+            .values()
+            .chain(&allocations.surface)
+            .cloned()
+            .collect();
+        rows.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.label.cmp(&b.label)));
+
+        ResourceTable { rows }
+    }
+}
+
+/// A buffer or texture created through a MemoryRegistry, which counts it
+/// until the handle is dropped. It derefs to the resource itself.
+#[derive(Debug)]
+pub struct Tracked<T> {
+    resource: T,
+    id: u64,
+    registry: MemoryRegistry,
+}
+
+impl<T> Deref for Tracked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.resource
+    }
+}
+
+impl<T> Drop for Tracked<T> {
+    fn drop(&mut self) {
+        self.registry.allocations.borrow_mut().live.remove(&self.id);
+    }
+}
+
+/// A listing of the tracked resources, printed as a table
+pub struct ResourceTable {
+    rows: Vec<Allocation>,
+}
+
+impl fmt::Display for ResourceTable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label_width = self
+            .rows
+            .iter()
+            .map(|allocation| allocation.label.len())
+            .max()
+            .unwrap_or(0)
+            .max("label".len());
+
+        write!(
+            f,
+            "{:<label_width$}  {:>10}  {:<20}  usage",
+            "label", "KiB", "format"
+        )?;
+        for allocation in &self.rows {
+            let format = match allocation.format {
+                Some(format) => format!("{format:?}"),
+                None => "-".to_string(),
+            };
+            let usage = match allocation.usage {
+                ResourceUsage::Buffer(usage) => format!("{usage:?}"),
+                ResourceUsage::Texture(usage) => format!("{usage:?}"),
+            };
+            write!(
+                f,
+                "\n{:<label_width$}  {:>10.1}  {format:<20}  {usage}",
+                allocation.label,
+                allocation.bytes as f64 / 1024.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the approximate size of a texture, including all of its mip levels.
+/// Drivers add their own padding and alignment, so this is a lower bound.
+pub fn texture_bytes(
+    format: wgpu::TextureFormat,
+    size: wgpu::Extent3d,
+    mip_level_count: u32,
+    sample_count: u32,
+) -> u64 {
+    // Compressed formats store a whole block of texels in 'block_size' bytes.
+    // Combined depth/stencil formats don't have a well defined size,
+    // so assume 4 bytes per texel for them.
+    let block_size = format.block_size(None).unwrap_or(4) as u64;
+    let (block_width, block_height) = format.block_dimensions();
+
+    (0..mip_level_count)
+        .map(|level| {
+            let width = (size.width >> level).max(1);
+            let height = (size.height >> level).max(1);
+            let blocks_x = width.div_ceil(block_width) as u64;
+            let blocks_y = height.div_ceil(block_height) as u64;
+
+            blocks_x * blocks_y * size.depth_or_array_layers as u64 * block_size
+        })
+        .sum::<u64>()
+        * sample_count as u64
+}
@@ -1,260 +1,4068 @@
+mod accumulation;
+mod adapter;
+mod automata;
+mod camera;
+mod compressed;
+mod config;
+mod conservative;
+mod cutout;
+mod debug;
+mod debug_draw;
+mod decal;
+mod depth;
+mod depth_view;
+mod dynamic_resolution;
+mod easing;
+mod fade;
+mod fps;
+mod frame;
+mod fullscreen;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gpu;
+mod gpu_timer;
+mod input_log;
+mod latency;
+mod limits;
+mod mandelbrot;
+mod memory;
+mod monitor;
+mod msaa;
+mod perf_graph;
+mod plot;
+mod point_cloud;
+mod readback;
+mod render_policy;
+mod render_state;
+mod rng;
+mod sdf;
+mod shader;
+mod spline;
+mod surface;
+mod texture;
+mod texture_array;
+mod time_uniforms;
+mod timestep;
+mod timings;
+mod upload;
+mod upscale;
+mod vertex;
+mod views;
+// In a browser there's no terminal: logs and panics go to the console,
+// and the window is a canvas that has to be added to the page
+#[cfg(target_arch = "wasm32")]
+mod web;
+mod wrapping;
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use instant::Instant;
 use winit::{
     event::*,
-    event_loop::{ControlFlow, EventLoop},
-    window::Window,
-    window::WindowBuilder,
+    event_loop::{ControlFlow, EventLoop, EventLoopBuilder, EventLoopProxy, EventLoopWindowTarget},
+    window::{Icon, Window, WindowBuilder},
 };
 
-/// Create and display the main window
-pub async fn run() {
+use accumulation::AccumulationRenderer;
+use automata::CellularAutomata;
+use camera::{Camera, CameraController, CameraUniforms};
+use config::AppConfig;
+use conservative::ConservativeRasterDemo;
+use cutout::CutoutDemo;
+use debug::debug_group;
+use debug_draw::{Aabb, DebugDraw};
+use decal::DecalDemo;
+use depth_view::DepthView;
+use dynamic_resolution::ResolutionController;
+use easing::Animation;
+use fade::Fade;
+use fps::FpsCounter;
+use frame::{ClearConfig, FrameBuilder};
+use gpu::GpuContext;
+use gpu_timer::{GpuTimer, RenderStats};
+use input_log::InputLog;
+use latency::LatencyProbe;
+use mandelbrot::MandelbrotDemo;
+use memory::{MemoryRegistry, MemoryReport, Tracked};
+use perf_graph::PerfGraph;
+use plot::{Plot, PlotStyle};
+use point_cloud::{PointCloud, PointSize};
+use readback::ReadbackError;
+use render_policy::{RedrawScheduler, RenderPolicy, Visibility};
+use render_state::{ActiveShader, RenderStateReport};
+use rng::Rng;
+use sdf::SdfScene;
+use shader::ShaderStage;
+use spline::SplineRenderer;
+use surface::{choose_present_mode, is_renderable, surface_view_formats, RenderSurface};
+use texture::{SamplerDesc, Texture};
+use texture_array::TextureArrayDemo;
+use time_uniforms::TimeUniforms;
+use timestep::FixedTimestep;
+use timings::TimingsLog;
+use upload::BufferWriter;
+use upscale::{RenderResolution, Upscaler};
+use vertex::{Instance, Vertex};
+use views::{ViewError, Viewpoint, Viewpoints};
+use wrapping::WrappingDemo;
+
+/// Custom events that can be posted to the event loop from other threads
+/// (e.g. background asset loaders) through an `EventLoopProxy`
+#[derive(Debug)]
+// Not every variant is sent from within the crate yet
+#[allow(dead_code)]
+pub enum UserEvent {
+    ReloadShader,
+    LoadModel(PathBuf),
+    Quit,
+}
+
+/// What can prevent the app from starting, or a screenshot from being saved
+#[derive(Debug)]
+pub enum CoreError {
+    WindowBuild(winit::error::OsError),
+    SurfaceCreation(wgpu::CreateSurfaceError),
+    NoAdapter,
+    DeviceRequest(wgpu::RequestDeviceError),
+    Readback(ReadbackError),
+    ImageSave(PathBuf, image::ImageError),
+    // With --capture-first-frame
+    NoFrameRendered,
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WindowBuild(e) => write!(f, "Failed to create the window: {e}"),
+            Self::SurfaceCreation(e) => write!(f, "Failed to create a surface for the window: {e}"),
+            Self::NoAdapter => write!(
+                f,
+                "No graphics adapter can present to the window. \
+                 Check the GPU drivers, or allow more backends in the config"
+            ),
+            Self::DeviceRequest(e) => write!(f, "Failed to create the device: {e}"),
+            Self::Readback(e) => write!(f, "Failed to read the frame back: {e}"),
+            Self::ImageSave(path, e) => write!(f, "Failed to save {path:?}: {e}"),
+            Self::NoFrameRendered => write!(
+                f,
+                "No frame was rendered to capture, is the window minimized?"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CoreError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::WindowBuild(e) => Some(e),
+            Self::SurfaceCreation(e) => Some(e),
+            Self::NoAdapter => None,
+            Self::DeviceRequest(e) => Some(e),
+            Self::Readback(e) => Some(e),
+            Self::ImageSave(_, e) => Some(e),
+            Self::NoFrameRendered => None,
+        }
+    }
+}
+
+impl From<winit::error::OsError> for CoreError {
+    fn from(e: winit::error::OsError) -> Self {
+        Self::WindowBuild(e)
+    }
+}
+
+impl From<wgpu::CreateSurfaceError> for CoreError {
+    fn from(e: wgpu::CreateSurfaceError) -> Self {
+        Self::SurfaceCreation(e)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for CoreError {
+    fn from(e: wgpu::RequestDeviceError) -> Self {
+        Self::DeviceRequest(e)
+    }
+}
+
+impl From<ReadbackError> for CoreError {
+    fn from(e: ReadbackError) -> Self {
+        Self::Readback(e)
+    }
+}
+
+/// How long the camera takes to fly to a loaded view
+const VIEW_TRANSITION: Duration = Duration::from_millis(600);
+/// How much a key press or a line of scrolling changes the field of view,
+/// in degrees, and how long it takes to get there
+const FOV_STEP: f32 = 5.0;
+const FOV_TRANSITION: Duration = Duration::from_millis(250);
+
+/// How long the frame takes to fade in after switching to fullscreen and back
+const FULLSCREEN_TRANSITION: Duration = Duration::from_millis(400);
+
+/// shader.wgsl in the source tree, what the mesh shader is reloaded from
+#[cfg(not(target_arch = "wasm32"))]
+const MESH_SHADER_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/core/shader.wgsl");
+const MESH_SHADER_LABEL: &str = "Mesh Shader";
+const MESH_ENTRY_POINTS: &[(&str, ShaderStage)] = &[
+    ("vs_main", ShaderStage::Vertex),
+    ("fs_main", ShaderStage::Fragment),
+];
+
+/// The resolution of the pixel-perfect mode without one in the config
+const PIXEL_PERFECT_SIZE: winit::dpi::PhysicalSize<u32> = winit::dpi::PhysicalSize::new(320, 180);
+
+/// How big screenshots are without a window size in the config
+const SCREENSHOT_SIZE: winit::dpi::PhysicalSize<u32> = winit::dpi::PhysicalSize::new(1280, 720);
+
+/// Create and display the main window.
+/// Only returns if the setup fails: once running, the event loop exits the
+/// process itself. With `--screenshot` there's no window, and it returns once
+/// the screenshot is saved.
+pub async fn run() -> Result<(), CoreError> {
+    #[cfg(not(target_arch = "wasm32"))]
     env_logger::init();
+    #[cfg(target_arch = "wasm32")]
+    web::init_logging();
 
-    let event_loop = EventLoop::new();
-    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    let mut config = AppConfig::load();
 
-    // Application State holding the WGPU Surface
-    let mut app_state = AppState::new(window).await;
+    if let Some(path) = &config.screenshot_path {
+        if let Err(e) = save_screenshot(&config, path).await {
+            log::error!("{e}");
+            return Err(e);
+        }
+        return Ok(());
+    }
+
+    // Before the app is set up, which a replay needs the recorded seed for
+    let mut input_log = InputLog::from_config(&mut config);
+
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event().build();
+    let mut app_state = match create_app_state(&config, &event_loop).await {
+        Ok(app_state) => app_state,
+        Err(e) => {
+            log::error!("{e}");
+            return Err(e);
+        }
+    };
+
+    // Some platforms show garbage in a new window until something is presented
+    // to it, so the window starts hidden and only appears after a first
+    // clear frame has been submitted
+    if config.hide_until_first_frame {
+        let rendered = app_state.render().unwrap_or_else(|e| {
+            log::warn!("Failed to render the first frame: {e:?}");
+            false
+        });
+        if let Some(window) = app_state.window() {
+            window.set_visible(true);
+        }
+
+        // That was the frame to capture, there's no need for the event loop
+        if config.capture_first_frame {
+            app_state.shutdown();
+            if !rendered {
+                log::error!("{}", CoreError::NoFrameRendered);
+                return Err(CoreError::NoFrameRendered);
+            }
+            return Ok(());
+        }
+    }
+
+    let mut redraw_scheduler = RedrawScheduler::new(config.render_policy);
 
     // Event loop
     event_loop.run(move |event, _, control_flow| match event {
         Event::WindowEvent {
             ref event,
             window_id,
-        } if window_id == app_state.window().id() => {
-            if !app_state.input(event) {
-                match event {
-                    WindowEvent::CloseRequested
-                    | WindowEvent::KeyboardInput {
-                        input:
-                            KeyboardInput {
-                                state: ElementState::Pressed,
-                                virtual_keycode: Some(VirtualKeyCode::Escape),
-                                ..
-                            },
-                        ..
-                    } => *control_flow = ControlFlow::Exit,
-
-                    // Resize
-                    WindowEvent::Resized(physical_size) => {
-                        app_state.resize(*physical_size);
-                    }
-                    // Moved between monitors with different DPIs?
-                    WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
-                        // new_inner_size is &&mut so we have to dereference it twice
-                        app_state.resize(**new_inner_size);
-                    }
-                    _ => {}
-                }
-            }
+        } if app_state.window_id() == Some(window_id)
+            && !input_log.filter_live_event(event)
+            && handle_window_event(&mut app_state, event) =>
+        {
+            *control_flow = ControlFlow::Exit;
         }
         // Redraw
-        Event::RedrawRequested(window_id) if window_id == app_state.window().id() => {
-            app_state.update();
+        Event::RedrawRequested(window_id) if app_state.window_id() == Some(window_id) => {
+            let measured = app_state.measure_frame_time();
+            let Some(frame_time) = input_log.frame(&mut app_state, measured) else {
+                *control_flow = ControlFlow::Exit;
+                return;
+            };
+            app_state.update(frame_time);
             match app_state.render() {
+                // All the resources are created in AppState::new(),
+                // so the very first frame already uses all of them
+                Ok(true) if config.capture_first_frame => *control_flow = ControlFlow::Exit,
+                Ok(false) if config.capture_first_frame => {
+                    log::error!("{}", CoreError::NoFrameRendered);
+                    *control_flow = ControlFlow::ExitWithCode(1);
+                }
                 Ok(_) => {}
-                // Reconfigure the surface if lost
-                Err(wgpu::SurfaceError::Lost) => app_state.resize(app_state.size),
-                // Exit if we are OOM
-                Err(wgpu::SurfaceError::OutOfMemory) => *control_flow = ControlFlow::Exit,
                 Err(e) => {
-                    eprintln!("{e:?}");
+                    if !app_state.recover_from_surface_error(e) {
+                        *control_flow = ControlFlow::Exit;
+                    }
                 }
             }
         }
-        Event::MainEventsCleared => {
-            // RedrawRequested will only trigger once, unless we manually
-            // request it.
-            app_state.window().request_redraw();
+        // Events posted from other threads through the EventLoopProxy
+        Event::UserEvent(UserEvent::Quit) => {
+            input_log.quit();
+            *control_flow = ControlFlow::Exit;
+        }
+        Event::UserEvent(event) => app_state.user_event(event),
+        // RedrawRequested will only trigger once, unless we manually
+        // request it. The render policy decides whether we do.
+        // A replay moves forward one frame at a time, so it always needs the next one.
+        Event::MainEventsCleared
+            if redraw_scheduler.should_redraw(
+                app_state.needs_redraw() || input_log.is_replaying(),
+                app_state.visibility(),
+                control_flow,
+            ) =>
+        {
+            if let Some(window) = app_state.window() {
+                window.request_redraw();
+            }
+        }
+        // Last event before the process exits: winit never returns from run(),
+        // so AppState is never dropped and this is our chance to clean up
+        Event::LoopDestroyed => {
+            input_log.finish();
+            app_state.shutdown();
         }
         _ => {}
     });
 }
 
+/// What a window event does, whether it's live or replayed from an input log.
+/// Returns true when the app has to exit.
+fn handle_window_event(app_state: &mut AppState, event: &WindowEvent) -> bool {
+    if app_state.input(event) {
+        return false;
+    }
+
+    match event {
+        WindowEvent::CloseRequested
+        | WindowEvent::KeyboardInput {
+            input:
+                KeyboardInput {
+                    state: ElementState::Pressed,
+                    virtual_keycode: Some(VirtualKeyCode::Escape),
+                    ..
+                },
+            ..
+        } => return true,
+
+        // Resize
+        WindowEvent::Resized(physical_size) => {
+            app_state.resize(*physical_size);
+        }
+        // Moved between monitors with different DPIs?
+        WindowEvent::ScaleFactorChanged { new_inner_size, .. } => {
+            // new_inner_size is &&mut so we have to dereference it twice
+            app_state.resize(**new_inner_size);
+        }
+        _ => {}
+    }
+
+    false
+}
+
+/// Open the window and set up everything needed to render into it
+async fn create_app_state(
+    config: &AppConfig,
+    event_loop: &EventLoop<UserEvent>,
+) -> Result<AppState, CoreError> {
+    let window = window_builder(config, event_loop).build(event_loop)?;
+    #[cfg(target_arch = "wasm32")]
+    web::append_canvas_to_body(&window);
+
+    // The proxy lets other threads wake up the event loop by posting a UserEvent
+    let event_proxy = event_loop.create_proxy();
+
+    // Application State holding the WGPU Surface
+    AppState::new(window, event_proxy, config).await
+}
+
+/// Render a single frame without a window, of the window size of 'config',
+/// and save it as a PNG at 'path'
+async fn save_screenshot(config: &AppConfig, path: &Path) -> Result<(), CoreError> {
+    let size = config.window_size.unwrap_or(SCREENSHOT_SIZE);
+    let mut app_state = AppState::headless(size, config).await?;
+
+    app_state.update(Duration::ZERO);
+    let image = app_state.render_to_image(size.width, size.height)?;
+    image
+        .save(path)
+        .map_err(|e| CoreError::ImageSave(path.to_path_buf(), e))?;
+    println!(
+        "Saved a {}x{} screenshot to {path:?}",
+        size.width, size.height
+    );
+
+    app_state.shutdown();
+    Ok(())
+}
+
+/// Give the window a title, an icon and (on Linux) the name used
+/// by the desktop environment to group it in the taskbar
+fn window_builder<T>(config: &AppConfig, target: &EventLoopWindowTarget<T>) -> WindowBuilder {
+    let mut builder = WindowBuilder::new()
+        .with_title(&config.app_name)
+        .with_window_icon(config.icon_path.as_deref().and_then(load_icon))
+        .with_visible(!config.hide_until_first_frame);
+
+    if let Some(size) = config.window_size {
+        builder = builder.with_inner_size(size);
+    }
+
+    monitor::list_monitors(target);
+    if let Some(monitor) = monitor::monitor_by_index(target, config.monitor_index) {
+        if let Some(mode_index) = config.video_mode_index {
+            builder = builder.with_fullscreen(Some(monitor::fullscreen_mode(monitor, mode_index)));
+        } else if config.monitor_index.is_some() {
+            // Windowed: open in the top left corner of the chosen monitor
+            builder = builder.with_position(monitor.position());
+        }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        use winit::platform::wayland::WindowBuilderExtWayland;
+        use winit::platform::x11::WindowBuilderExtX11;
+
+        builder = WindowBuilderExtX11::with_name(builder, &config.app_name, &config.app_name);
+        builder = WindowBuilderExtWayland::with_name(builder, &config.app_name, &config.app_name);
+    }
+
+    builder
+}
+
+/// Decode a PNG into the RGBA bytes that winit expects for an icon.
+/// A missing or broken icon is not worth failing for, we just go without it.
+fn load_icon(path: &Path) -> Option<Icon> {
+    let image = match image::open(path) {
+        Ok(image) => image.into_rgba8(),
+        Err(e) => {
+            log::warn!("Failed to load the window icon from {path:?}: {e}");
+            return None;
+        }
+    };
+
+    let (width, height) = image.dimensions();
+    match Icon::from_rgba(image.into_raw(), width, height) {
+        Ok(icon) => Some(icon),
+        Err(e) => {
+            log::warn!("Invalid window icon {path:?}: {e}");
+            None
+        }
+    }
+}
+
 struct AppState {
-    surface: wgpu::Surface,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
-    surface_config: wgpu::SurfaceConfiguration,
-    size: winit::dpi::PhysicalSize<u32>,
-    window: Window,
+    gpu: GpuContext,
+    surface: RenderSurface,
+    // None when rendering headlessly, see headless()
+    window: Option<Window>,
+    event_proxy: Option<EventLoopProxy<UserEvent>>,
+    memory: MemoryRegistry,
+    timestep: FixedTimestep,
+    // Last known cursor position, in physical pixels
+    cursor_position: winit::dpi::PhysicalPosition<f64>,
+    last_frame: Instant,
+    // When the app started, what the clear color pulse is timed from
+    start_time: Instant,
+    perf_graph: PerfGraph,
+    fps_counter: FpsCounter,
+    // None when the adapter can't time the render pass
+    gpu_timer: Option<GpuTimer>,
+    render_stats: RenderStats,
+    // Draws the vertex and index buffers with shader.wgsl, on top of the clear color.
+    // The shader is kept to rebuild the pipeline, it can be reloaded with F5.
+    mesh_shader: wgpu::ShaderModule,
+    // What mesh_shader was built from, for the render state report
+    mesh_shader_source: String,
+    mesh_pipeline: wgpu::RenderPipeline,
+    // Draws the edges of the triangles only, toggled with W
+    wireframe: bool,
+    // Which faces of the mesh are skipped, cycled with X, and which winding
+    // faces the camera, flipped with Z. Behind the quad (orbit around it),
+    // the back faces are all there is to see.
+    cull_mode: Option<wgpu::Face>,
+    front_face: wgpu::FrontFace,
+    vertex_buffer: Tracked<wgpu::Buffer>,
+    index_buffer: Tracked<wgpu::Buffer>,
+    // How many indices draw_indexed() reads from the index buffer
+    index_count: u32,
+    // One Instance per copy of the quad, in a grid changed with + and -
+    instance_buffer: Tracked<wgpu::Buffer>,
+    instance_count: u32,
+    instance_grid_size: u32,
+    // The texture sampled by shader.wgsl, bound at group 1 of the mesh pipeline.
+    // The bind group would keep it alive on its own, but the registry only
+    // counts it while the handle is around.
+    mesh_texture: Texture,
+    texture_bind_group_layout: wgpu::BindGroupLayout,
+    texture_bind_group: wgpu::BindGroup,
+    // The time shader.wgsl animates with, bound at group 0 of the mesh pipeline
+    time_bind_group_layout: wgpu::BindGroupLayout,
+    time_buffer: Tracked<wgpu::Buffer>,
+    time_bind_group: wgpu::BindGroup,
+    // What the mesh is seen through, bound at group 2 of the mesh pipeline
+    camera: Camera,
+    camera_controller: CameraController,
+    // Where the cursor was when dragging last moved the camera,
+    // None while the left button is released
+    orbit_from: Option<winit::dpi::PhysicalPosition<f64>>,
+    // Flying to a loaded view, None when the camera is where it should be
+    camera_animation: Option<Animation<Camera>>,
+    // Changing the field of view, within fov_limits
+    fov_animation: Option<Animation<f32>>,
+    fov_limits: (f32, f32),
+    // Where the views are saved to and loaded from, one file per digit key
+    views_dir: PathBuf,
+    // Of the textures dropped on the window too
+    texture_sampler: SamplerDesc,
+    // Ctrl and a digit saves a view, the digit alone loads it
+    modifiers: ModifiersState,
+    viewpoints: Viewpoints,
+    // None when gamepads aren't supported. It's only polled when a frame is
+    // rendered, so with the on_change policy it can't wake the app up.
+    #[cfg(feature = "gamepad")]
+    gamepad: Option<gamepad::GamepadInput>,
+    camera_bind_group_layout: wgpu::BindGroupLayout,
+    camera_buffer: Tracked<wgpu::Buffer>,
+    camera_bind_group: wgpu::BindGroup,
+    // How long the mesh has been spinning for, it's paused with Q
+    mesh_time: Duration,
+    spin_mesh: bool,
+    clear_color: wgpu::Color,
+    pulse_clear_color: bool,
+    mouse_clear_color: bool,
+    // Whether each frame starts cleared to clear_color or from the previous one
+    clear_config: ClearConfig,
+    automata: CellularAutomata,
+    show_automata: bool,
+    conservative_demo: ConservativeRasterDemo,
+    show_conservative_demo: bool,
+    splines: SplineRenderer,
+    show_splines: bool,
+    plot: Plot,
+    show_plot: bool,
+    cutout_demo: CutoutDemo,
+    show_cutout_demo: bool,
+    decal_demo: DecalDemo,
+    show_decal_demo: bool,
+    // Lines, spheres, boxes and arrows queued during the frame
+    debug_draw: DebugDraw,
+    show_debug_shapes: bool,
+    point_cloud: PointCloud,
+    show_point_cloud: bool,
+    // Shows the depth buffer of the main pass instead of the frame
+    depth_view: DepthView,
+    show_depth_view: bool,
+    sdf_scene: SdfScene,
+    show_sdf_scene: bool,
+    // Progressive rendering of the SDF scene, with its camera frozen
+    accumulation: AccumulationRenderer,
+    accumulate: bool,
+    texture_array_demo: TextureArrayDemo,
+    show_texture_array_demo: bool,
+    wrapping_demo: WrappingDemo,
+    show_wrapping_demo: bool,
+    // None when the device can't run its compute shader
+    mandelbrot: Option<MandelbrotDemo>,
+    show_mandelbrot: bool,
+    // CSV of the frame times, when enabled with --log-timings
+    timings_log: Option<TimingsLog>,
+    // Streams the per-frame buffer writes, None to use queue.write_buffer()
+    staging_belt: Option<wgpu::util::StagingBelt>,
+    // Something changed since the last frame was rendered
+    dirty: bool,
+    latency_probe: Option<LatencyProbe>,
+    sample_count: u32,
+    // The targets of the main pass, with the size of the surface times the
+    // render scale, or the base resolution of the pixel-perfect mode
+    targets: RenderTargets,
+    render_scale: f32,
+    pixel_perfect: Option<winit::dpi::PhysicalSize<u32>>,
+    // What Shift+F3 turns the pixel-perfect mode on with
+    pixel_perfect_size: winit::dpi::PhysicalSize<u32>,
+    // Lowers the render scale when the frames are too slow, while enabled
+    dynamic_resolution: ResolutionController,
+    adapt_resolution: bool,
+    // Shows the scene in the surface when it's rendered at another size
+    upscaler: Upscaler,
+    app_name: String,
+    // Borderless fullscreen, toggled with F11
+    fullscreen: bool,
+    // Hides the switch to fullscreen and back
+    fade: Fade,
+    // When to restore the window title after flashing a message in it
+    title_reset_at: Option<Instant>,
+    // Whether the window has the keyboard focus and whether it's hidden,
+    // which slow down or stop the redraws
+    focused: bool,
+    occluded: bool,
 }
 
 impl AppState {
-    async fn new(window: Window) -> Self {
+    async fn new(
+        window: Window,
+        event_proxy: EventLoopProxy<UserEvent>,
+        config: &AppConfig,
+    ) -> Result<Self, CoreError> {
         let size = window.inner_size();
-
-        // The instance is a handle to the actual GPU
-        // Choosing all backends means: Vulkan | Metal | DX12 | Browser WebGPU
-        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
-            backends: wgpu::Backends::all(),
-            dx12_shader_compiler: Default::default(),
-        });
+        let instance = create_instance(config);
 
         // The 'surface' represents the part of the window that we can
         // draw to.  It needs to live as long as the window that created it.
         // The 'AppState' owns the window, so while this is unsafe code,
         // it should practically be okay.
-        let surface = unsafe { instance.create_surface(&window) }.unwrap();
-
-        let adapter_options = wgpu::RequestAdapterOptions {
-            // HighPerformance will favour performance over battery life
-            power_preference: wgpu::PowerPreference::HighPerformance,
-            // This tells wgpu to find an adapter that can present
-            // to the supplied surface
-            compatible_surface: Some(&surface),
-            // Forces wgpu to pick an adapter that will work on all hardware
-            // This might mean that the rendering backend will be software instead
-            // of hardware accelerated on the GPU
-            force_fallback_adapter: false,
-        };
-
-        let adapter = instance.request_adapter(&adapter_options).await.unwrap();
-        println!("Adapter: {adapter:?}");
-
-        let device_description = wgpu::DeviceDescriptor {
-            // This allows you to choose extra features you might want
-            features: wgpu::Features::empty(),
-            // More about limits: https://docs.rs/wgpu/latest/wgpu/struct.Limits.html
-            limits: wgpu::Limits::default(),
-            label: None,
-        };
-        let trace_path = None;
-
-        let (device, queue) = adapter
-            .request_device(&device_description, trace_path)
-            .await
-            .unwrap();
+        let surface = unsafe { instance.create_surface(&window) }?;
 
-        let surface_capabilities = surface.get_capabilities(&adapter);
+        let gpu = GpuContext::new(instance, Some(&surface), config).await?;
+        let surface = RenderSurface::new(surface, &gpu, size, config);
 
-        eprintln!("Format supported by this surface:");
-        for surface_format in surface_capabilities.formats.iter() {
-            eprintln!("{:?}", surface_format);
-        }
+        Self::with_surface(gpu, surface, Some(window), Some(event_proxy), config)
+    }
 
-        let surface_format = surface_capabilities
-            .formats
-            .iter()
-            .copied()
-            .find(|f| f.is_srgb())
-            .unwrap_or(surface_capabilities.formats[0]);
-
-        // Red, green, blue, and alpha channels. 16 bit float per channel. Float in shader.
-        // let surface_format = wgpu::TextureFormat::Rgba16Float;
-        eprintln!("Surface format chosen: {surface_format:?}");
-
-        // TODO:
-        // Make sure that the width and height of the `SurfaceTexture` are not 0,
-        // as that can cause your app to crash.
-
-        let surface_config = wgpu::SurfaceConfiguration {
-            // 'RENDER_ATTACHMENTS' specifies that the texture will be used
-            // to write to the screen
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: surface_format,
-            width: size.width,
-            height: size.height,
-            // present_mode: surface_capabilities.present_modes[0],
-            // This caps the display rate at the displays framerate:
-            // which is essentially VSync
-            present_mode: wgpu::PresentMode::Fifo,
-            // alpha_mode: surface_capabilities.alpha_modes[0],
-            alpha_mode: wgpu::CompositeAlphaMode::Auto,
-            view_formats: vec![],
+    /// Everything but the window, rendering into an offscreen surface of
+    /// 'size' instead, e.g. to take screenshots or in tests.
+    /// Its format is the one of the config, or sRGB RGBA8 by default.
+    async fn headless(
+        size: winit::dpi::PhysicalSize<u32>,
+        config: &AppConfig,
+    ) -> Result<Self, CoreError> {
+        let gpu = GpuContext::new(create_instance(config), None, config).await?;
+        let format = config
+            .surface_format
+            .unwrap_or(wgpu::TextureFormat::Rgba8UnormSrgb);
+        let surface = RenderSurface::offscreen(size, format);
+
+        Self::with_surface(gpu, surface, None, None, config)
+    }
+
+    fn with_surface(
+        gpu: GpuContext,
+        surface: RenderSurface,
+        window: Option<Window>,
+        event_proxy: Option<EventLoopProxy<UserEvent>>,
+        config: &AppConfig,
+    ) -> Result<Self, CoreError> {
+        // It can start in fullscreen with --video-mode
+        let fullscreen = window
+            .as_ref()
+            .is_some_and(|window| window.fullscreen().is_some());
+
+        let supported =
+            msaa::supported_sample_counts(&gpu.adapter, &gpu.device, surface.config.format);
+        let sample_count = msaa::validate_sample_count(config.sample_count, &supported);
+
+        let memory = MemoryRegistry::default();
+        memory.set_surface(&surface.config, surface.image_count());
+        let resolution = match config.pixel_perfect {
+            Some(base) => RenderResolution::PixelPerfect(base),
+            None => RenderResolution::Scaled(config.render_scale),
         };
+        let targets = RenderTargets::new(&gpu, &memory, &surface.config, sample_count, resolution);
+        let size = targets.size;
+        let upscaler = Upscaler::new(&gpu.device, surface.config.format);
+        let fade = Fade::new(&gpu.device, &memory, surface.config.format);
 
-        surface.configure(&device, &surface_config);
+        let perf_graph = PerfGraph::new(
+            &gpu.device,
+            &memory,
+            surface.config.format,
+            sample_count,
+            config.target_frame_time,
+        );
+        let gpu_timer = GpuTimer::new(&gpu.device, &gpu.queue, &memory);
 
-        Self {
+        let (time_bind_group_layout, time_buffer, time_bind_group) =
+            time_uniforms::create_time_bindings(&gpu.device, &memory);
+        let mesh_texture = config.texture_path.as_deref().and_then(|path| {
+            Texture::from_path(
+                &gpu.device,
+                &gpu.queue,
+                &memory,
+                path,
+                config.texture_sampler,
+            )
+            .map_err(|e| log::error!("Failed to load the texture {path:?}: {e}"))
+            .ok()
+        });
+        // Without a texture to tint, the quad shows its corner colors
+        let textured = mesh_texture.is_some();
+        let mesh_texture =
+            mesh_texture.unwrap_or_else(|| Texture::white(&gpu.device, &gpu.queue, &memory));
+        let texture_bind_group_layout = Texture::bind_group_layout(&gpu.device);
+        let texture_bind_group =
+            mesh_texture.create_bind_group(&gpu.device, &texture_bind_group_layout);
+
+        let mut camera = Camera::new(targets.aspect_ratio());
+        camera.fovy = camera.fovy.clamp(config.fov_limits.0, config.fov_limits.1);
+        let (camera_bind_group_layout, camera_buffer, camera_bind_group) =
+            camera::create_camera_bindings(&gpu.device, &memory, &camera);
+
+        let mesh_shader = create_mesh_shader(&gpu.device, include_str!("shader.wgsl"));
+        let mesh_pipeline = create_mesh_pipeline(
+            &gpu.device,
+            &mesh_shader,
+            &[
+                &time_bind_group_layout,
+                &texture_bind_group_layout,
+                &camera_bind_group_layout,
+            ],
+            surface.config.format,
+            sample_count,
+            wgpu::PrimitiveState {
+                cull_mode: config.cull_mode,
+                front_face: config.front_face,
+                ..default_mesh_primitive()
+            },
+        );
+        let (vertex_buffer, index_buffer) = create_quad_buffers(&gpu.device, &memory, textured);
+        let instance_grid_size = config.instance_grid_size;
+        let (instance_buffer, instance_count) =
+            create_instance_buffer(&gpu.device, &memory, instance_grid_size);
+
+        let mut rng = Rng::new(config.random_seed);
+
+        let automata = CellularAutomata::new(
+            &gpu.device,
+            &memory,
+            surface.config.format,
+            sample_count,
+            config.automata_size,
+            config.automata_steps_per_second,
+        );
+        automata.randomize(&gpu.queue, &mut rng);
+
+        let conservative_demo = ConservativeRasterDemo::new(
+            &gpu.device,
+            &gpu.queue,
+            &memory,
+            surface.config.format,
+            sample_count,
+        );
+
+        let splines =
+            SplineRenderer::new(&gpu.device, &memory, surface.config.format, sample_count);
+
+        // A damped sine wave, in the bottom left quarter of the window
+        let mut plot = Plot::new(PlotStyle::Line, [0.05, 0.55, 0.45, 0.95]);
+        let wave: Vec<(f32, f32)> = (0..=100)
+            .map(|i| {
+                let x = i as f32 * 0.1;
+                (x, (x * 2.0).sin() * (-x * 0.3).exp() * 3.0)
+            })
+            .collect();
+        plot.set_series(&wave);
+
+        let cutout_demo = CutoutDemo::new(
+            &gpu.device,
+            surface.config.format,
+            sample_count,
+            config.alpha_to_coverage,
+        );
+
+        let decal_demo = DecalDemo::new(
+            &gpu.device,
+            &camera_bind_group_layout,
+            surface.config.format,
+            sample_count,
+            config.decal_depth_bias,
+        );
+
+        let debug_draw = DebugDraw::new(
+            &gpu.device,
+            &memory,
+            &camera_bind_group_layout,
+            surface.config.format,
+            sample_count,
+        );
+
+        let mut point_cloud = PointCloud::new(
+            &gpu.device,
+            &memory,
+            &camera_bind_group_layout,
+            surface.config.format,
+            sample_count,
+        );
+        point_cloud.set_points(&point_cloud::demo_points(&mut rng));
+
+        let depth_view = DepthView::new(&gpu.device, &memory, surface.config.format, sample_count);
+
+        let sdf_scene = SdfScene::new(
+            &gpu.device,
+            &memory,
+            surface.config.format,
+            sample_count,
+            config.stereo.then_some(config.ipd),
+        );
+        let accumulation = AccumulationRenderer::new(
+            &gpu.device,
+            &memory,
+            size,
+            surface.config.format,
+            sample_count,
+        );
+
+        let texture_array_demo = TextureArrayDemo::new(
+            &gpu.device,
+            &gpu.queue,
+            &memory,
+            surface.config.format,
+            sample_count,
+        );
+        let wrapping_demo = WrappingDemo::new(
+            &gpu.device,
+            &gpu.queue,
+            &memory,
+            surface.config.format,
+            sample_count,
+        );
+        let mandelbrot = MandelbrotDemo::new(
+            &gpu.device,
+            &memory,
+            size,
+            surface.config.format,
+            sample_count,
+        );
+
+        let timings_log = config
+            .timings_path
+            .as_deref()
+            .and_then(|path| match TimingsLog::create(path) {
+                Ok(log) => {
+                    println!("Logging the frame times to {path:?}");
+                    Some(log)
+                }
+                Err(e) => {
+                    log::error!("Failed to create {path:?}: {e}");
+                    None
+                }
+            });
+
+        Ok(Self {
             window,
+            gpu,
             surface,
-            device,
-            queue,
-            surface_config,
-            size,
-        }
+            event_proxy,
+            memory,
+            timestep: FixedTimestep::new(config.fixed_timestep, config.max_substeps),
+            cursor_position: winit::dpi::PhysicalPosition::new(0.0, 0.0),
+            last_frame: Instant::now(),
+            start_time: Instant::now(),
+            perf_graph,
+            fps_counter: FpsCounter::new(),
+            gpu_timer,
+            render_stats: RenderStats {
+                frame_time: Duration::ZERO,
+                gpu_pass_time: None,
+            },
+            mesh_shader,
+            mesh_shader_source: include_str!("shader.wgsl").to_string(),
+            mesh_pipeline,
+            wireframe: false,
+            cull_mode: config.cull_mode,
+            front_face: config.front_face,
+            vertex_buffer,
+            index_buffer,
+            index_count: vertex::QUAD_INDICES.len() as u32,
+            instance_buffer,
+            instance_count,
+            instance_grid_size,
+            mesh_texture,
+            texture_bind_group_layout,
+            texture_bind_group,
+            time_bind_group_layout,
+            time_buffer,
+            time_bind_group,
+            camera,
+            camera_controller: CameraController::new(),
+            orbit_from: None,
+            camera_animation: None,
+            fov_animation: None,
+            fov_limits: config.fov_limits,
+            views_dir: config.views_dir.clone(),
+            texture_sampler: config.texture_sampler,
+            modifiers: ModifiersState::empty(),
+            viewpoints: Viewpoints::new(config.viewpoints.clone()),
+            #[cfg(feature = "gamepad")]
+            gamepad: gamepad::GamepadInput::new(config.gamepad_deadzone),
+            camera_bind_group_layout,
+            camera_buffer,
+            camera_bind_group,
+            mesh_time: Duration::ZERO,
+            // Spinning would keep the power saving policy from ever sleeping
+            spin_mesh: config.render_policy != RenderPolicy::OnChange,
+            clear_color: config.clear_color,
+            pulse_clear_color: config.pulse_clear_color,
+            mouse_clear_color: config.mouse_clear_color,
+            clear_config: ClearConfig::clear(config.clear_color),
+            automata,
+            show_automata: false,
+            conservative_demo,
+            show_conservative_demo: false,
+            splines,
+            show_splines: false,
+            plot,
+            show_plot: false,
+            cutout_demo,
+            show_cutout_demo: false,
+            decal_demo,
+            show_decal_demo: false,
+            debug_draw,
+            show_debug_shapes: false,
+            point_cloud,
+            show_point_cloud: false,
+            depth_view,
+            show_depth_view: false,
+            sdf_scene,
+            show_sdf_scene: false,
+            accumulation,
+            accumulate: false,
+            texture_array_demo,
+            show_texture_array_demo: false,
+            wrapping_demo,
+            show_wrapping_demo: false,
+            mandelbrot,
+            show_mandelbrot: false,
+            timings_log,
+            staging_belt: config
+                .staging_belt
+                .then(|| wgpu::util::StagingBelt::new(upload::STAGING_CHUNK_SIZE)),
+            dirty: true,
+            latency_probe: config.latency_probe.then(LatencyProbe::new),
+            sample_count,
+            targets,
+            render_scale: config.render_scale,
+            pixel_perfect: config.pixel_perfect,
+            pixel_perfect_size: config.pixel_perfect.unwrap_or(PIXEL_PERFECT_SIZE),
+            dynamic_resolution: ResolutionController::new(
+                config.dynamic_resolution.unwrap_or_default(),
+            ),
+            adapt_resolution: config.dynamic_resolution.is_some(),
+            upscaler,
+            app_name: config.app_name.clone(),
+            fullscreen,
+            fade,
+            title_reset_at: None,
+            focused: true,
+            occluded: false,
+        })
+    }
+
+    /// None when rendering headlessly
+    pub fn window(&self) -> Option<&Window> {
+        self.window.as_ref()
     }
 
-    pub fn window(&self) -> &Window {
-        &self.window
+    pub fn window_id(&self) -> Option<winit::window::WindowId> {
+        self.window.as_ref().map(Window::id)
     }
 
-    /// Support the resizing of the window
+    /// Returns a proxy that background threads can use to post a UserEvent,
+    /// None when rendering headlessly
+    #[allow(dead_code)]
+    pub fn event_proxy(&self) -> Option<EventLoopProxy<UserEvent>> {
+        self.event_proxy.clone()
+    }
+
+    fn set_title(&self, title: &str) {
+        if let Some(window) = &self.window {
+            window.set_title(title);
+        }
+    }
+
+    /// Support the resizing of the window.
+    /// Minimizing it resizes it to 0x0, which the surface can't be configured
+    /// with: the surface keeps its last size, and no frames are rendered
+    /// until the window is restored.
     fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>) {
-        if new_size.width > 0 && new_size.height > 0 {
-            self.size = new_size;
-            self.surface_config.width = new_size.width;
-            self.surface_config.height = new_size.height;
-            self.surface.configure(&self.device, &self.surface_config);
+        if self.surface.resize(&self.gpu.device, new_size) {
+            self.dirty = true;
+            self.recreate_size_dependent_targets();
         }
     }
 
-    // input() returns a bool to indicate whether an event has been fully processed.
-    // If the method returns true, the main loop won't process the event any further.
-    fn input(&mut self, event: &WindowEvent) -> bool {
-        false
+    /// Recreate every texture that has the size of the surface or of the
+    /// scene, after the surface was reconfigured with a new size or the
+    /// render resolution changed. The camera follows their aspect ratio.
+    /// Assigning the new ones drops the old ones, freeing their memory.
+    fn recreate_size_dependent_targets(&mut self) {
+        self.memory
+            .set_surface(&self.surface.config, self.surface.image_count());
+        self.targets = RenderTargets::new(
+            &self.gpu,
+            &self.memory,
+            &self.surface.config,
+            self.sample_count,
+            self.render_resolution(),
+        );
+        self.camera.aspect = self.targets.aspect_ratio();
+        let size = self.targets.size;
+        self.accumulation
+            .resize(&self.gpu.device, &self.memory, size);
+        if let Some(mandelbrot) = &mut self.mandelbrot {
+            mandelbrot.resize(&self.gpu.device, &self.memory, size);
+        }
     }
 
-    fn update(&mut self) {
-        // TODO:
+    /// Change the number of MSAA samples (1 disables MSAA).
+    /// Unsupported counts fall back to the highest supported one below them.
+    pub fn set_sample_count(&mut self, sample_count: u32) {
+        let format = self.surface.config.format;
+        let supported = msaa::supported_sample_counts(&self.gpu.adapter, &self.gpu.device, format);
+        let sample_count = msaa::validate_sample_count(sample_count, &supported);
+        if sample_count == self.sample_count {
+            return;
+        }
+
+        self.flash_message(&format!("MSAA: {sample_count}x"));
+        self.sample_count = sample_count;
+        self.targets = RenderTargets::new(
+            &self.gpu,
+            &self.memory,
+            &self.surface.config,
+            sample_count,
+            self.render_resolution(),
+        );
+
+        // Pipelines bake in the sample count of their render target
+        self.rebuild_pipelines();
     }
 
-    /// Perform the actual magic of rendering to the window
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
-        let surface_texture = self.surface.get_current_texture()?;
+    /// Render the scene at the size of the surface times 'scale', then scale
+    /// it to the surface: 0.5 renders at half the resolution, 2.0
+    /// supersamples it. The scale is lowered if the scene would be larger
+    /// than the device supports, see render_size().
+    pub fn set_render_scale(&mut self, scale: f32) {
+        if !(scale > 0.0 && scale.is_finite()) {
+            log::warn!("Ignoring the render scale {scale}, it must be a positive number");
+            return;
+        }
 
-        // This line creates a TextureView with default settings.
-        // We need to do this because we want to control how the render
-        // code interacts with the texture.
-        let view = surface_texture
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        self.render_scale = scale;
+        self.recreate_size_dependent_targets();
+        self.dirty = true;
+    }
 
-        // We also need to create a CommandEncoder to create the actual
-        // commands to send to the gpu. Most modern graphics frameworks
-        // expect commands to be stored in a command buffer before being
-        // sent to the gpu. The encoder builds a command buffer that we
-        // can then send to the gpu.
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
+    /// The size the scene is currently rendered at
+    pub fn render_size(&self) -> winit::dpi::PhysicalSize<u32> {
+        self.targets.size
+    }
+
+    /// Render the scene at 'base' pixels whatever the size of the window,
+    /// and show it at the largest integer scale that fits with nearest
+    /// filtering, for pixel art. None goes back to the render scale.
+    pub fn set_pixel_perfect(&mut self, base: Option<winit::dpi::PhysicalSize<u32>>) {
+        self.pixel_perfect = base.filter(|base| base.width > 0 && base.height > 0);
+        if let Some(base) = self.pixel_perfect {
+            self.pixel_perfect_size = base;
+        }
+        self.recreate_size_dependent_targets();
+        self.dirty = true;
+    }
+
+    fn toggle_pixel_perfect(&mut self) {
+        let base = self.pixel_perfect_size;
+        if self.pixel_perfect.is_some() {
+            self.set_pixel_perfect(None);
+            self.flash_message("Pixel-perfect: off");
+        } else {
+            self.set_pixel_perfect(Some(base));
+            self.flash_message(&format!("Pixel-perfect: {}x{}", base.width, base.height));
+        }
+    }
+
+    fn render_resolution(&self) -> RenderResolution {
+        match self.pixel_perfect {
+            Some(base) => RenderResolution::PixelPerfect(base),
+            None => RenderResolution::Scaled(self.render_scale),
+        }
+    }
+
+    /// Go through half, native and twice the resolution.
+    /// The scale stops adapting to the frame times, and the pixel-perfect
+    /// mode is turned off.
+    fn cycle_render_scale(&mut self) {
+        self.adapt_resolution = false;
+        self.pixel_perfect = None;
+        let scale = match self.render_scale {
+            scale if scale < 1.0 => 1.0,
+            scale if scale < 2.0 => 2.0,
+            _ => 0.5,
+        };
+        self.set_render_scale(scale);
+
+        let size = self.render_size();
+        self.flash_message(&format!(
+            "Render scale: {scale}x ({}x{})",
+            size.width, size.height
+        ));
+    }
+
+    /// Start or stop adapting the render scale to the frame times
+    fn toggle_dynamic_resolution(&mut self) {
+        self.adapt_resolution = !self.adapt_resolution;
+        let state = if self.adapt_resolution { "on" } else { "off" };
+        self.flash_message(&format!("Dynamic resolution: {state}"));
+    }
+
+    /// Reconfigure the surface with the next format it supports, to compare
+    /// how the same content looks with each of them
+    fn cycle_surface_format(&mut self) {
+        let current = self
+            .surface
+            .formats
+            .iter()
+            .position(|&format| format == self.surface.config.format)
+            .unwrap_or(0);
+        let format = self.surface.formats[(current + 1) % self.surface.formats.len()];
+        if format == self.surface.config.format {
+            return;
+        }
+
+        self.flash_message(&format!("Surface format: {format:?}"));
+        self.surface.config.format = format;
+        self.surface.config.view_formats = surface_view_formats(&self.gpu.adapter, format);
+        self.surface.configure(&self.gpu.device);
+
+        // Not every format supports the same MSAA sample counts
+        let supported = msaa::supported_sample_counts(&self.gpu.adapter, &self.gpu.device, format);
+        self.sample_count = msaa::validate_sample_count(self.sample_count, &supported);
+        self.memory
+            .set_surface(&self.surface.config, self.surface.image_count());
+        self.targets = RenderTargets::new(
+            &self.gpu,
+            &self.memory,
+            &self.surface.config,
+            self.sample_count,
+            self.render_resolution(),
+        );
+
+        // Pipelines bake in the format of their render target
+        self.rebuild_pipelines();
+    }
+
+    /// Recreate the pipelines that draw into the surface,
+    /// after its format or sample count changed
+    fn rebuild_pipelines(&mut self) {
+        let format = self.surface.config.format;
+        let sample_count = self.sample_count;
+
+        self.perf_graph
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.mesh_pipeline = create_mesh_pipeline(
+            &self.gpu.device,
+            &self.mesh_shader,
+            &[
+                &self.time_bind_group_layout,
+                &self.texture_bind_group_layout,
+                &self.camera_bind_group_layout,
+            ],
+            format,
+            sample_count,
+            self.mesh_primitive(),
+        );
+        self.automata
+            .rebuild_display_pipeline(&self.gpu.device, format, sample_count);
+        self.conservative_demo
+            .rebuild_display_pipeline(&self.gpu.device, format, sample_count);
+        self.splines
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.cutout_demo
+            .rebuild_pipelines(&self.gpu.device, format, sample_count);
+        self.depth_view
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.decal_demo.rebuild_pipelines(
+            &self.gpu.device,
+            &self.camera_bind_group_layout,
+            format,
+            sample_count,
+        );
+        self.debug_draw
+            .rebuild_pipelines(&self.gpu.device, format, sample_count);
+        self.point_cloud
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.sdf_scene
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.accumulation
+            .rebuild_display_pipeline(&self.gpu.device, format, sample_count);
+        self.texture_array_demo
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.wrapping_demo
+            .rebuild_pipeline(&self.gpu.device, format, sample_count);
+        self.upscaler.rebuild_pipeline(&self.gpu.device, format);
+        self.fade.rebuild_pipeline(&self.gpu.device, format);
+        if let Some(mandelbrot) = &mut self.mandelbrot {
+            mandelbrot.rebuild_display_pipeline(&self.gpu.device, format, sample_count);
+        }
+    }
+
+    /// Change the color the next frames are cleared to.
+    /// With `pulse_clear_color`, its green channel keeps being animated.
+    pub fn set_clear_color(&mut self, color: wgpu::Color) {
+        self.clear_color = color;
+        if let wgpu::LoadOp::Clear(_) = self.clear_config.color {
+            self.clear_config.color = wgpu::LoadOp::Clear(color);
+        }
+        self.dirty = true;
+    }
+
+    /// Change how the next frames start. Loading the previous contents needs
+    /// MSAA, since the surface itself doesn't keep them between frames:
+    /// without it, frames are cleared to the clear color instead.
+    pub fn set_clear_config(&mut self, clear_config: ClearConfig) {
+        if clear_config.color == wgpu::LoadOp::Load && self.targets.msaa.is_none() {
+            log::warn!(
+                "The surface doesn't preserve its contents, frames will be cleared until MSAA is on"
+            );
+        }
+        self.clear_config = clear_config;
+        self.dirty = true;
+    }
+
+    /// Reconfigure the surface to present with 'mode', e.g. Immediate to
+    /// benchmark without the framerate being capped by VSync.
+    /// Falls back to Fifo, with a warning, if the surface doesn't support it.
+    pub fn set_present_mode(&mut self, mode: wgpu::PresentMode) {
+        let mode = choose_present_mode(&self.surface.present_modes, mode);
+        if mode == self.surface.config.present_mode {
+            return;
+        }
+
+        self.surface.config.present_mode = mode;
+        self.surface.configure(&self.gpu.device);
+    }
+
+    /// Switch between Fifo (VSync on) and Mailbox or Immediate (VSync off),
+    /// skipping the modes the surface doesn't support
+    fn toggle_vsync(&mut self) {
+        let next_mode = if self.surface.config.present_mode == wgpu::PresentMode::Fifo {
+            // Mailbox doesn't tear, so prefer it over Immediate
+            [wgpu::PresentMode::Mailbox, wgpu::PresentMode::Immediate]
+                .into_iter()
+                .find(|mode| self.surface.present_modes.contains(mode))
+        } else {
+            Some(wgpu::PresentMode::Fifo)
+        };
+
+        let Some(next_mode) = next_mode else {
+            self.flash_message("VSync can't be turned off on this surface");
+            return;
+        };
+
+        self.set_present_mode(next_mode);
+
+        let state = if next_mode == wgpu::PresentMode::Fifo {
+            "ON"
+        } else {
+            "OFF"
+        };
+        self.flash_message(&format!("VSync: {state} ({next_mode:?})"));
+    }
+
+    /// Read shader.wgsl from disk again and rebuild the mesh pipeline with it,
+    /// to iterate on the shader without restarting. If it doesn't compile,
+    /// the error is logged and the current pipeline is kept.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_mesh_shader(&mut self) {
+        let source = match std::fs::read_to_string(MESH_SHADER_PATH) {
+            Ok(source) => source,
+            Err(e) => {
+                log::error!("Failed to read {MESH_SHADER_PATH}: {e}");
+                return;
+            }
+        };
+
+        let layouts = [
+            &self.time_bind_group_layout,
+            &self.texture_bind_group_layout,
+            &self.camera_bind_group_layout,
+        ];
+        let (format, sample_count) = (self.surface.config.format, self.sample_count);
+        let primitive = self.mesh_primitive();
+        // naga reports syntax errors and missing entry points more clearly,
+        // wgpu catches the rest while creating the module and the pipeline
+        let reloaded = shader::check_entry_points(MESH_SHADER_LABEL, &source, MESH_ENTRY_POINTS)
+            .and_then(|()| {
+                shader::capture_validation_errors(&self.gpu.device, MESH_SHADER_LABEL, || {
+                    let shader =
+                        self.gpu
+                            .device
+                            .create_shader_module(wgpu::ShaderModuleDescriptor {
+                                label: Some(MESH_SHADER_LABEL),
+                                source: wgpu::ShaderSource::Wgsl(source.as_str().into()),
+                            });
+                    let pipeline = create_mesh_pipeline(
+                        &self.gpu.device,
+                        &shader,
+                        &layouts,
+                        format,
+                        sample_count,
+                        primitive,
+                    );
+                    (shader, pipeline)
+                })
             });
 
-        let render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Pass"),
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
-                // The resolve_target is the texture that will receive the resolved output.
-                // This will be the same as view unless multisampling is enabled.
-                // We don't need to specify this, so we leave it as None.
-                resolve_target: None,
-                // These are the operations that should be performed by the GPU
-                ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color {
-                        r: 1.0,
-                        g: 1.0,
-                        b: 1.0,
-                        a: 1.0,
-                    }),
-                    // This tells wgpu to store the rendered result to the Texture
-                    // behind our TextureView (in this case, the SurfaceTexture)
-                    store: true,
-                },
-            })],
-            depth_stencil_attachment: None,
+        match reloaded {
+            Ok((shader, pipeline)) => {
+                self.mesh_shader = shader;
+                self.mesh_shader_source = source;
+                self.mesh_pipeline = pipeline;
+                self.flash_message("Reloaded shader.wgsl");
+            }
+            Err(e) => {
+                log::error!("{e}");
+                self.flash_message("shader.wgsl has errors, keeping the previous version");
+            }
+        }
+    }
+
+    /// A page can't read shader.wgsl from disk, nor wait for the validation
+    /// of the new pipeline
+    #[cfg(target_arch = "wasm32")]
+    fn reload_mesh_shader(&mut self) {
+        self.flash_message("Reloading the shader isn't supported in a browser");
+    }
+
+    /// Switch the mesh between filled and wireframe rendering.
+    /// Does nothing without POLYGON_MODE_LINE, which not every adapter supports.
+    fn toggle_wireframe(&mut self) {
+        if !self
+            .gpu
+            .device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE)
+        {
+            log::warn!(
+                "Wireframe rendering needs POLYGON_MODE_LINE, which the adapter doesn't support"
+            );
+            self.flash_message("Wireframe not supported");
+            return;
+        }
+
+        self.wireframe = !self.wireframe;
+        self.rebuild_pipelines();
+        self.flash_message(match self.wireframe {
+            true => "Wireframe",
+            false => "Filled",
         });
+    }
 
-        // begin_render_pass() borrows encoder mutably (aka &mut self).
-        // We can't call encoder.finish() until we release that mutable borrow,
-        // which we do manually via the explicit drop()
-        drop(render_pass);
+    /// Go from drawing both faces of the mesh to culling the back ones,
+    /// then the front ones
+    fn cycle_cull_mode(&mut self) {
+        self.cull_mode = match self.cull_mode {
+            None => Some(wgpu::Face::Back),
+            Some(wgpu::Face::Back) => Some(wgpu::Face::Front),
+            Some(wgpu::Face::Front) => None,
+        };
+        self.rebuild_pipelines();
+        self.flash_message(&format!("Cull mode: {:?}", self.cull_mode));
+    }
 
-        // This tells wgpu to 'finish' the command buffer
-        // and submit it to the GPU queue
-        self.queue.submit(std::iter::once(encoder.finish()));
-        surface_texture.present();
+    /// Swap which winding is the front face, which is what a mesh exported
+    /// with the other convention needs: with back faces culled, its visible
+    /// faces disappear and the hidden ones show up
+    fn flip_front_face(&mut self) {
+        self.front_face = match self.front_face {
+            wgpu::FrontFace::Ccw => wgpu::FrontFace::Cw,
+            wgpu::FrontFace::Cw => wgpu::FrontFace::Ccw,
+        };
+        self.rebuild_pipelines();
+        self.flash_message(&format!("Front face: {:?}", self.front_face));
+    }
 
-        Ok(())
+    fn mesh_primitive(&self) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            cull_mode: self.cull_mode,
+            front_face: self.front_face,
+            polygon_mode: match self.wireframe {
+                true => wgpu::PolygonMode::Line,
+                false => wgpu::PolygonMode::Fill,
+            },
+            ..default_mesh_primitive()
+        }
+    }
+
+    /// Draw a 'size' x 'size' grid of quads, clamped to [1, MAX_GRID_SIZE]
+    pub fn set_instance_grid_size(&mut self, size: u32) {
+        let size = size.clamp(1, vertex::MAX_GRID_SIZE);
+        if size == self.instance_grid_size {
+            return;
+        }
+
+        self.instance_grid_size = size;
+        (self.instance_buffer, self.instance_count) =
+            create_instance_buffer(&self.gpu.device, &self.memory, size);
+        self.flash_message(&format!("Instances: {size}x{size}"));
+    }
+
+    /// Show the image at 'path' on the quad. The current texture is kept if
+    /// the image can't be loaded.
+    fn load_mesh_texture(&mut self, path: &Path) {
+        let texture = match Texture::from_path(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.memory,
+            path,
+            self.texture_sampler,
+        ) {
+            Ok(texture) => texture,
+            Err(e) => {
+                log::error!("Failed to load the texture {path:?}: {e}");
+                self.flash_message("Not a supported image, keeping the current texture");
+                return;
+            }
+        };
+
+        // The new image can have another size, so there's a new texture and
+        // a new bind group: the old texture goes away with the old bind group
+        self.texture_bind_group =
+            texture.create_bind_group(&self.gpu.device, &self.texture_bind_group_layout);
+        self.mesh_texture = texture;
+        // White corners, which don't tint the image
+        (self.vertex_buffer, self.index_buffer) =
+            create_quad_buffers(&self.gpu.device, &self.memory, true);
+
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        let size = self.mesh_texture.texture.size();
+        self.flash_message(&format!(
+            "Texture: {} ({}x{})",
+            name.to_string_lossy(),
+            size.width,
+            size.height
+        ));
+    }
+
+    /// Switch between borderless fullscreen on the current monitor and a window.
+    /// The surface follows the window size through the Resized events of the
+    /// switch. Not every platform sends them right away though, so it's also
+    /// resized to whatever size the window reports now.
+    fn toggle_fullscreen(&mut self) {
+        let Some(window) = &self.window else {
+            return;
+        };
+
+        self.fullscreen = !self.fullscreen;
+        window.set_fullscreen(
+            self.fullscreen
+                .then_some(winit::window::Fullscreen::Borderless(None)),
+        );
+        let size = window.inner_size();
+        self.resize(size);
+        self.fade.fade_in(FULLSCREEN_TRANSITION);
+    }
+
+    /// Save the camera into 'slot' of the views directory.
+    /// Returns the path of the file it was written to.
+    pub fn save_view(&mut self, slot: u8) -> Result<PathBuf, ViewError> {
+        views::save(&self.views_dir, slot, &self.camera)
+    }
+
+    /// Fly the camera to the view saved in 'slot', over VIEW_TRANSITION.
+    /// Nothing changes when the slot is empty or its file can't be read.
+    pub fn load_view(&mut self, slot: u8) -> Result<(), ViewError> {
+        let camera = views::load(&self.views_dir, slot)?;
+        self.animate_camera_to(camera);
+
+        Ok(())
+    }
+
+    /// Start moving the camera towards 'camera', keeping the aspect ratio
+    /// of the window
+    fn animate_camera_to(&mut self, camera: Camera) {
+        let end = Camera {
+            aspect: self.camera.aspect,
+            ..camera
+        };
+        self.camera_animation = Some(Animation::new(
+            self.camera,
+            end,
+            VIEW_TRANSITION,
+            easing::ease_in_out_cubic,
+        ));
+        // The view comes with its own field of view
+        self.fov_animation = None;
+        self.dirty = true;
+    }
+
+    /// Widen the field of view by 'degrees', or narrow it when negative,
+    /// within the limits of the config. Repeated changes add up to where the
+    /// previous one was headed.
+    pub fn change_fov(&mut self, degrees: f32) {
+        let (min, max) = self.fov_limits;
+        let from = self
+            .fov_animation
+            .as_ref()
+            .map_or(self.camera.fovy, |animation| *animation.end());
+        let fovy = (from + degrees).clamp(min, max);
+
+        self.fov_animation = Some(Animation::new(
+            self.camera.fovy,
+            fovy,
+            FOV_TRANSITION,
+            easing::ease_out_cubic,
+        ));
+        self.dirty = true;
+    }
+
+    /// Fly to the next viewpoint of the config, showing its name.
+    /// Without any, the camera stays where it is.
+    pub fn next_camera(&mut self) {
+        let viewpoint = self.viewpoints.next().cloned();
+        self.fly_to_viewpoint(viewpoint);
+    }
+
+    /// Fly to the previous viewpoint of the config, showing its name
+    pub fn prev_camera(&mut self) {
+        let viewpoint = self.viewpoints.prev().cloned();
+        self.fly_to_viewpoint(viewpoint);
+    }
+
+    fn fly_to_viewpoint(&mut self, viewpoint: Option<Viewpoint>) {
+        match viewpoint {
+            Some(viewpoint) => {
+                self.animate_camera_to(viewpoint.camera);
+                self.flash_message(&format!("Camera: {}", viewpoint.name));
+            }
+            None => self.flash_message("No viewpoints in the config"),
+        }
+    }
+
+    /// Fly the camera to where the whole grid of quads is in view,
+    /// looking at it from the same direction
+    pub fn zoom_to_fit(&mut self) {
+        let instances = vertex::grid_instances(self.instance_grid_size);
+        let (center, radius) = vertex::bounding_sphere(vertex::QUAD_VERTICES, &instances);
+        self.animate_camera_to(self.camera.framing(center, radius));
+    }
+
+    /// Briefly show a message in the window title
+    fn flash_message(&mut self, message: &str) {
+        println!("{message}");
+        self.set_title(&format!("{} - {message}", self.app_name));
+        self.title_reset_at = Some(Instant::now() + Duration::from_secs(2));
+    }
+
+    /// Switch to the next supported MSAA sample count, wrapping back to 1x
+    fn cycle_sample_count(&mut self) {
+        let format = self.surface.config.format;
+        let supported = msaa::supported_sample_counts(&self.gpu.adapter, &self.gpu.device, format);
+        let next = supported
+            .iter()
+            .copied()
+            .find(|&count| count > self.sample_count)
+            .unwrap_or(1);
+
+        self.set_sample_count(next);
+    }
+
+    /// Deal with a frame that couldn't be rendered because of the surface.
+    /// Returns false when there's no recovering from it and the app should exit.
+    fn recover_from_surface_error(&mut self, error: wgpu::SurfaceError) -> bool {
+        match error {
+            // The surface is gone or no longer matches the window, e.g. when
+            // the window changed size faster than the Resized events came in:
+            // reconfigure it with the current size of the window
+            wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated => {
+                let size = self
+                    .window
+                    .as_ref()
+                    .map_or(self.surface.size, Window::inner_size);
+                self.resize(size);
+                true
+            }
+            // The frame is just skipped, the next one will likely make it.
+            // This happens routinely, e.g. while the window is hidden.
+            wgpu::SurfaceError::Timeout => {
+                log::debug!("Timed out getting a surface texture, skipping the frame");
+                true
+            }
+            wgpu::SurfaceError::OutOfMemory => {
+                log::error!("Out of memory, exiting");
+                false
+            }
+        }
+    }
+
+    /// Approximate GPU memory currently allocated by the app
+    pub fn gpu_memory_report(&self) -> MemoryReport {
+        self.memory.report()
+    }
+
+    /// Everything that decides how the current frame looks,
+    /// from the adapter down to the shaders of the visible demos
+    pub fn render_state_report(&self) -> RenderStateReport<'_> {
+        let show_mandelbrot = self.show_mandelbrot && self.mandelbrot.is_some();
+        let fullscreen_demos = self.show_automata
+            || self.show_conservative_demo
+            || self.show_sdf_scene
+            || show_mandelbrot
+            || self.show_depth_view
+            || self.targets.scene.is_some()
+            || self.fade.is_fading();
+        let shaders = [
+            (
+                true,
+                ActiveShader {
+                    file: "perf_graph.wgsl",
+                    source: include_str!("perf_graph.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                true,
+                ActiveShader {
+                    file: "shader.wgsl",
+                    source: &self.mesh_shader_source,
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                fullscreen_demos,
+                ActiveShader {
+                    file: "fullscreen.wgsl",
+                    source: fullscreen::FULLSCREEN_VERTEX_SHADER,
+                    entry_points: &["vs_fullscreen"],
+                },
+            ),
+            (
+                self.show_splines || self.show_plot,
+                ActiveShader {
+                    file: "spline.wgsl",
+                    source: include_str!("spline.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                self.show_automata,
+                ActiveShader {
+                    file: "automata.wgsl",
+                    source: include_str!("automata.wgsl"),
+                    entry_points: &["cs_step"],
+                },
+            ),
+            (
+                self.show_automata,
+                ActiveShader {
+                    file: "automata_display.wgsl",
+                    source: include_str!("automata_display.wgsl"),
+                    entry_points: &["fs_automata"],
+                },
+            ),
+            (
+                show_mandelbrot,
+                ActiveShader {
+                    file: "mandelbrot.wgsl",
+                    source: include_str!("mandelbrot.wgsl"),
+                    entry_points: &["cs_mandelbrot"],
+                },
+            ),
+            (
+                show_mandelbrot,
+                ActiveShader {
+                    file: "mandelbrot_display.wgsl",
+                    source: include_str!("mandelbrot_display.wgsl"),
+                    entry_points: &["fs_mandelbrot"],
+                },
+            ),
+            (
+                self.show_conservative_demo,
+                ActiveShader {
+                    file: "conservative.wgsl",
+                    source: include_str!("conservative.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                self.show_conservative_demo,
+                ActiveShader {
+                    file: "conservative_display.wgsl",
+                    source: include_str!("conservative_display.wgsl"),
+                    entry_points: &["fs_conservative"],
+                },
+            ),
+            (
+                self.show_cutout_demo,
+                ActiveShader {
+                    file: "cutout.wgsl",
+                    source: include_str!("cutout.wgsl"),
+                    entry_points: &["vs_main", "fs_discard", "fs_coverage"],
+                },
+            ),
+            (
+                self.show_depth_view,
+                ActiveShader {
+                    file: "depth_view.wgsl",
+                    source: include_str!("depth_view.wgsl"),
+                    entry_points: &["fs_depth_view"],
+                },
+            ),
+            (
+                self.show_decal_demo,
+                ActiveShader {
+                    file: "decal.wgsl",
+                    source: include_str!("decal.wgsl"),
+                    entry_points: &["vs_floor", "vs_decal", "fs_floor", "fs_decal"],
+                },
+            ),
+            (
+                self.show_debug_shapes,
+                ActiveShader {
+                    file: "debug_draw.wgsl",
+                    source: include_str!("debug_draw.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                self.show_point_cloud,
+                ActiveShader {
+                    file: "point_cloud.wgsl",
+                    source: include_str!("point_cloud.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                self.show_sdf_scene,
+                ActiveShader {
+                    file: "sdf.wgsl",
+                    source: include_str!("sdf.wgsl"),
+                    entry_points: &["fs_sdf"],
+                },
+            ),
+            (
+                self.show_sdf_scene && self.accumulate,
+                ActiveShader {
+                    file: "accumulation.wgsl",
+                    source: include_str!("accumulation.wgsl"),
+                    entry_points: &["fs_accumulation"],
+                },
+            ),
+            (
+                self.show_texture_array_demo,
+                ActiveShader {
+                    file: "texture_array.wgsl",
+                    source: include_str!("texture_array.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                self.show_wrapping_demo,
+                ActiveShader {
+                    file: "wrapping.wgsl",
+                    source: include_str!("wrapping.wgsl"),
+                    entry_points: &["vs_main", "fs_main"],
+                },
+            ),
+            (
+                self.targets.scene.is_some(),
+                ActiveShader {
+                    file: "upscale.wgsl",
+                    source: include_str!("upscale.wgsl"),
+                    entry_points: &["fs_upscale"],
+                },
+            ),
+            (
+                self.fade.is_fading(),
+                ActiveShader {
+                    file: "fade.wgsl",
+                    source: include_str!("fade.wgsl"),
+                    entry_points: &["fs_fade"],
+                },
+            ),
+        ];
+
+        RenderStateReport {
+            adapter: self.gpu.adapter.get_info(),
+            features: self.gpu.device.features(),
+            surface_config: &self.surface.config,
+            sample_count: self.sample_count,
+            render_size: self.targets.size,
+            shaders: shaders
+                .into_iter()
+                .filter(|(active, _)| *active)
+                .map(|(_, shader)| shader)
+                .collect(),
+        }
+    }
+
+    // input() returns a bool to indicate whether an event has been fully processed.
+    // If the method returns true, the main loop won't process the event any further.
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        if let Some(latency_probe) = &mut self.latency_probe {
+            latency_probe.on_input(event);
+        }
+
+        let handled = match event {
+            // Print how much GPU memory we're using
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::M),
+                        ..
+                    },
+                ..
+            } => {
+                println!("{}", self.gpu_memory_report());
+                true
+            }
+            // List every tracked GPU resource
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::G),
+                        ..
+                    },
+                ..
+            } => {
+                println!("{}", self.memory.resources());
+                println!("{} live buffers and textures", self.memory.live_count());
+                true
+            }
+            // Print the render state, to attach to bug reports
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::I),
+                        ..
+                    },
+                ..
+            } => {
+                println!("{}", self.render_state_report());
+                true
+            }
+            // Toggle VSync
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::V),
+                        ..
+                    },
+                ..
+            } => {
+                self.toggle_vsync();
+                true
+            }
+            // Toggle borderless fullscreen
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F11),
+                        ..
+                    },
+                ..
+            } => {
+                self.toggle_fullscreen();
+                true
+            }
+            // Pause or resume the spinning of the mesh
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Q),
+                        ..
+                    },
+                ..
+            } => {
+                self.spin_mesh = !self.spin_mesh;
+                true
+            }
+            // Switch between filled and wireframe rendering of the mesh
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::W),
+                        ..
+                    },
+                ..
+            } => {
+                self.toggle_wireframe();
+                true
+            }
+            // Cycle through the faces of the mesh that are culled
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::X),
+                        ..
+                    },
+                ..
+            } => {
+                self.cycle_cull_mode();
+                true
+            }
+            // Flip the winding of the front faces of the mesh
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Z),
+                        ..
+                    },
+                ..
+            } => {
+                self.flip_front_face();
+                true
+            }
+            // Reload shader.wgsl from disk. On F5 like a page reload, R has
+            // been toggling the conservative rasterization demo since before
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F5),
+                        ..
+                    },
+                ..
+            } => {
+                self.reload_mesh_shader();
+                true
+            }
+            // Grow or shrink the grid of instances
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode:
+                            Some(
+                                key @ (VirtualKeyCode::Equals
+                                | VirtualKeyCode::Plus
+                                | VirtualKeyCode::NumpadAdd
+                                | VirtualKeyCode::Minus
+                                | VirtualKeyCode::NumpadSubtract),
+                            ),
+                        ..
+                    },
+                ..
+            } => {
+                let size = match key {
+                    VirtualKeyCode::Minus | VirtualKeyCode::NumpadSubtract => {
+                        self.instance_grid_size.saturating_sub(1)
+                    }
+                    _ => self.instance_grid_size + 1,
+                };
+                self.set_instance_grid_size(size);
+                true
+            }
+            // Cycle through the surface formats
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F),
+                        ..
+                    },
+                ..
+            } => {
+                self.cycle_surface_format();
+                true
+            }
+            // Cycle through the MSAA sample counts
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::A),
+                        ..
+                    },
+                ..
+            } => {
+                self.cycle_sample_count();
+                true
+            }
+            // Toggle the Game of Life
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::L),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_automata = !self.show_automata;
+                true
+            }
+            // Toggle the conservative rasterization demo
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::R),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_conservative_demo = !self.show_conservative_demo;
+                true
+            }
+            // Toggle the spline demo
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::S),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_splines = !self.show_splines;
+                true
+            }
+            // Toggle the plot demo
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::P),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_plot = !self.show_plot;
+                true
+            }
+            // Toggle the cutout transparency demo
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::T),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_cutout_demo = !self.show_cutout_demo;
+                true
+            }
+            // Toggle the view of the depth buffer
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::N),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_depth_view = !self.show_depth_view;
+                if self.show_depth_view && !self.targets.depth_sampled {
+                    self.flash_message("The depth view needs MSAA off on this backend");
+                }
+                true
+            }
+            // Toggle the decal on the floor
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::E),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_decal_demo = !self.show_decal_demo;
+                true
+            }
+            // Toggle the depth bias of the decal, it z-fights with the floor without it
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::H),
+                        ..
+                    },
+                ..
+            } => {
+                let biased = !self.decal_demo.is_biased();
+                self.decal_demo.set_biased(
+                    biased,
+                    &self.gpu.device,
+                    &self.camera_bind_group_layout,
+                    self.surface.config.format,
+                    self.sample_count,
+                );
+                self.flash_message(&format!(
+                    "Decal depth bias: {}",
+                    if biased { "on" } else { "off" }
+                ));
+                true
+            }
+            // Toggle the ray marched distance field scene
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::D),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_sdf_scene = !self.show_sdf_scene;
+                true
+            }
+            // Toggle the progressive rendering of the distance field scene,
+            // which freezes its camera until toggled off
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::J),
+                        ..
+                    },
+                ..
+            } => {
+                self.accumulate = !self.accumulate;
+                if self.accumulate {
+                    self.show_sdf_scene = true;
+                } else {
+                    // The title showed the sample count
+                    self.set_title(&self.app_name);
+                }
+                self.accumulation.reset();
+                true
+            }
+            // Toggle trails: the previous frame is kept instead of cleared,
+            // and everything that moves smears over it
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::K),
+                        ..
+                    },
+                ..
+            } => {
+                let color = match self.clear_config.color {
+                    wgpu::LoadOp::Load => wgpu::LoadOp::Clear(self.clear_color),
+                    wgpu::LoadOp::Clear(_) => wgpu::LoadOp::Load,
+                };
+                self.flash_message(if color == wgpu::LoadOp::Load {
+                    "Trails: on"
+                } else {
+                    "Trails: off"
+                });
+                self.set_clear_config(ClearConfig {
+                    color,
+                    ..self.clear_config
+                });
+                true
+            }
+            // Toggle the texture array demo
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Y),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_texture_array_demo = !self.show_texture_array_demo;
+                true
+            }
+            // Toggle the debug shapes: the bounds and normals of the quads,
+            // and the world axes
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::O),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_debug_shapes = !self.show_debug_shapes;
+                true
+            }
+            // Toggle the point cloud, or with Shift switch its points between
+            // a size in pixels and a size in world units
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F2),
+                        ..
+                    },
+                ..
+            } => {
+                if self.modifiers.shift() {
+                    let (size, name) = match self.point_cloud.size() {
+                        PointSize::Screen(_) => (PointSize::World(0.02), "world units"),
+                        PointSize::World(_) => (PointSize::Screen(3.0), "pixels"),
+                    };
+                    self.point_cloud.set_size(size);
+                    self.flash_message(&format!("Point sizes in {name}"));
+                } else {
+                    self.show_point_cloud = !self.show_point_cloud;
+                }
+                true
+            }
+            // Cycle the resolution the scene is rendered at, or with Shift
+            // toggle the pixel-perfect mode
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F3),
+                        ..
+                    },
+                ..
+            } => {
+                if self.modifiers.shift() {
+                    self.toggle_pixel_perfect();
+                } else {
+                    self.cycle_render_scale();
+                }
+                true
+            }
+            // Toggle the adaptation of the render scale to the frame times
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::F4),
+                        ..
+                    },
+                ..
+            } => {
+                self.toggle_dynamic_resolution();
+                true
+            }
+            // Toggle the repeating and clamped textures
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::U),
+                        ..
+                    },
+                ..
+            } => {
+                self.show_wrapping_demo = !self.show_wrapping_demo;
+                true
+            }
+            // Toggle the Mandelbrot set computed by a compute shader
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::B),
+                        ..
+                    },
+                ..
+            } => {
+                if self.mandelbrot.is_some() {
+                    self.show_mandelbrot = !self.show_mandelbrot;
+                } else {
+                    self.flash_message("The Mandelbrot demo isn't supported by this device");
+                }
+                true
+            }
+            // Change the colors of the Game of Life
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::C),
+                        ..
+                    },
+                ..
+            } => {
+                self.automata.next_palette();
+                true
+            }
+            // Narrow and widen the field of view
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode:
+                            Some(key @ (VirtualKeyCode::Comma | VirtualKeyCode::Period)),
+                        ..
+                    },
+                ..
+            } => {
+                let sign = if *key == VirtualKeyCode::Comma {
+                    -1.0
+                } else {
+                    1.0
+                };
+                self.change_fov(sign * FOV_STEP);
+                true
+            }
+            // Cycle through the viewpoints of the config
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::PageDown),
+                        ..
+                    },
+                ..
+            } => {
+                self.next_camera();
+                true
+            }
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::PageUp),
+                        ..
+                    },
+                ..
+            } => {
+                self.prev_camera();
+                true
+            }
+            // Frame the grid of quads
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(VirtualKeyCode::Home),
+                        ..
+                    },
+                ..
+            } => {
+                self.zoom_to_fit();
+                true
+            }
+            // Save the view into the slot of a digit while Ctrl is held,
+            // restore it otherwise
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state: ElementState::Pressed,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } if views::key_slot(*key).is_some() => {
+                let slot = views::key_slot(*key).expect("checked by the guard");
+                if self.modifiers.ctrl() {
+                    match self.save_view(slot) {
+                        Ok(path) => self.flash_message(&format!("Saved the view to {path:?}")),
+                        Err(e) => {
+                            log::error!("{e}");
+                            self.flash_message(&format!("Failed to save view {slot}"));
+                        }
+                    }
+                } else if let Err(e) = self.load_view(slot) {
+                    if !matches!(e, ViewError::EmptySlot(_)) {
+                        log::error!("{e}");
+                    }
+                    self.flash_message(&e.to_string());
+                }
+                true
+            }
+            // Fly the camera while the movement keys are held, which
+            // interrupts the flight to a loaded view
+            WindowEvent::KeyboardInput {
+                input:
+                    KeyboardInput {
+                        state,
+                        virtual_keycode: Some(key),
+                        ..
+                    },
+                ..
+            } if self.camera_controller.process_key(*key, *state) => {
+                self.camera_animation = None;
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers = *modifiers;
+                false
+            }
+            // Show a dropped image on the quad
+            WindowEvent::DroppedFile(path) => {
+                self.load_mesh_texture(path);
+                true
+            }
+            // Whatever changed in the meantime is rendered again as soon as
+            // the window is back, e.g. a resize while it was in the background
+            WindowEvent::Focused(focused) => {
+                self.focused = *focused;
+                // The release may go to another window
+                if !focused {
+                    self.orbit_from = None;
+                }
+                true
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.occluded = *occluded;
+                true
+            }
+            // Winit already reports the cursor in physical pixels,
+            // which is the same space as the framebuffer
+            WindowEvent::CursorMoved { position, .. } => {
+                self.cursor_position = *position;
+                if let Some(from) = self.orbit_from {
+                    self.camera_animation = None;
+                    // Dragging right turns the camera to the left of the
+                    // target, dragging down lifts it above
+                    self.camera.orbit(
+                        -(position.x - from.x) as f32 * camera::ORBIT_SPEED,
+                        (position.y - from.y) as f32 * camera::ORBIT_SPEED,
+                    );
+                    self.orbit_from = Some(*position);
+                }
+                if self.mouse_clear_color {
+                    // Left to right goes from no red to full red,
+                    // top to bottom from no blue to full blue
+                    let (x, y) = normalized_position(*position, self.surface.size);
+                    self.set_clear_color(wgpu::Color {
+                        r: x,
+                        b: y,
+                        ..self.clear_color
+                    });
+                }
+                self.mouse_clear_color || self.orbit_from.is_some()
+            }
+            // Dragging with the left button orbits the camera around its target.
+            // The drag starts from where the button was pressed, the position
+            // of the last move may be long gone.
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.orbit_from = match state {
+                    ElementState::Pressed => Some(self.cursor_position),
+                    ElementState::Released => None,
+                };
+                false
+            }
+            // The mouse wheel zooms the camera in and out, or changes the
+            // field of view while Ctrl is held
+            WindowEvent::MouseWheel { delta, .. } => {
+                if self.modifiers.ctrl() {
+                    // Scrolling up zooms in, like moving closer does
+                    self.change_fov(-camera::scroll_lines(delta) * FOV_STEP);
+                } else {
+                    self.camera_animation = None;
+                    self.camera.zoom(delta);
+                }
+                true
+            }
+            _ => false,
+        };
+
+        // Whatever we reacted to may change what's on screen
+        self.dirty |= handled;
+        handled
+    }
+
+    /// Whether there's something new to show since the last frame.
+    /// This is what the OnChange render policy checks before redrawing.
+    fn needs_redraw(&self) -> bool {
+        self.dirty || self.is_animating()
+    }
+
+    /// How much of the window the user can see, which decides how often
+    /// it's redrawn
+    fn visibility(&self) -> Visibility {
+        match (self.occluded, self.focused) {
+            (true, _) => Visibility::Occluded,
+            (false, false) => Visibility::Unfocused,
+            (false, true) => Visibility::Focused,
+        }
+    }
+
+    /// Whether something changes on screen every frame, even without input.
+    /// When nothing does, the OnChange policy lets the event loop sleep.
+    fn is_animating(&self) -> bool {
+        let palette_animating = !self.automata.is_palette_settled();
+
+        self.show_automata
+            || (self.show_sdf_scene && !(self.accumulate && self.accumulation.is_converged()))
+            || palette_animating
+            || self.spin_mesh
+            || self.camera_controller.is_moving()
+            || self.camera_animation.is_some()
+            || self.fov_animation.is_some()
+            || self.fade.is_fading()
+            || self.pulse_clear_color
+            || self.title_reset_at.is_some()
+    }
+
+    /// Position of the cursor in framebuffer pixels, (0, 0) being the top left.
+    /// This is what picking should use: on high-DPI screens the logical
+    /// position would be off by the scale factor.
+    #[allow(dead_code)]
+    pub fn cursor_physical_position(&self) -> (u32, u32) {
+        let max_x = self.surface.size.width.saturating_sub(1);
+        let max_y = self.surface.size.height.saturating_sub(1);
+
+        // The cursor can be outside the window while dragging
+        let x = (self.cursor_position.x.max(0.0) as u32).min(max_x);
+        let y = (self.cursor_position.y.max(0.0) as u32).min(max_y);
+
+        (x, y)
+    }
+
+    /// Wait for the GPU to finish all the submitted work before exiting.
+    /// Tearing down the device while frames are still in flight makes some
+    /// drivers complain about resources being destroyed while in use.
+    pub fn shutdown(&mut self) {
+        self.gpu.device.poll(wgpu::Maintain::Wait);
+        // The wait completed any pending readback of the timestamps:
+        // picking it up unmaps the buffer before it's destroyed
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.pass_time(&self.gpu.device);
+        }
+
+        if let Some(timings_log) = &mut self.timings_log {
+            if let Err(e) = timings_log.flush() {
+                log::error!("Failed to write the frame times: {e}");
+            }
+        }
+
+        log::info!(
+            "Shut down after {:.1} s, the GPU is idle",
+            self.start_time.elapsed().as_secs_f64()
+        );
+    }
+
+    /// Handle the events posted through the EventLoopProxy
+    fn user_event(&mut self, event: UserEvent) {
+        match event {
+            UserEvent::ReloadShader => self.reload_mesh_shader(),
+            UserEvent::LoadModel(path) => {
+                log::warn!(
+                    "Received a request to load {path:?}, but model loading is not supported yet"
+                );
+            }
+            // Quit is handled directly by the event loop
+            UserEvent::Quit => {}
+        }
+
+        // Posting an event wakes up a sleeping event loop, but with the
+        // OnChange policy it only redraws if something is marked as changed
+        // (e.g. a reloaded shader or a model that finished loading)
+        self.dirty = true;
+    }
+
+    /// A few curves of different widths, to show off the spline renderer
+    fn queue_demo_splines(&mut self) {
+        let (width, height) = (
+            self.targets.size.width as f32,
+            self.targets.size.height as f32,
+        );
+        let wave: Vec<[f32; 2]> = (0..=8)
+            .map(|i| {
+                let x = width * (0.1 + 0.1 * i as f32);
+                let y = height * if i % 2 == 0 { 0.4 } else { 0.6 };
+                [x, y]
+            })
+            .collect();
+
+        self.splines.draw_spline(&wave, 12.0, [0.1, 0.4, 0.9, 1.0]);
+        self.splines.draw_spline(
+            &[
+                [width * 0.2, height * 0.85],
+                [width * 0.5, height * 0.7],
+                [width * 0.8, height * 0.85],
+            ],
+            3.0,
+            [0.9, 0.3, 0.1, 1.0],
+        );
+        self.splines.draw_spline(
+            &[[width * 0.1, height * 0.2], [width * 0.9, height * 0.25]],
+            1.0,
+            [0.0, 0.0, 0.0, 1.0],
+        );
+    }
+
+    /// The bounds of the grid and the normals of the quads, hidden by the
+    /// quads in front of them, and the world axes over everything
+    fn queue_debug_shapes(&mut self) {
+        let instances = vertex::grid_instances(self.instance_grid_size);
+        let (center, radius) = vertex::bounding_sphere(vertex::QUAD_VERTICES, &instances);
+        let depth_tested = self.debug_draw.depth_tested();
+        depth_tested.sphere(center, radius, [1.0, 0.9, 0.2, 1.0]);
+        // Around each quad whatever its rotation: as far as its corners
+        let half_size = std::f32::consts::FRAC_1_SQRT_2;
+        for instance in &instances {
+            let [x, y, z] = instance.offset;
+            depth_tested.aabb(
+                Aabb {
+                    min: [x - half_size, y - half_size, z],
+                    max: [x + half_size, y + half_size, z],
+                },
+                [0.2, 0.8, 0.9, 1.0],
+            );
+            depth_tested.arrow(instance.offset, [x, y, z + 0.3], [0.9, 0.3, 0.9, 1.0]);
+        }
+
+        let on_top = self.debug_draw.on_top();
+        on_top.arrow([0.0; 3], [0.5, 0.0, 0.0], [1.0, 0.0, 0.0, 1.0]);
+        on_top.arrow([0.0; 3], [0.0, 0.5, 0.0], [0.0, 1.0, 0.0, 1.0]);
+        on_top.arrow([0.0; 3], [0.0, 0.0, 0.5], [0.0, 0.0, 1.0, 1.0]);
+    }
+
+    /// Time elapsed since the previous call, i.e. the duration of the last frame
+    fn measure_frame_time(&mut self) -> Duration {
+        let now = Instant::now();
+        let frame_time = now - self.last_frame;
+        self.last_frame = now;
+
+        frame_time
+    }
+
+    /// The CPU and GPU times of the last frames
+    pub fn stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    /// Called once per frame: runs as many fixed updates as needed
+    /// to catch up with the real time elapsed since the last frame
+    fn update(&mut self, frame_time: Duration) {
+        self.render_stats = RenderStats {
+            frame_time,
+            gpu_pass_time: self
+                .gpu_timer
+                .as_mut()
+                .and_then(|gpu_timer| gpu_timer.pass_time(&self.gpu.device)),
+        };
+        self.perf_graph.push(frame_time);
+        // The pixel-perfect mode has a fixed resolution
+        if self.adapt_resolution && self.pixel_perfect.is_none() {
+            // The GPU time is the one the scale changes, the frame time also
+            // includes waiting for vsync
+            let time = self.render_stats.gpu_pass_time.unwrap_or(frame_time);
+            if let Some(scale) = self.dynamic_resolution.update(time, self.render_scale) {
+                self.set_render_scale(scale);
+            }
+        }
+        if let Some(report) = self.fps_counter.push(frame_time) {
+            // Flash messages and the accumulated sample count take precedence
+            let title_busy =
+                self.title_reset_at.is_some() || (self.show_sdf_scene && self.accumulate);
+            if !title_busy {
+                let gpu_time = self
+                    .stats()
+                    .gpu_pass_time
+                    .map(|time| format!(", GPU {:.1} ms", time.as_secs_f64() * 1000.0))
+                    .unwrap_or_default();
+                self.set_title(&format!(
+                    "{} - {:.0} FPS ({:.1} ms{gpu_time})",
+                    self.app_name,
+                    report.fps,
+                    report.frame_time.as_secs_f64() * 1000.0
+                ));
+            }
+        }
+        if let Some(timings_log) = &mut self.timings_log {
+            // The GPU time is from a frame or two ago, it's read back asynchronously
+            if let Err(e) = timings_log.record(frame_time, self.render_stats.gpu_pass_time) {
+                log::error!("Failed to write the frame times, stopping the log: {e}");
+                self.timings_log = None;
+            }
+        }
+        self.automata.animate(&self.gpu.queue, frame_time);
+        if self.pulse_clear_color {
+            // The green channel goes back and forth between 0 and 1
+            let elapsed = self.start_time.elapsed().as_secs_f64();
+            self.set_clear_color(wgpu::Color {
+                g: 0.5 + 0.5 * elapsed.sin(),
+                ..self.clear_color
+            });
+        }
+        if self.spin_mesh {
+            self.mesh_time += frame_time;
+        }
+        if let Some(animation) = &mut self.camera_animation {
+            let finished = animation.update(frame_time);
+            // The window may have been resized in the meantime
+            self.camera = Camera {
+                aspect: self.camera.aspect,
+                ..animation.value()
+            };
+            if finished {
+                self.camera_animation = None;
+            }
+        }
+        if let Some(animation) = &mut self.fov_animation {
+            let finished = animation.update(frame_time);
+            self.camera.fovy = animation.value();
+            if finished {
+                self.fov_animation = None;
+            }
+        }
+        self.fade.update(frame_time);
+        #[cfg(feature = "gamepad")]
+        self.poll_gamepad();
+        // Moved by the time the frame took, not by a fixed step per frame
+        self.camera_controller
+            .update_camera(&mut self.camera, frame_time);
+        // The camera stays still while the samples accumulate
+        if self.show_sdf_scene && !self.accumulate {
+            self.sdf_scene.update(frame_time);
+        }
+
+        if self
+            .title_reset_at
+            .is_some_and(|reset_at| Instant::now() >= reset_at)
+        {
+            self.set_title(&self.app_name);
+            self.title_reset_at = None;
+        }
+
+        let steps = self.timestep.advance(frame_time);
+        let dt = self.timestep.step().as_secs_f32();
+
+        for _ in 0..steps {
+            self.fixed_update(dt);
+        }
+    }
+
+    /// Feed the gamepad into the camera controller, and tell when it's
+    /// plugged in or out
+    #[cfg(feature = "gamepad")]
+    fn poll_gamepad(&mut self) {
+        let Some(gamepad) = &mut self.gamepad else {
+            return;
+        };
+
+        match gamepad.poll(&mut self.camera_controller) {
+            Some(gamepad::Connection::Connected(name)) => {
+                self.flash_message(&format!("Gamepad connected: {name}"))
+            }
+            Some(gamepad::Connection::Disconnected(name)) => {
+                self.flash_message(&format!("Gamepad disconnected: {name}"))
+            }
+            None => {}
+        }
+        // Flying to a view stops as soon as the sticks move
+        if self.camera_controller.is_moving() {
+            self.camera_animation = None;
+        }
+    }
+
+    /// Advance the simulation by exactly 'dt' seconds.
+    /// Anything that needs to be deterministic (physics, animation)
+    /// should happen here rather than in update().
+    fn fixed_update(&mut self, dt: f32) {
+        if self.show_automata {
+            self.automata.update(Duration::from_secs_f32(dt));
+        }
+    }
+
+    /// Perform the actual magic of rendering to the window.
+    /// Returns whether a frame was rendered, which it isn't while minimized.
+    fn render(&mut self) -> Result<bool, wgpu::SurfaceError> {
+        // Nothing is visible, and the surface doesn't match the window anyway
+        if !is_renderable(self.surface.size) {
+            return Ok(false);
+        }
+
+        let frame = self.surface.acquire(&self.gpu.device, &self.memory)?;
+        self.dirty = false;
+
+        // This line creates a TextureView in the same format as the surface.
+        // We need to do this because we want to control how the render
+        // code interacts with the texture.
+        let view = self
+            .surface
+            .view(&frame, self.surface.config.format.is_srgb());
+        self.draw_frame(&view);
+
+        frame.present();
+        if let Some(latency_probe) = &mut self.latency_probe {
+            latency_probe.on_present();
+        }
+
+        Ok(true)
+    }
+
+    /// Render the same frame as render() into a new 'width' x 'height'
+    /// texture rather than the window, and read it back.
+    /// The surface format has to be an 8 bit RGBA or BGRA one.
+    pub fn render_to_image(
+        &mut self,
+        width: u32,
+        height: u32,
+    ) -> Result<image::RgbaImage, ReadbackError> {
+        let format = self.surface.config.format;
+        let bgra = match format {
+            wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+            _ => return Err(ReadbackError::UnsupportedFormat(format)),
+        };
+        if width == 0 || height == 0 {
+            return Err(ReadbackError::EmptyRegion { width, height });
+        }
+
+        // Like the surface, so that the same pipelines can draw into it
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            width,
+            height,
+            ..self.surface.config.clone()
+        };
+        let texture = self.memory.create_texture(
+            &self.gpu.device,
+            &wgpu::TextureDescriptor {
+                label: Some("Image Texture"),
+                size: wgpu::Extent3d {
+                    width: config.width,
+                    height: config.height,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format,
+                usage: config.usage,
+                view_formats: &[],
+            },
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        // The image gets targets and a camera of its own size for the frame,
+        // to be back to the ones of the window once it's rendered
+        let targets = RenderTargets::new(
+            &self.gpu,
+            &self.memory,
+            &config,
+            self.sample_count,
+            self.render_resolution(),
+        );
+        let aspect = targets.aspect_ratio();
+        let window_targets = std::mem::replace(&mut self.targets, targets);
+        let window_aspect = std::mem::replace(&mut self.camera.aspect, aspect);
+
+        self.draw_frame(&view);
+
+        self.targets = window_targets;
+        self.camera.aspect = window_aspect;
+
+        let mut texels = readback::read_texture_region(
+            &self.gpu.device,
+            &self.gpu.queue,
+            &self.memory,
+            &texture,
+            (0, 0, config.width, config.height),
+        )?;
+        if bgra {
+            for texel in texels.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+        }
+
+        // The texels come back tightly packed, which is the layout of an RgbaImage
+        Ok(
+            image::RgbaImage::from_raw(config.width, config.height, texels)
+                .expect("a readback of the whole texture has exactly width * height texels"),
+        )
+    }
+
+    /// Record everything that's drawn in a frame into 'view', with the
+    /// current targets, and submit it. The scene has the size of the targets,
+    /// and is scaled to 'view' when the targets have a scene texture.
+    fn draw_frame(&mut self, view: &wgpu::TextureView) {
+        let size = self.targets.size;
+        // We also need to create a CommandEncoder to create the actual
+        // commands to send to the gpu. Most modern graphics frameworks
+        // expect commands to be stored in a command buffer before being
+        // sent to the gpu. The encoder builds a command buffer that we
+        // can then send to the gpu.
+        // Each pass can be recorded on its own encoder: the FrameBuilder
+        // collects the resulting command buffers and submits them together.
+        let mut frame = FrameBuilder::new();
+
+        // Compute work has to be recorded outside of the render pass,
+        // and must be submitted first since the render pass reads its output
+        let mandelbrot = self.mandelbrot.as_mut().filter(|_| self.show_mandelbrot);
+        if self.show_automata || mandelbrot.is_some() {
+            let mut compute_encoder =
+                self.gpu
+                    .device
+                    .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                        label: Some("Compute Encoder"),
+                    });
+            debug_group(&mut compute_encoder, "Compute", |encoder| {
+                if self.show_automata {
+                    self.automata.encode(encoder);
+                }
+                if let Some(mandelbrot) = mandelbrot {
+                    mandelbrot.encode(encoder);
+                }
+            });
+            frame.add_encoder(compute_encoder.finish());
+        }
+
+        let mut encoder = self
+            .gpu
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        if self.show_splines {
+            self.queue_demo_splines();
+        }
+        if self.show_debug_shapes {
+            self.queue_debug_shapes();
+        }
+        if self.show_plot {
+            self.plot.draw(&mut self.splines, size);
+        }
+
+        // The copies out of the staging belt are recorded before the render pass
+        let mut writer = BufferWriter {
+            device: &self.gpu.device,
+            queue: &self.gpu.queue,
+            encoder: &mut encoder,
+            staging_belt: self.staging_belt.as_mut(),
+        };
+        writer.write(
+            &self.camera_buffer,
+            0,
+            bytemuck::bytes_of(&CameraUniforms::new(&self.camera)),
+        );
+        writer.write(
+            &self.time_buffer,
+            0,
+            bytemuck::bytes_of(&TimeUniforms::new(self.mesh_time)),
+        );
+        self.perf_graph.prepare(&mut writer);
+        self.fade.prepare(&mut writer);
+        self.splines.prepare(&mut writer, size);
+        self.debug_draw.prepare(&mut writer);
+        if self.show_point_cloud {
+            self.point_cloud.prepare(&mut writer, &self.camera, size);
+        }
+        let accumulating = self.show_sdf_scene && self.accumulate;
+        let sample = if accumulating {
+            self.accumulation.next_sample(self.sdf_scene.camera())
+        } else {
+            None
+        };
+        if self.show_sdf_scene {
+            self.sdf_scene.prepare(&mut writer, size, sample);
+        }
+        if self.show_depth_view {
+            self.depth_view.prepare(&mut writer, &self.camera);
+        }
+
+        // Blend one more sample in, before the render pass shows the average
+        if sample.is_some() {
+            let mut pass = self.accumulation.begin_pass(&mut encoder);
+            self.sdf_scene.draw_sample(&mut pass);
+            drop(pass);
+
+            self.set_title(&format!(
+                "{} - {} samples",
+                self.app_name,
+                self.accumulation.sample_count()
+            ));
+        }
+
+        // The latency probe flashes the whole screen every frame
+        let load = match &self.latency_probe {
+            Some(latency_probe) => wgpu::LoadOp::Clear(latency_probe.flash_color()),
+            None => self
+                .clear_config
+                .color_load_op(self.targets.msaa.is_some(), self.clear_color),
+        };
+
+        // At another size than the surface, the scene is rendered into a
+        // texture of its own first
+        let scene_view = match &self.targets.scene {
+            Some((_, scene_view)) => scene_view,
+            None => view,
+        };
+        // With MSAA we render into the multisampled texture,
+        // which then gets resolved into the surface texture
+        let (color_view, resolve_target) = match &self.targets.msaa {
+            Some((_, msaa_view)) => (msaa_view, Some(scene_view)),
+            None => (scene_view, None),
+        };
+
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.begin(&mut encoder);
+        }
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: color_view,
+                // The resolve_target is the texture that will receive the resolved output.
+                // This will be the same as view unless multisampling is enabled.
+                resolve_target,
+                // These are the operations that should be performed by the GPU
+                ops: wgpu::Operations {
+                    load,
+                    // This tells wgpu to store the rendered result to the Texture
+                    // behind our TextureView (in this case, the SurfaceTexture)
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.targets.depth.1,
+                depth_ops: Some(wgpu::Operations {
+                    load: self.clear_config.depth,
+                    // Only the next frame and the depth view read it once this pass is done
+                    store: self.clear_config.store_depth()
+                        || (self.show_depth_view && self.targets.depth_sampled),
+                }),
+                stencil_ops: None,
+            }),
+        });
+
+        debug_group(&mut render_pass, "Scene", |pass| {
+            pass.set_pipeline(&self.mesh_pipeline);
+            pass.set_bind_group(0, &self.time_bind_group, &[]);
+            pass.set_bind_group(1, &self.texture_bind_group, &[]);
+            pass.set_bind_group(2, &self.camera_bind_group, &[]);
+            pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+            pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+            pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            pass.draw_indexed(0..self.index_count, 0, 0..self.instance_count);
+            // The distance field covers the whole screen, so it goes right after
+            if self.show_sdf_scene && !accumulating {
+                self.sdf_scene.draw(pass);
+            }
+            if self.show_cutout_demo {
+                self.cutout_demo.draw(pass);
+            }
+            if self.show_decal_demo {
+                self.decal_demo.draw(pass, &self.camera_bind_group);
+            }
+            if self.show_texture_array_demo {
+                self.texture_array_demo.draw(pass);
+            }
+            if self.show_wrapping_demo {
+                self.wrapping_demo.draw(pass);
+            }
+            if self.show_point_cloud {
+                self.point_cloud.draw(pass, &self.camera_bind_group);
+            }
+            // After the rest of the scene, which hides the depth-tested lines
+            self.debug_draw.draw(pass, &self.camera_bind_group);
+            self.splines.draw(pass);
+        });
+
+        // Images computed in earlier passes, shown in screen space over the scene
+        debug_group(&mut render_pass, "Post", |pass| {
+            if accumulating {
+                self.accumulation.draw(pass);
+            }
+            if self.show_automata {
+                self.automata.draw(pass);
+            }
+            if let Some(mandelbrot) = self.mandelbrot.as_ref().filter(|_| self.show_mandelbrot) {
+                mandelbrot.draw(pass);
+            }
+            if self.show_conservative_demo {
+                self.conservative_demo.draw(pass);
+            }
+        });
+
+        // Overlays are drawn last, on top of everything else
+        debug_group(&mut render_pass, "UI", |pass| {
+            self.perf_graph.draw(pass);
+        });
+
+        // begin_render_pass() borrows encoder mutably (aka &mut self).
+        // We can't call encoder.finish() until we release that mutable borrow,
+        // which we do manually via the explicit drop()
+        drop(render_pass);
+        if self.show_depth_view && self.targets.depth_sampled {
+            self.depth_view.encode(
+                &self.gpu.device,
+                &mut encoder,
+                scene_view,
+                &self.targets.depth.1,
+            );
+        }
+        if self.targets.scene.is_some() {
+            self.upscaler.encode(
+                &self.gpu.device,
+                &mut encoder,
+                scene_view,
+                view,
+                self.targets.surface_size,
+                self.targets.resolution,
+            );
+        }
+        self.fade.encode(&mut encoder, view);
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.end(&mut encoder);
+        }
+
+        // The staging buffers have to be unmapped before the copies run on the GPU
+        if let Some(staging_belt) = &mut self.staging_belt {
+            staging_belt.finish();
+        }
+
+        // This tells wgpu to 'finish' the command buffer
+        // and submit it to the GPU queue, after the compute work
+        frame.add_encoder(encoder.finish());
+        frame.submit(&self.gpu.queue);
+        if let Some(gpu_timer) = &mut self.gpu_timer {
+            gpu_timer.after_submit();
+        }
+        // Map the staging buffers again once the GPU is done with them,
+        // so that the next frames can reuse them
+        if let Some(staging_belt) = &mut self.staging_belt {
+            staging_belt.recall();
+        }
+    }
+}
+
+/// Where 'position' is in a window of 'size', from (0, 0) at the top left to
+/// (1, 1) at the bottom right. While dragging, the cursor can be reported
+/// outside of the window: that's clamped to the edges.
+fn normalized_position(
+    position: winit::dpi::PhysicalPosition<f64>,
+    size: winit::dpi::PhysicalSize<u32>,
+) -> (f64, f64) {
+    let normalize = |value: f64, extent: u32| {
+        if extent == 0 {
+            0.0
+        } else {
+            (value / extent as f64).clamp(0.0, 1.0)
+        }
+    };
+
+    (
+        normalize(position.x, size.width),
+        normalize(position.y, size.height),
+    )
+}
+
+/// Build the module of the mesh pipeline from 'source', the baked-in contents
+/// of shader.wgsl. It has to be valid, so a mismatch panics.
+fn create_mesh_shader(device: &wgpu::Device, source: &str) -> wgpu::ShaderModule {
+    shader::create_shader_module(device, MESH_SHADER_LABEL, source, MESH_ENTRY_POINTS)
+}
+
+/// How the triangles of the mesh are rasterized by default: filled, with
+/// their counter-clockwise side facing the camera and their back culled
+fn default_mesh_primitive() -> wgpu::PrimitiveState {
+    wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        front_face: wgpu::FrontFace::Ccw,
+        cull_mode: Some(wgpu::Face::Back),
+        polygon_mode: wgpu::PolygonMode::Fill,
+        ..Default::default()
+    }
+}
+
+/// The pipeline of the geometry drawn by 'shader' (built from shader.wgsl),
+/// into a 'format' target with 'sample_count' samples.
+/// Any polygon mode but Fill needs its feature to be enabled on 'device'.
+fn create_mesh_pipeline(
+    device: &wgpu::Device,
+    shader: &wgpu::ShaderModule,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    primitive: wgpu::PrimitiveState,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Mesh Pipeline Layout"),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Mesh Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[Vertex::desc(), Instance::desc()],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            // Must match the format of the surface we render into
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                // Transparent texels let the clear color show through
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive,
+        depth_stencil: depth::depth_tested(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
+
+/// Upload the quad to a vertex buffer and an index buffer, for the mesh pipeline.
+/// A 'textured' quad has white corners, so the texture is shown as it is.
+fn create_quad_buffers(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    textured: bool,
+) -> (Tracked<wgpu::Buffer>, Tracked<wgpu::Buffer>) {
+    let vertices: Vec<Vertex> = vertex::QUAD_VERTICES
+        .iter()
+        .map(|&vertex| match textured {
+            true => Vertex {
+                color: [1.0; 3],
+                ..vertex
+            },
+            false => vertex,
+        })
+        .collect();
+
+    let vertex_buffer = memory.create_buffer_init(
+        device,
+        &wgpu::util::BufferInitDescriptor {
+            label: Some("Vertex Buffer"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        },
+    );
+    let index_buffer = memory.create_buffer_init(
+        device,
+        &wgpu::util::BufferInitDescriptor {
+            label: Some("Index Buffer"),
+            contents: bytemuck::cast_slice(vertex::QUAD_INDICES),
+            usage: wgpu::BufferUsages::INDEX,
+        },
+    );
+
+    (vertex_buffer, index_buffer)
+}
+
+/// Upload a 'size' x 'size' grid of instances, returning the buffer and the
+/// number of instances in it
+fn create_instance_buffer(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    size: u32,
+) -> (Tracked<wgpu::Buffer>, u32) {
+    let instances = vertex::grid_instances(size);
+    let buffer = memory.create_buffer_init(
+        device,
+        &wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&instances),
+            usage: wgpu::BufferUsages::VERTEX,
+        },
+    );
+
+    (buffer, instances.len() as u32)
+}
+
+/// The render targets that must always match the size of what's rendered
+/// into, which have to be recreated whenever it changes size, the sample
+/// count changes or the render scale changes
+struct RenderTargets {
+    // What's rendered into, and what decides the size of the scene in it
+    surface_size: winit::dpi::PhysicalSize<u32>,
+    resolution: RenderResolution,
+    // The size of the scene, e.g. the surface size times the render scale
+    size: winit::dpi::PhysicalSize<u32>,
+    // Color target the scene is rendered into before it's scaled to the
+    // surface, None when it already has the size of the surface
+    scene: Option<(Tracked<wgpu::Texture>, wgpu::TextureView)>,
+    // Multisampled color target, None when MSAA is off
+    msaa: Option<(Tracked<wgpu::Texture>, wgpu::TextureView)>,
+    // Depth buffer of the main pass, with the same size and sample count
+    depth: (Tracked<wgpu::Texture>, wgpu::TextureView),
+    // Whether the depth view can show the depth buffer
+    depth_sampled: bool,
+}
+
+impl RenderTargets {
+    fn new(
+        gpu: &GpuContext,
+        memory: &MemoryRegistry,
+        surface_config: &wgpu::SurfaceConfiguration,
+        sample_count: u32,
+        resolution: RenderResolution,
+    ) -> Self {
+        let depth_sampled = depth::is_sampleable(&gpu.adapter, sample_count);
+        let surface_size =
+            winit::dpi::PhysicalSize::new(surface_config.width, surface_config.height);
+        let size = resolution.size(surface_size, gpu.device.limits().max_texture_dimension_2d);
+        // The targets are configured like the surface, at their own size
+        let config = wgpu::SurfaceConfiguration {
+            width: size.width,
+            height: size.height,
+            ..surface_config.clone()
+        };
+
+        Self {
+            surface_size,
+            resolution,
+            size,
+            scene: (size != surface_size)
+                .then(|| upscale::create_scene_texture(&gpu.device, memory, &config)),
+            msaa: msaa::create_msaa_texture(&gpu.device, memory, &config, sample_count),
+            depth: depth::create_depth_texture(
+                &gpu.device,
+                memory,
+                size.width,
+                size.height,
+                sample_count,
+                depth_sampled,
+            ),
+            depth_sampled,
+        }
+    }
+
+    /// Width over height of the scene
+    fn aspect_ratio(&self) -> f32 {
+        self.size.width as f32 / self.size.height as f32
+    }
+}
+
+/// The instance is a handle to the actual GPU.
+/// By default we allow all backends: Vulkan | Metal | DX12 | Browser WebGPU
+fn create_instance(config: &AppConfig) -> wgpu::Instance {
+    wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: config.backends,
+        dx12_shader_compiler: Default::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An AppState rendering into an offscreen surface of 'width' x 'height'.
+    /// Returns None on machines without a usable adapter (e.g. CI without a GPU).
+    fn headless_app_state(width: u32, height: u32, config: &AppConfig) -> Option<AppState> {
+        let size = winit::dpi::PhysicalSize::new(width, height);
+        match pollster::block_on(AppState::headless(size, config)) {
+            Ok(app_state) => Some(app_state),
+            Err(CoreError::NoAdapter) => None,
+            Err(e) => panic!("{e}"),
+        }
+    }
+
+    #[test]
+    fn resize_does_not_leak_tracked_memory() {
+        // 4x is always supported, so there's an MSAA texture to recreate too
+        let config = AppConfig {
+            sample_count: 4,
+            ..Default::default()
+        };
+        let Some(mut app_state) = headless_app_state(800, 600, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        assert!(app_state.targets.msaa.is_some());
+        let baseline_count = app_state.memory.live_count();
+        let baseline = app_state.memory.report();
+
+        for i in 1..=50 {
+            // Grow, like when dragging the window border...
+            app_state.resize(winit::dpi::PhysicalSize::new(800 + i * 7, 600 + i * 3));
+            assert_eq!(
+                app_state.memory.live_count(),
+                baseline_count,
+                "resize cycle {i}"
+            );
+            assert!(app_state.memory.report().render_targets > baseline.render_targets);
+
+            // ...and back to the original size
+            app_state.resize(winit::dpi::PhysicalSize::new(800, 600));
+            assert_eq!(
+                app_state.memory.live_count(),
+                baseline_count,
+                "resize cycle {i}"
+            );
+            assert_eq!(app_state.memory.report(), baseline, "resize cycle {i}");
+        }
+    }
+
+    #[test]
+    fn minimized_window_is_not_rendered() {
+        assert!(!is_renderable(winit::dpi::PhysicalSize::new(0, 0)));
+        assert!(!is_renderable(winit::dpi::PhysicalSize::new(800, 0)));
+        assert!(!is_renderable(winit::dpi::PhysicalSize::new(0, 600)));
+        assert!(is_renderable(winit::dpi::PhysicalSize::new(1, 1)));
+
+        // MSAA, so that there's a multisampled target sized after the surface too
+        let config = AppConfig {
+            sample_count: 4,
+            ..Default::default()
+        };
+        let surface_size = |app_state: &AppState| {
+            let config = &app_state.surface.config;
+            (config.width, config.height)
+        };
+
+        // Like a window created minimized: creating 0x0 targets would be a
+        // validation error, and panic
+        let Some(mut app_state) = headless_app_state(0, 0, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        assert_eq!(surface_size(&app_state), (1, 1));
+        assert_eq!(app_state.targets.depth.0.width(), 1);
+        assert!(!app_state.render().unwrap());
+        assert!(app_state.dirty);
+
+        // Restoring it renders again
+        app_state.resize(winit::dpi::PhysicalSize::new(800, 600));
+        assert!(app_state.render().unwrap());
+        assert!(!app_state.dirty);
+
+        // What a window gets when it's minimized: the surface keeps its size
+        app_state.resize(winit::dpi::PhysicalSize::new(0, 0));
+        assert_eq!(surface_size(&app_state), (800, 600));
+        assert!(!app_state.render().unwrap());
+    }
+
+    #[test]
+    fn render_to_image_keeps_the_clear_color_around_the_quad() {
+        let config = AppConfig {
+            clear_color: wgpu::Color::RED,
+            ..Default::default()
+        };
+        // Not a multiple of 64 pixels, so the readback rows need padding
+        let (width, height) = (100, 75);
+        let Some(mut app_state) = headless_app_state(width, height, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+
+        // With and without MSAA, which the frame is resolved from
+        for sample_count in [1, 4] {
+            app_state.set_sample_count(sample_count);
+            let image = app_state.render_to_image(width, height).unwrap();
+            assert_eq!(image.dimensions(), (width, height));
+            assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+            assert_ne!(image.get_pixel(width / 2, height / 2).0, [255, 0, 0, 255]);
+        }
+
+        // Another size than the surface's, with a camera of its aspect ratio
+        let image = app_state.render_to_image(40, 120).unwrap();
+        assert_eq!(image.dimensions(), (40, 120));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+        assert_ne!(image.get_pixel(20, 60).0, [255, 0, 0, 255]);
+        // The window keeps its own targets and camera
+        assert_eq!(app_state.targets.depth.0.width(), width);
+        assert_eq!(app_state.camera.aspect, width as f32 / height as f32);
+    }
+
+    #[test]
+    fn render_scale_sizes_the_scene_but_not_the_frame() {
+        let config = AppConfig {
+            clear_color: wgpu::Color::RED,
+            ..Default::default()
+        };
+        let Some(mut app_state) = headless_app_state(64, 64, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        assert!(app_state.targets.scene.is_none());
+
+        for (scale, side) in [(0.25, 16), (2.0, 128)] {
+            app_state.set_render_scale(scale);
+            assert_eq!(
+                app_state.render_size(),
+                winit::dpi::PhysicalSize::new(side, side)
+            );
+            assert_eq!(app_state.targets.depth.0.width(), side);
+            assert!(app_state.targets.scene.is_some());
+
+            // Scaled back to the size of the frame
+            let image = app_state.render_to_image(64, 64).unwrap();
+            assert_eq!(image.dimensions(), (64, 64));
+            assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+            assert_ne!(image.get_pixel(32, 32).0, [255, 0, 0, 255]);
+        }
+
+        app_state.set_render_scale(1.0);
+        assert!(app_state.targets.scene.is_none());
+    }
+
+    #[test]
+    fn pixel_perfect_scenes_are_letterboxed_at_an_integer_scale() {
+        let config = AppConfig {
+            clear_color: wgpu::Color::RED,
+            pixel_perfect: Some(winit::dpi::PhysicalSize::new(16, 16)),
+            ..Default::default()
+        };
+        let Some(mut app_state) = headless_app_state(100, 75, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        assert_eq!(
+            app_state.render_size(),
+            winit::dpi::PhysicalSize::new(16, 16)
+        );
+        assert_eq!(app_state.camera.aspect, 1.0);
+
+        // 4x, a 64x64 square from (18, 5)
+        let image = app_state.render_to_image(100, 75).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(17, 37).0, [0, 0, 0, 255]);
+        assert_eq!(image.get_pixel(18, 5).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(81, 68).0, [255, 0, 0, 255]);
+        assert_eq!(image.get_pixel(82, 68).0, [0, 0, 0, 255]);
+        assert_ne!(image.get_pixel(50, 37).0, [255, 0, 0, 255]);
+        // Every pixel of the scene is a sharp 4x4 block
+        for y in 0..64 {
+            for x in 0..64 {
+                let block = image.get_pixel(18 + x / 4 * 4, 5 + y / 4 * 4);
+                assert_eq!(image.get_pixel(18 + x, 5 + y), block, "at ({x}, {y})");
+            }
+        }
+
+        // Resizing keeps the resolution
+        app_state.resize(winit::dpi::PhysicalSize::new(300, 200));
+        assert_eq!(
+            app_state.render_size(),
+            winit::dpi::PhysicalSize::new(16, 16)
+        );
+        app_state.set_pixel_perfect(None);
+        assert_eq!(
+            app_state.render_size(),
+            winit::dpi::PhysicalSize::new(300, 200)
+        );
+        assert_eq!(app_state.camera.aspect, 1.5);
+    }
+
+    #[test]
+    fn loaded_views_are_flown_to() {
+        let config = AppConfig {
+            views_dir: std::env::temp_dir()
+                .join(format!("webgpu-101-app-views-{}", std::process::id())),
+            ..Default::default()
+        };
+        let Some(mut app_state) = headless_app_state(64, 32, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let saved_eye = [1.0, 2.0, 3.0];
+        app_state.camera.eye = saved_eye;
+        app_state.save_view(1).unwrap();
+        app_state.camera.eye = [0.0, 0.0, 5.0];
+
+        // An empty slot leaves the camera alone
+        assert!(matches!(
+            app_state.load_view(2),
+            Err(ViewError::EmptySlot(2))
+        ));
+        assert!(app_state.camera_animation.is_none());
+
+        app_state.load_view(1).unwrap();
+        app_state.update(VIEW_TRANSITION / 2);
+        assert_ne!(app_state.camera.eye, saved_eye);
+        assert!(app_state.is_animating());
+        app_state.update(VIEW_TRANSITION / 2);
+        assert_eq!(app_state.camera.eye, saved_eye);
+        assert!(app_state.camera_animation.is_none());
+        // The window keeps its aspect ratio
+        assert_eq!(app_state.camera.aspect, 2.0);
+
+        std::fs::remove_dir_all(&config.views_dir).unwrap();
+    }
+
+    #[test]
+    fn cameras_cycle_through_the_viewpoints() {
+        let Some(mut app_state) = headless_app_state(64, 64, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let viewpoints = views::default_viewpoints();
+
+        app_state.prev_camera();
+        app_state.update(VIEW_TRANSITION);
+        assert_eq!(app_state.camera.eye, viewpoints[2].camera.eye);
+        app_state.next_camera();
+        app_state.update(VIEW_TRANSITION);
+        assert_eq!(app_state.camera.eye, viewpoints[0].camera.eye);
+        assert_eq!(app_state.camera.target, viewpoints[0].camera.target);
+
+        // Without viewpoints, the interactive camera stays put
+        app_state.viewpoints = Viewpoints::new(Vec::new());
+        app_state.camera.eye = [5.0, 5.0, 5.0];
+        app_state.next_camera();
+        assert!(app_state.camera_animation.is_none());
+        app_state.update(VIEW_TRANSITION);
+        assert_eq!(app_state.camera.eye, [5.0, 5.0, 5.0]);
+    }
+
+    #[test]
+    fn field_of_view_changes_stay_within_the_limits() {
+        let config = AppConfig {
+            fov_limits: (30.0, 60.0),
+            instance_grid_size: 3,
+            ..Default::default()
+        };
+        let Some(mut app_state) = headless_app_state(64, 64, &config) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+
+        // The steps add up, up to the limit
+        for _ in 0..10 {
+            app_state.change_fov(FOV_STEP);
+        }
+        app_state.update(FOV_TRANSITION / 2);
+        assert!(app_state.camera.fovy > 45.0 && app_state.camera.fovy < 60.0);
+        app_state.update(FOV_TRANSITION);
+        assert_eq!(app_state.camera.fovy, 60.0);
+        assert!(app_state.fov_animation.is_none());
+
+        app_state.change_fov(-100.0);
+        app_state.update(FOV_TRANSITION);
+        assert_eq!(app_state.camera.fovy, 30.0);
+
+        // The grid is centered on the origin
+        app_state.zoom_to_fit();
+        app_state.update(VIEW_TRANSITION);
+        let (_, radius) =
+            vertex::bounding_sphere(vertex::QUAD_VERTICES, &vertex::grid_instances(3));
+        let expected = radius / 15.0_f32.to_radians().sin();
+        assert_eq!(app_state.camera.target, [0.0; 3]);
+        assert!((app_state.camera.distance() - expected).abs() < 1e-3);
+    }
+
+    #[test]
+    fn depth_view_shades_by_distance() {
+        let Some(mut app_state) = headless_app_state(64, 64, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        app_state.show_depth_view = true;
+
+        // With and without MSAA, which makes the depth buffer multisampled
+        for sample_count in [1, 4] {
+            app_state.set_sample_count(sample_count);
+            if !app_state.targets.depth_sampled {
+                eprintln!("{sample_count}x depth buffers can't be sampled, skipping");
+                continue;
+            }
+            let image = app_state.render_to_image(64, 64).unwrap();
+            // Nothing is drawn in the corners, which are as far as can be
+            assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+            // The quad is halfway to white, at the distance of the target
+            let [red, green, blue, _] = image.get_pixel(32, 32).0;
+            assert!(red == green && green == blue, "{:?}", [red, green, blue]);
+            assert!((50..230).contains(&red), "{red}");
+        }
+    }
+
+    #[test]
+    fn biased_decal_is_drawn_over_the_floor() {
+        let Some(mut app_state) = headless_app_state(64, 64, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        app_state.show_decal_demo = true;
+        // Looking down at the dot in the middle of the decal, under the mesh
+        app_state.camera.eye = [0.0, 3.0, 1.5];
+        app_state.camera.target = [0.0, -0.75, 0.0];
+
+        let image = app_state.render_to_image(64, 64).unwrap();
+        let [red, green, blue, _] = image.get_pixel(32, 32).0;
+        assert!(
+            red > 200 && green < 150 && blue < 150,
+            "{:?}",
+            [red, green, blue]
+        );
+    }
+
+    #[test]
+    fn depth_tested_debug_lines_are_hidden_by_the_quad() {
+        let Some(mut app_state) = headless_app_state(64, 64, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        // A band of red lines behind the middle of the quad
+        let band = |lines: &mut debug_draw::DebugLines| {
+            for i in 0..=40 {
+                let y = -0.1 + i as f32 * 0.005;
+                lines.line([-0.1, y, -0.5], [0.1, y, -0.5], [1.0, 0.0, 0.0, 1.0]);
+            }
+        };
+        let is_red = |image: &image::RgbaImage| {
+            let [red, green, blue, _] = image.get_pixel(32, 32).0;
+            red > 200 && green < 50 && blue < 50
+        };
+
+        band(app_state.debug_draw.depth_tested());
+        let image = app_state.render_to_image(64, 64).unwrap();
+        assert!(!is_red(&image), "{:?}", image.get_pixel(32, 32));
+
+        band(app_state.debug_draw.on_top());
+        let image = app_state.render_to_image(64, 64).unwrap();
+        assert!(is_red(&image), "{:?}", image.get_pixel(32, 32));
+
+        // Forgotten once drawn
+        let image = app_state.render_to_image(64, 64).unwrap();
+        assert!(!is_red(&image), "{:?}", image.get_pixel(32, 32));
+    }
+
+    #[test]
+    fn points_are_round_in_world_and_screen_sizes() {
+        let Some(mut app_state) = headless_app_state(64, 64, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        // A single point in front of the quad, 20 pixels wide either way
+        app_state.show_point_cloud = true;
+        app_state.point_cloud.set_points(&[point_cloud::Point {
+            color: [1.0, 0.0, 0.0, 1.0],
+            ..point_cloud::Point::new([0.0, 0.0, 0.5])
+        }]);
+        let is_red = |image: &image::RgbaImage, x, y| {
+            let [red, green, blue, _] = image.get_pixel(x, y).0;
+            red > 200 && green < 50 && blue < 50
+        };
+
+        for size in [PointSize::Screen(20.0), PointSize::World(0.4)] {
+            app_state.point_cloud.set_size(size);
+            let image = app_state.render_to_image(64, 64).unwrap();
+            assert!(is_red(&image, 32, 32), "{size:?}");
+            assert!(is_red(&image, 32, 40), "{size:?}");
+            // In the corner of the quad, but outside of the circle
+            assert!(!is_red(&image, 40, 40), "{size:?}");
+            assert!(!is_red(&image, 32, 46), "{size:?}");
+        }
+    }
+
+    #[test]
+    fn textures_repeat_or_clamp_past_their_edges() {
+        let Some(mut app_state) = headless_app_state(64, 64, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        app_state.show_wrapping_demo = true;
+
+        // Both at U = -0.25, halfway up the quads
+        let image = app_state.render_to_image(64, 64).unwrap();
+        let near = |[red, green, blue, _]: [u8; 4], expected: [u8; 3]| {
+            [red, green, blue]
+                .iter()
+                .zip(expected)
+                .all(|(&value, expected)| value.abs_diff(expected) <= 10)
+        };
+        // The last column of the checkerboard, wrapped around
+        let tiling = image.get_pixel(8, 32).0;
+        assert!(near(tiling, [70, 40, 10]), "{tiling:?}");
+        // The black line at the left edge of the UV grid, stretched out
+        let clamped = image.get_pixel(37, 32).0;
+        assert!(near(clamped, [0, 0, 0]), "{clamped:?}");
+    }
+
+    #[test]
+    fn compressed_textures_load_or_fall_back_to_png() {
+        let Some(mut app_state) = headless_app_state(16, 16, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let dir = std::env::temp_dir().join(format!("webgpu-101-dds-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Zeroed BC1 blocks are opaque black, the PNG is white
+        let dds = ddsfile::Dds::new_d3d(ddsfile::NewD3dParams {
+            height: 8,
+            width: 8,
+            depth: None,
+            format: ddsfile::D3DFormat::DXT1,
+            mipmap_levels: Some(4),
+            caps2: None,
+        })
+        .unwrap();
+        let mut file = std::fs::File::create(dir.join("tiles.dds")).unwrap();
+        dds.write(&mut file).unwrap();
+        image::RgbaImage::from_pixel(8, 8, image::Rgba([255; 4]))
+            .save(dir.join("tiles.png"))
+            .unwrap();
+
+        app_state.load_mesh_texture(&dir.join("tiles.dds"));
+        let bc = app_state
+            .gpu
+            .device
+            .features()
+            .contains(wgpu::Features::TEXTURE_COMPRESSION_BC);
+        let (format, mip_levels) = if bc {
+            (wgpu::TextureFormat::Bc1RgbaUnormSrgb, 4)
+        } else {
+            (wgpu::TextureFormat::Rgba8UnormSrgb, 1)
+        };
+        assert_eq!(app_state.mesh_texture.texture.format(), format);
+        assert_eq!(app_state.mesh_texture.texture.mip_level_count(), mip_levels);
+        app_state.render_to_image(16, 16).unwrap();
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn replaying_the_input_ends_with_the_same_camera() {
+        use input_log::InputEvent;
+
+        let path =
+            std::env::temp_dir().join(format!("webgpu-101-input-{}.log", std::process::id()));
+        let mut config = AppConfig {
+            record_input: Some(path.clone()),
+            ..Default::default()
+        };
+        let mut log = InputLog::from_config(&mut config);
+        let seed = config.random_seed.expect("recording picks a seed");
+        let Some(mut app_state) = headless_app_state(64, 64, &config) else {
+            eprintln!("No adapter available, skipping");
+            let _ = std::fs::remove_file(&path);
+            return;
+        };
+        let start = app_state.camera;
+
+        // Fly forward, orbit with a drag, then zoom and widen the field of view
+        let key = |key, state| InputEvent::Key { key, state };
+        let cursor = |x, y| InputEvent::CursorMoved { x, y };
+        let left = |state| InputEvent::MouseInput {
+            button: MouseButton::Left,
+            state,
+        };
+        let wheel = || InputEvent::MouseWheel {
+            delta: MouseScrollDelta::LineDelta(0.0, 1.0),
+        };
+        let modifiers = |state| InputEvent::Modifiers { state };
+        let session = [
+            vec![key(VirtualKeyCode::Up, ElementState::Pressed)],
+            vec![],
+            vec![key(VirtualKeyCode::Up, ElementState::Released)],
+            vec![cursor(10.0, 10.0), left(ElementState::Pressed)],
+            vec![cursor(30.0, 20.0)],
+            vec![left(ElementState::Released), wheel()],
+            vec![modifiers(ModifiersState::CTRL), wheel()],
+            vec![modifiers(ModifiersState::empty())],
+        ];
+        let dt = Duration::from_millis(100);
+        for events in &session {
+            for event in events {
+                let event = event.to_window_event().unwrap();
+                assert!(!log.filter_live_event(&event));
+                assert!(!handle_window_event(&mut app_state, &event));
+            }
+            let frame_time = log.frame(&mut app_state, dt).unwrap();
+            app_state.update(frame_time);
+        }
+        // Let the field of view settle
+        for _ in 0..10 {
+            let frame_time = log.frame(&mut app_state, dt).unwrap();
+            app_state.update(frame_time);
+        }
+        log.finish();
+        let recorded = app_state.camera;
+        assert_ne!(recorded.eye, start.eye);
+        assert_ne!(recorded.fovy, start.fovy);
+
+        app_state.camera = start;
+        let mut config = AppConfig {
+            replay_input: Some(path.clone()),
+            ..Default::default()
+        };
+        let mut log = InputLog::from_config(&mut config);
+        assert!(log.is_replaying());
+        assert_eq!(config.random_seed, Some(seed));
+        while let Some(frame_time) = log.frame(&mut app_state, Duration::ZERO) {
+            app_state.update(frame_time);
+        }
+
+        assert_eq!(app_state.camera.eye, recorded.eye);
+        assert_eq!(app_state.camera.target, recorded.target);
+        assert_eq!(app_state.camera.fovy, recorded.fovy);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn frames_fade_in_after_switching_to_fullscreen() {
+        let Some(mut app_state) = headless_app_state(16, 16, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        app_state.set_clear_color(wgpu::Color::WHITE);
+
+        // Headless there's no window to switch, only the fade to start
+        app_state.fade.fade_in(FULLSCREEN_TRANSITION);
+        let image = app_state.render_to_image(16, 16).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert!(app_state.is_animating());
+
+        app_state.update(FULLSCREEN_TRANSITION / 2);
+        let image = app_state.render_to_image(16, 16).unwrap();
+        let [r, ..] = image.get_pixel(0, 0).0;
+        assert!(r > 0 && r < 255, "half way through the fade: {r}");
+
+        app_state.update(FULLSCREEN_TRANSITION);
+        assert!(!app_state.fade.is_fading());
+        let image = app_state.render_to_image(16, 16).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn render_state_report_lists_the_shaders_in_use() {
+        let Some(mut app_state) = headless_app_state(16, 16, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let files = |app_state: &AppState| {
+            app_state
+                .render_state_report()
+                .shaders
+                .iter()
+                .map(|shader| shader.file)
+                .collect::<Vec<_>>()
+        };
+        assert!(!files(&app_state).contains(&"mandelbrot.wgsl"));
+
+        if app_state.mandelbrot.is_some() {
+            app_state.show_mandelbrot = true;
+            let files = files(&app_state);
+            assert!(files.contains(&"mandelbrot.wgsl"));
+            assert!(files.contains(&"mandelbrot_display.wgsl"));
+            assert!(files.contains(&"fullscreen.wgsl"));
+        }
+
+        // The mesh shader is the one the pipeline was last built from
+        app_state.mesh_shader_source = format!("// Reloaded\n{}", include_str!("shader.wgsl"));
+        let report = app_state.render_state_report();
+        let mesh_shader = report
+            .shaders
+            .iter()
+            .find(|shader| shader.file == "shader.wgsl")
+            .unwrap();
+        assert!(mesh_shader.source.starts_with("// Reloaded"));
+    }
+
+    #[test]
+    fn offscreen_frames_and_swapchain_images_are_counted() {
+        let Some(app_state) = headless_app_state(64, 32, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let live_count = app_state.memory.live_count();
+        let render_targets = app_state.memory.report().render_targets;
+
+        // Offscreen, every frame is a texture of its own while it's alive
+        let frame = app_state
+            .surface
+            .acquire(&app_state.gpu.device, &app_state.memory)
+            .unwrap();
+        assert_eq!(app_state.memory.live_count(), live_count + 1);
+        drop(frame);
+        assert_eq!(app_state.memory.live_count(), live_count);
+        assert_eq!(app_state.surface.image_count(), 0);
+        assert_eq!(app_state.memory.report().render_targets, render_targets);
+
+        // A window's surface is made of several images
+        let image_bytes = memory::texture_bytes(
+            app_state.surface.config.format,
+            wgpu::Extent3d {
+                width: 64,
+                height: 32,
+                depth_or_array_layers: 1,
+            },
+            1,
+            1,
+        );
+        app_state.memory.set_surface(&app_state.surface.config, 3);
+        assert_eq!(
+            app_state.memory.report().render_targets,
+            render_targets + 3 * image_bytes
+        );
+    }
+
+    #[test]
+    fn empty_and_multisampled_readbacks_fail_without_allocating() {
+        let Some(mut app_state) = headless_app_state(16, 16, &AppConfig::default()) else {
+            eprintln!("No adapter available, skipping");
+            return;
+        };
+        let live_count = app_state.memory.live_count();
+
+        for (width, height) in [(0, 16), (16, 0), (0, 0)] {
+            assert!(matches!(
+                app_state.render_to_image(width, height),
+                Err(ReadbackError::EmptyRegion { .. })
+            ));
+        }
+        assert_eq!(app_state.memory.live_count(), live_count);
+
+        // 4x is always supported
+        let texture = app_state
+            .gpu
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Multisampled Readback Texture"),
+                size: wgpu::Extent3d {
+                    width: 16,
+                    height: 16,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 4,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+        let read = |texture, width, height| {
+            readback::read_texture_region(
+                &app_state.gpu.device,
+                &app_state.gpu.queue,
+                &app_state.memory,
+                texture,
+                (0, 0, width, height),
+            )
+        };
+        assert!(matches!(
+            read(&texture, 16, 16),
+            Err(ReadbackError::Multisampled(4))
+        ));
+
+        let texture = app_state
+            .gpu
+            .device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Empty Readback Texture"),
+                size: wgpu::Extent3d {
+                    width: 16,
+                    height: 16,
+                    depth_or_array_layers: 1,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            });
+        assert!(matches!(
+            read(&texture, 0, 16),
+            Err(ReadbackError::EmptyRegion {
+                width: 0,
+                height: 16
+            })
+        ));
     }
 }
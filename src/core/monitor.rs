@@ -0,0 +1,68 @@
+use winit::event_loop::EventLoopWindowTarget;
+use winit::monitor::MonitorHandle;
+use winit::window::Fullscreen;
+
+/// Enumerate the connected monitors and their video modes,
+/// printing their indices so they can be picked in the config
+pub fn list_monitors<T>(target: &EventLoopWindowTarget<T>) -> Vec<MonitorHandle> {
+    let monitors: Vec<MonitorHandle> = target.available_monitors().collect();
+
+    println!("Available monitors:");
+    for (index, monitor) in monitors.iter().enumerate() {
+        let size = monitor.size();
+        println!(
+            "  [{index}] {} ({}x{})",
+            monitor.name().unwrap_or_else(|| "Unknown".to_string()),
+            size.width,
+            size.height
+        );
+        for (mode_index, mode) in monitor.video_modes().enumerate() {
+            let size = mode.size();
+            println!(
+                "      mode [{mode_index}] {}x{} @ {:.2} Hz, {} bit",
+                size.width,
+                size.height,
+                mode.refresh_rate_millihertz() as f32 / 1000.0,
+                mode.bit_depth()
+            );
+        }
+    }
+
+    monitors
+}
+
+/// The monitor at 'index' in the list returned by list_monitors().
+/// Falls back to the primary monitor when there's no index or it's invalid.
+pub fn monitor_by_index<T>(
+    target: &EventLoopWindowTarget<T>,
+    index: Option<usize>,
+) -> Option<MonitorHandle> {
+    if let Some(index) = index {
+        match target.available_monitors().nth(index) {
+            Some(monitor) => return Some(monitor),
+            None => log::warn!("There is no monitor with index {index}, using the primary one"),
+        }
+    }
+
+    // Wayland has no concept of a primary monitor, so just take the first one
+    target
+        .primary_monitor()
+        .or_else(|| target.available_monitors().next())
+}
+
+/// Exclusive fullscreen with the video mode at 'mode_index' on 'monitor',
+/// or borderless fullscreen if the monitor doesn't have that mode.
+/// Exclusive fullscreen really switches the resolution and refresh rate
+/// of the monitor, which is what you want for benchmarks at a fixed rate.
+pub fn fullscreen_mode(monitor: MonitorHandle, mode_index: usize) -> Fullscreen {
+    match monitor.video_modes().nth(mode_index) {
+        Some(mode) => Fullscreen::Exclusive(mode),
+        None => {
+            log::warn!(
+                "Monitor {:?} has no video mode with index {mode_index}, using borderless fullscreen",
+                monitor.name()
+            );
+            Fullscreen::Borderless(Some(monitor))
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use super::depth::DEPTH_FORMAT;
+use super::memory::{MemoryRegistry, Tracked};
+
+/// The sample counts worth trying, from no MSAA to 8x
+const SAMPLE_COUNTS: [u32; 4] = [1, 2, 4, 8];
+
+/// The sample counts we can render to 'format' with, in increasing order.
+/// The depth buffer has the same sample count, so it must support them too.
+/// WebGPU only guarantees 1 and 4: any other count needs the adapter to
+/// support it and the TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES feature.
+pub fn supported_sample_counts(
+    adapter: &wgpu::Adapter,
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> Vec<u32> {
+    let adapter_specific = device
+        .features()
+        .contains(wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES);
+    let flags = adapter.get_texture_format_features(format).flags;
+    let depth_flags = adapter.get_texture_format_features(DEPTH_FORMAT).flags;
+
+    SAMPLE_COUNTS
+        .into_iter()
+        .filter(|&count| {
+            if adapter_specific {
+                count == 1
+                    || (flags.sample_count_supported(count)
+                        && depth_flags.sample_count_supported(count))
+            } else {
+                count == 1 || count == 4
+            }
+        })
+        .collect()
+}
+
+/// Return 'requested' if it's supported, otherwise the highest supported
+/// sample count below it
+pub fn validate_sample_count(requested: u32, supported: &[u32]) -> u32 {
+    if supported.contains(&requested) {
+        return requested;
+    }
+
+    let fallback = supported
+        .iter()
+        .copied()
+        .filter(|&count| count < requested)
+        .max()
+        .unwrap_or(1);
+    log::warn!(
+        "{requested}x MSAA is not supported (supported: {supported:?}), using {fallback}x instead"
+    );
+
+    fallback
+}
+
+/// Create the multisampled color target the scene is rendered into before
+/// being resolved into the surface texture. With a single sample there is
+/// nothing to resolve, so we render straight into the surface instead.
+pub fn create_msaa_texture(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    surface_config: &wgpu::SurfaceConfiguration,
+    sample_count: u32,
+) -> Option<(Tracked<wgpu::Texture>, wgpu::TextureView)> {
+    if sample_count <= 1 {
+        return None;
+    }
+
+    let texture = memory.create_texture(
+        device,
+        &wgpu::TextureDescriptor {
+            label: Some("MSAA Texture"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    Some((texture, view))
+}
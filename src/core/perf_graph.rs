@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::depth;
+use super::memory::{MemoryRegistry, Tracked};
+use super::shader::{self, ShaderStage};
+use super::upload::BufferWriter;
+
+/// How many frames are shown in the graph
+const HISTORY_LENGTH: usize = 120;
+
+// Where the graph is drawn, in clip space: the top right corner of the window
+const GRAPH_LEFT: f32 = 0.5;
+const GRAPH_RIGHT: f32 = 0.98;
+const GRAPH_BOTTOM: f32 = 0.7;
+const GRAPH_TOP: f32 = 0.98;
+
+const NORMAL_COLOR: [f32; 3] = [0.0, 0.6, 0.0];
+const SPIKE_COLOR: [f32; 3] = [1.0, 0.0, 0.0];
+const TARGET_COLOR: [f32; 3] = [0.5, 0.5, 0.5];
+
+// One line segment per pair of consecutive frames, plus the target line
+const MAX_VERTICES: usize = (HISTORY_LENGTH - 1) * 2 + 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct GraphVertex {
+    position: [f32; 2],
+    color: [f32; 3],
+}
+
+/// Rolling graph of the last frame times, drawn as a line overlay.
+/// Frames slower than the target frame time are drawn in red.
+pub struct PerfGraph {
+    frame_times: VecDeque<f32>,
+    target_frame_ms: f32,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Tracked<wgpu::Buffer>,
+    vertex_count: u32,
+}
+
+impl PerfGraph {
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        target_frame_time: Duration,
+    ) -> Self {
+        // The vertices are rewritten every frame, so we only allocate the buffer once
+        let vertex_buffer = memory.create_buffer(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("Perf Graph Vertex Buffer"),
+                size: (MAX_VERTICES * std::mem::size_of::<GraphVertex>()) as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        );
+
+        Self {
+            frame_times: VecDeque::with_capacity(HISTORY_LENGTH),
+            target_frame_ms: target_frame_time.as_secs_f32() * 1000.0,
+            pipeline: create_pipeline(device, format, sample_count),
+            vertex_buffer,
+            vertex_count: 0,
+        }
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = create_pipeline(device, format, sample_count);
+    }
+
+    /// Record the duration of the last frame
+    pub fn push(&mut self, frame_time: Duration) {
+        if self.frame_times.len() == HISTORY_LENGTH {
+            self.frame_times.pop_front();
+        }
+        self.frame_times
+            .push_back(frame_time.as_secs_f32() * 1000.0);
+    }
+
+    /// Rebuild the lines from the recorded frame times and upload them
+    pub fn prepare(&mut self, writer: &mut BufferWriter) {
+        // The graph goes from 0 ms at the bottom to twice the target at the top,
+        // anything slower than that is clamped to the top
+        let max_ms = self.target_frame_ms * 2.0;
+        let to_y = |ms: f32| GRAPH_BOTTOM + (ms / max_ms).min(1.0) * (GRAPH_TOP - GRAPH_BOTTOM);
+        let step = (GRAPH_RIGHT - GRAPH_LEFT) / (HISTORY_LENGTH - 1) as f32;
+        let color = |ms: f32| {
+            if ms > self.target_frame_ms {
+                SPIKE_COLOR
+            } else {
+                NORMAL_COLOR
+            }
+        };
+
+        let target_y = to_y(self.target_frame_ms);
+        let mut vertices = vec![
+            GraphVertex {
+                position: [GRAPH_LEFT, target_y],
+                color: TARGET_COLOR,
+            },
+            GraphVertex {
+                position: [GRAPH_RIGHT, target_y],
+                color: TARGET_COLOR,
+            },
+        ];
+
+        // Newest frames are on the right
+        let offset = HISTORY_LENGTH - self.frame_times.len();
+        let points: Vec<(f32, f32)> = self
+            .frame_times
+            .iter()
+            .enumerate()
+            .map(|(i, &ms)| (GRAPH_LEFT + (offset + i) as f32 * step, ms))
+            .collect();
+
+        for pair in points.windows(2) {
+            let (x0, ms0) = pair[0];
+            let (x1, ms1) = pair[1];
+            // Color the whole segment leading to a spike
+            let segment_color = color(ms0.max(ms1));
+
+            vertices.push(GraphVertex {
+                position: [x0, to_y(ms0)],
+                color: segment_color,
+            });
+            vertices.push(GraphVertex {
+                position: [x1, to_y(ms1)],
+                color: segment_color,
+            });
+        }
+
+        writer.write(&self.vertex_buffer, 0, bytemuck::cast_slice(&vertices));
+        self.vertex_count = vertices.len() as u32;
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = shader::create_shader_module(
+        device,
+        "Perf Graph Shader",
+        include_str!("perf_graph.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+
+    let vertex_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<GraphVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x3],
+    };
+
+    // Everything the graph needs is in its vertices
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Perf Graph Pipeline Layout"),
+        bind_group_layouts: &[],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Perf Graph Pipeline"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::LineList,
+            ..Default::default()
+        },
+        depth_stencil: depth::depth_ignored(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,188 @@
+use super::spline::SplineRenderer;
+
+/// Roughly how many gridlines each axis gets
+const TARGET_TICKS: f32 = 5.0;
+
+const GRID_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+const AXIS_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+const SERIES_COLOR: [f32; 4] = [0.1, 0.4, 0.9, 1.0];
+
+/// How the points of the series are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+// Only the line plot is used by the demo
+#[allow(dead_code)]
+pub enum PlotStyle {
+    /// Straight lines between consecutive points
+    Line,
+    /// A small square at each point
+    Scatter,
+}
+
+/// The range of the data shown along one axis, snapped to whole ticks
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AxisRange {
+    min: f32,
+    max: f32,
+    step: f32,
+}
+
+impl AxisRange {
+    /// Fit the range around 'values', growing it to a round number of ticks.
+    /// Empty and flat data still get a range of a sensible size.
+    fn fit(values: impl Iterator<Item = f32>) -> Self {
+        let (min, max) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| {
+            (min.min(value), max.max(value))
+        });
+        let (min, max) = match (min.is_finite(), max > min) {
+            (false, _) => (0.0, 1.0),
+            // A single value, or all the same: center it
+            (true, false) => {
+                let margin = if min == 0.0 { 1.0 } else { min.abs() * 0.1 };
+                (min - margin, max + margin)
+            }
+            (true, true) => (min, max),
+        };
+
+        let step = nice_step((max - min) / TARGET_TICKS);
+        Self {
+            min: (min / step).floor() * step,
+            max: (max / step).ceil() * step,
+            step,
+        }
+    }
+
+    /// Where 'value' falls in the range, 0 at min and 1 at max
+    fn normalize(&self, value: f32) -> f32 {
+        (value - self.min) / (self.max - self.min)
+    }
+
+    fn ticks(&self) -> impl Iterator<Item = f32> + '_ {
+        let count = ((self.max - self.min) / self.step).round() as i32;
+        (0..=count).map(move |i| self.min + i as f32 * self.step)
+    }
+}
+
+/// The closest of 1, 2 or 5 times a power of ten to 'rough_step'
+fn nice_step(rough_step: f32) -> f32 {
+    let magnitude = 10f32.powf(rough_step.log10().floor());
+    let normalized = rough_step / magnitude;
+    let nice = if normalized < 1.5 {
+        1.0
+    } else if normalized < 3.5 {
+        2.0
+    } else if normalized < 7.5 {
+        5.0
+    } else {
+        10.0
+    };
+
+    nice * magnitude
+}
+
+/// A 2D plot of a series of (x, y) points, with gridlines and axes.
+/// The axes range automatically over the data, and everything is drawn as
+/// strokes through the spline renderer, mapped from data space to a
+/// rectangle of the window.
+pub struct Plot {
+    style: PlotStyle,
+    // Left, top, right and bottom edges, as fractions of the window size
+    area: [f32; 4],
+    series: Vec<(f32, f32)>,
+    x_range: AxisRange,
+    y_range: AxisRange,
+}
+
+impl Plot {
+    pub fn new(style: PlotStyle, area: [f32; 4]) -> Self {
+        Self {
+            style,
+            area,
+            series: Vec::new(),
+            x_range: AxisRange::fit(std::iter::empty()),
+            y_range: AxisRange::fit(std::iter::empty()),
+        }
+    }
+
+    /// Replace the points of the plot. Points that aren't finite are skipped.
+    pub fn set_series(&mut self, series: &[(f32, f32)]) {
+        self.series = series
+            .iter()
+            .copied()
+            .filter(|(x, y)| x.is_finite() && y.is_finite())
+            .collect();
+        self.x_range = AxisRange::fit(self.series.iter().map(|&(x, _)| x));
+        self.y_range = AxisRange::fit(self.series.iter().map(|&(_, y)| y));
+    }
+
+    /// Queue the plot into 'splines', for a window of 'size' pixels
+    pub fn draw(&self, splines: &mut SplineRenderer, size: winit::dpi::PhysicalSize<u32>) {
+        let (width, height) = (size.width as f32, size.height as f32);
+        let [left, top, right, bottom] = [
+            self.area[0] * width,
+            self.area[1] * height,
+            self.area[2] * width,
+            self.area[3] * height,
+        ];
+        // Pixels have y going down, so the y axis is flipped
+        let to_screen = |x: f32, y: f32| {
+            [
+                left + self.x_range.normalize(x) * (right - left),
+                bottom - self.y_range.normalize(y) * (bottom - top),
+            ]
+        };
+
+        for x in self.x_range.ticks() {
+            splines.draw_polyline(
+                &[
+                    to_screen(x, self.y_range.min),
+                    to_screen(x, self.y_range.max),
+                ],
+                1.0,
+                GRID_COLOR,
+            );
+        }
+        for y in self.y_range.ticks() {
+            splines.draw_polyline(
+                &[
+                    to_screen(self.x_range.min, y),
+                    to_screen(self.x_range.max, y),
+                ],
+                1.0,
+                GRID_COLOR,
+            );
+        }
+
+        // The axes go through the origin when it's in view,
+        // otherwise they stick to the bottom and left edges
+        let axis_x = 0f32.clamp(self.x_range.min, self.x_range.max);
+        let axis_y = 0f32.clamp(self.y_range.min, self.y_range.max);
+        splines.draw_polyline(
+            &[
+                to_screen(self.x_range.min, axis_y),
+                to_screen(self.x_range.max, axis_y),
+            ],
+            2.0,
+            AXIS_COLOR,
+        );
+        splines.draw_polyline(
+            &[
+                to_screen(axis_x, self.y_range.min),
+                to_screen(axis_x, self.y_range.max),
+            ],
+            2.0,
+            AXIS_COLOR,
+        );
+
+        let points: Vec<[f32; 2]> = self.series.iter().map(|&(x, y)| to_screen(x, y)).collect();
+        match self.style {
+            PlotStyle::Line => splines.draw_polyline(&points, 2.0, SERIES_COLOR),
+            PlotStyle::Scatter => {
+                for [x, y] in points {
+                    // A one pixel long stroke gets square caps of half its
+                    // width on both ends, which makes it a square
+                    splines.draw_polyline(&[[x - 0.5, y], [x + 0.5, y]], 5.0, SERIES_COLOR);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,299 @@
+use super::camera::Camera;
+use super::depth;
+use super::memory::{MemoryRegistry, Tracked};
+use super::rng::Rng;
+use super::shader::{self, ShaderStage};
+use super::upload::BufferWriter;
+
+/// Points of the demo cloud
+const DEMO_POINT_COUNT: usize = 200_000;
+const DEMO_RADIUS: f32 = 1.2;
+
+/// One point of a cloud, read by the shader as an instance
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Point {
+    pub position: [f32; 3],
+    /// Times the size of the cloud, see `PointSize`
+    pub size: f32,
+    pub color: [f32; 4],
+}
+
+impl Point {
+    /// A white point of the size of the cloud
+    pub fn new(position: [f32; 3]) -> Self {
+        Self {
+            position,
+            size: 1.0,
+            color: [1.0; 4],
+        }
+    }
+}
+
+/// The diameter of the points
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PointSize {
+    /// In world units: the points get smaller further away, like the rest of
+    /// the scene
+    World(f32),
+    /// In pixels, whatever the distance
+    Screen(f32),
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct PointUniforms {
+    // Where the sides of the sprites go in world space, facing the camera
+    right: [f32; 4],
+    up: [f32; 4],
+    viewport_size: [f32; 2],
+    size: f32,
+    // 1 for PointSize::World, 0 for PointSize::Screen
+    world_space: u32,
+}
+
+/// Draws points as round antialiased sprites facing the camera.
+/// All the points are kept in a single instance buffer, and drawn by a
+/// single draw call: each instance is a quad made of 6 vertices generated in
+/// the vertex shader, which is all the GPU needs to go through millions of
+/// them. The quads are cut round in the fragment shader.
+pub struct PointCloud {
+    bind_group: wgpu::BindGroup,
+    uniforms_buffer: Tracked<wgpu::Buffer>,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    instance_buffer: Tracked<wgpu::Buffer>,
+    // Where the instance buffer is created again when it has to grow
+    memory: MemoryRegistry,
+    // Set by set_points() until prepare() uploads them
+    pending: Option<Vec<Point>>,
+    point_count: u32,
+    size: PointSize,
+}
+
+impl PointCloud {
+    /// The points are seen through the camera bound with 'camera_bind_group_layout'
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        camera_bind_group_layout: &wgpu::BindGroupLayout,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let uniforms_buffer = memory.create_buffer(
+            device,
+            &wgpu::BufferDescriptor {
+                label: Some("Point Cloud Uniforms Buffer"),
+                size: std::mem::size_of::<PointUniforms>() as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+        );
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Point Cloud Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Point Cloud Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniforms_buffer.as_entire_binding(),
+            }],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Point Cloud Pipeline Layout"),
+            bind_group_layouts: &[camera_bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            pipeline: create_pipeline(device, &pipeline_layout, format, sample_count),
+            bind_group,
+            uniforms_buffer,
+            pipeline_layout,
+            instance_buffer: create_instance_buffer(device, memory, 1),
+            memory: memory.clone(),
+            pending: None,
+            point_count: 0,
+            size: PointSize::Screen(3.0),
+        }
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, format, sample_count);
+    }
+
+    /// Replace all the points, uploaded by the next `prepare()`.
+    /// The points are kept until they're replaced again.
+    pub fn set_points(&mut self, points: &[Point]) {
+        self.pending = Some(points.to_vec());
+    }
+
+    pub fn size(&self) -> PointSize {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: PointSize) {
+        self.size = size;
+    }
+
+    /// Upload the points set since the last frame, and orient the sprites
+    /// towards 'camera' for a target of 'size' pixels
+    pub fn prepare(
+        &mut self,
+        writer: &mut BufferWriter,
+        camera: &Camera,
+        size: winit::dpi::PhysicalSize<u32>,
+    ) {
+        if let Some(points) = self.pending.take() {
+            let bytes: &[u8] = bytemuck::cast_slice(&points);
+            // Only ever grows: the same cloud set again reuses its buffer
+            if bytes.len() as wgpu::BufferAddress > self.instance_buffer.size() {
+                self.instance_buffer =
+                    create_instance_buffer(writer.device, &self.memory, points.len());
+            }
+            writer.write(&self.instance_buffer, 0, bytes);
+            self.point_count = points.len() as u32;
+        }
+
+        let (right, up) = camera.right_and_up();
+        let (diameter, world_space) = match self.size {
+            PointSize::World(diameter) => (diameter, 1),
+            PointSize::Screen(diameter) => (diameter, 0),
+        };
+        let uniforms = PointUniforms {
+            right: [right[0], right[1], right[2], 0.0],
+            up: [up[0], up[1], up[2], 0.0],
+            viewport_size: [size.width as f32, size.height as f32],
+            size: diameter,
+            world_space,
+        };
+        writer.write(&self.uniforms_buffer, 0, bytemuck::bytes_of(&uniforms));
+    }
+
+    /// Expects the camera bind group at group 0
+    pub fn draw<'a>(
+        &'a self,
+        render_pass: &mut wgpu::RenderPass<'a>,
+        camera_bind_group: &'a wgpu::BindGroup,
+    ) {
+        if self.point_count == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.point_count);
+    }
+}
+
+/// Points spread over a sphere around the mesh, colored by their direction
+pub fn demo_points(rng: &mut Rng) -> Vec<Point> {
+    (0..DEMO_POINT_COUNT)
+        .map(|_| {
+            let direction = rng.point_on_sphere();
+            let [x, y, z] = direction.map(|value| value * 0.5 + 0.5);
+            Point {
+                color: [x, y, z, 1.0],
+                ..Point::new(direction.map(|value| value * DEMO_RADIUS))
+            }
+        })
+        .collect()
+}
+
+fn create_instance_buffer(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    point_count: usize,
+) -> Tracked<wgpu::Buffer> {
+    memory.create_buffer(
+        device,
+        &wgpu::BufferDescriptor {
+            label: Some("Point Cloud Instance Buffer"),
+            size: (point_count.max(1) * std::mem::size_of::<Point>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        },
+    )
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = shader::create_shader_module(
+        device,
+        "Point Cloud Shader",
+        include_str!("point_cloud.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+    // One point per instance, the corners come from the vertex index
+    let instance_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<Point>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Instance,
+        attributes: &wgpu::vertex_attr_array![
+            0 => Float32x3,
+            1 => Float32,
+            2 => Float32x4
+        ],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Point Cloud Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[instance_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                // The edges of the points are partially transparent
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        // The sprites always face the camera
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        // The points aren't sorted: the faded edge of a point still hides the
+        // points behind it that are drawn later, a thin fringe around it shows
+        // what was drawn before instead
+        depth_stencil: depth::depth_tested(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
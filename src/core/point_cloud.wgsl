@@ -0,0 +1,81 @@
+// Draws each point given by point_cloud.rs as a quad facing the camera, made
+// of 6 vertices generated here, and cuts the quad round. The corners go from
+// -1 to 1, which the fragment shader uses as the distance to the center.
+
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+};
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniforms;
+
+struct PointUniforms {
+    right: vec4<f32>,
+    up: vec4<f32>,
+    viewport_size: vec2<f32>,
+    // Diameter, in world units or in pixels
+    size: f32,
+    world_space: u32,
+};
+
+@group(1) @binding(0)
+var<uniform> cloud: PointUniforms;
+
+struct PointInput {
+    @location(0) position: vec3<f32>,
+    @location(1) size: f32,
+    @location(2) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, point: PointInput) -> VertexOutput {
+    // Constant arrays can only be indexed through a variable in naga
+    var corners = CORNERS;
+    let corner = corners[vertex_index];
+    let diameter = cloud.size * point.size;
+
+    var position: vec4<f32>;
+    if (cloud.world_space == 1u) {
+        // Pushed out in the plane facing the camera, before the projection
+        let offset = (cloud.right.xyz * corner.x + cloud.up.xyz * corner.y) * diameter * 0.5;
+        position = camera.view_projection * vec4<f32>(point.position + offset, 1.0);
+    } else {
+        // Pushed out after the projection, by a number of pixels: clip space
+        // gets divided by w, so the offset is multiplied by it first
+        position = camera.view_projection * vec4<f32>(point.position, 1.0);
+        let offset = corner * diameter / cloud.viewport_size;
+        position = vec4<f32>(position.xy + offset * position.w, position.zw);
+    }
+
+    var out: VertexOutput;
+    out.position = position;
+    out.corner = corner;
+    out.color = point.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.corner);
+    // Fade out over the last pixel, however large the point is on screen
+    let coverage = clamp((1.0 - distance) / fwidth(distance), 0.0, 1.0);
+    if (coverage <= 0.0) {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
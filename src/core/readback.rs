@@ -0,0 +1,166 @@
+use std::fmt;
+use std::sync::mpsc;
+
+use super::memory::MemoryRegistry;
+
+/// What can go wrong when copying a texture back to the CPU
+#[derive(Debug)]
+pub enum ReadbackError {
+    /// Compressed and combined depth/stencil formats have no simple texel layout
+    UnsupportedFormat(wgpu::TextureFormat),
+    /// The texture wasn't created with COPY_SRC
+    MissingCopySrc,
+    /// Multisampled textures can't be copied, only their resolve target
+    Multisampled(u32),
+    /// Nothing to read back, wgpu can't create anything that small either
+    EmptyRegion {
+        width: u32,
+        height: u32,
+    },
+    OutOfBounds {
+        region: (u32, u32, u32, u32),
+        size: (u32, u32),
+    },
+    Map(wgpu::BufferAsyncError),
+}
+
+impl fmt::Display for ReadbackError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnsupportedFormat(format) => {
+                write!(f, "Reading back {format:?} textures is not supported")
+            }
+            Self::MissingCopySrc => {
+                write!(f, "The texture needs the COPY_SRC usage to be read back")
+            }
+            Self::Multisampled(sample_count) => write!(
+                f,
+                "Textures with {sample_count} samples can't be read back, \
+                 only once resolved"
+            ),
+            Self::EmptyRegion { width, height } => {
+                write!(f, "Can't read back an empty {width}x{height} region")
+            }
+            Self::OutOfBounds {
+                region: (x, y, width, height),
+                size: (texture_width, texture_height),
+            } => write!(
+                f,
+                "Region {width}x{height} at ({x}, {y}) is outside of the \
+                 {texture_width}x{texture_height} texture"
+            ),
+            Self::Map(e) => write!(f, "Failed to map the readback buffer: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReadbackError {}
+
+/// Copy the (x, y, width, height) region of the first mip level of 'texture'
+/// to the CPU, blocking until the GPU is done. The texels come back tightly
+/// packed, row by row.
+/// Works with any uncompressed color format (Rgba8, Rgba16Float, R32Uint...)
+/// and with the depth only formats (Depth32Float, Depth16Unorm).
+pub fn read_texture_region(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    memory: &MemoryRegistry,
+    texture: &wgpu::Texture,
+    (x, y, width, height): (u32, u32, u32, u32),
+) -> Result<Vec<u8>, ReadbackError> {
+    let format = texture.format();
+    let aspect = if format.has_depth_aspect() {
+        wgpu::TextureAspect::DepthOnly
+    } else {
+        wgpu::TextureAspect::All
+    };
+    let bytes_per_texel = match (format.block_size(Some(aspect)), format.block_dimensions()) {
+        (Some(size), (1, 1)) if !format.has_stencil_aspect() => size,
+        _ => return Err(ReadbackError::UnsupportedFormat(format)),
+    };
+    if !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+        return Err(ReadbackError::MissingCopySrc);
+    }
+    if texture.sample_count() > 1 {
+        return Err(ReadbackError::Multisampled(texture.sample_count()));
+    }
+    if width == 0 || height == 0 {
+        return Err(ReadbackError::EmptyRegion { width, height });
+    }
+    if x.checked_add(width)
+        .is_none_or(|right| right > texture.width())
+        || y.checked_add(height)
+            .is_none_or(|bottom| bottom > texture.height())
+    {
+        return Err(ReadbackError::OutOfBounds {
+            region: (x, y, width, height),
+            size: (texture.width(), texture.height()),
+        });
+    }
+
+    // Buffer copies need every row to start at a multiple of 256 bytes,
+    // so the rows get padded here and the padding is removed once mapped
+    let row_bytes = width * bytes_per_texel;
+    let padded_row_bytes = row_bytes.next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+
+    // Dropped before this returns, but large readbacks still show up in the
+    // memory report while they're waiting for the GPU
+    let buffer = memory.create_buffer(
+        device,
+        &wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (padded_row_bytes * height) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        },
+    );
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Readback Encoder"),
+    });
+    encoder.copy_texture_to_buffer(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d { x, y, z: 0 },
+            aspect,
+        },
+        wgpu::ImageCopyBuffer {
+            buffer: &buffer,
+            layout: wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row_bytes),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+    queue.submit(Some(encoder.finish()));
+
+    // Mapping is asynchronous: the callback runs during poll(),
+    // once the copy has finished
+    let slice = buffer.slice(..);
+    let (sender, receiver) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    receiver
+        .recv()
+        .expect("map_async callback dropped without being called")
+        .map_err(ReadbackError::Map)?;
+
+    let texels = slice
+        .get_mapped_range()
+        .chunks(padded_row_bytes as usize)
+        .flat_map(|row| &row[..row_bytes as usize])
+        .copied()
+        .collect();
+    buffer.unmap();
+
+    Ok(texels)
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use instant::Instant;
+use winit::event_loop::ControlFlow;
+
+/// When the event loop asks for a new frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderPolicy {
+    /// Redraw as fast as possible (or at the display rate with VSync)
+    AlwaysRedraw,
+    /// Only redraw when something changed: input, animations, resizes...
+    /// A static scene then costs next to nothing, which saves battery.
+    OnChange,
+    /// Redraw at a fixed rate, e.g. 30 fps, regardless of the display rate
+    FixedRate(Duration),
+}
+
+/// How much of the window the user can see, whatever the policy
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Visibility {
+    /// Rendered as the policy says
+    Focused,
+    /// Behind another window or on another screen: only the changes are
+    /// rendered, at BACKGROUND_RATE at most
+    Unfocused,
+    /// Minimized or fully covered: nothing is rendered until it's visible again
+    Occluded,
+}
+
+// Frames per second while unfocused, enough to follow a resize
+const BACKGROUND_RATE: f64 = 10.0;
+
+/// Decides, once all the pending events are handled, whether to redraw
+pub struct RedrawScheduler {
+    policy: RenderPolicy,
+    next_frame: Instant,
+    // Earliest time of the next frame while unfocused
+    next_background_frame: Instant,
+}
+
+impl RedrawScheduler {
+    pub fn new(policy: RenderPolicy) -> Self {
+        Self {
+            policy,
+            next_frame: Instant::now(),
+            next_background_frame: Instant::now(),
+        }
+    }
+
+    /// Called on MainEventsCleared with whether the app has changes to show.
+    /// Returns whether to request a redraw, and sets how long the event loop
+    /// can sleep until the next one.
+    /// Changes made while the window isn't visible stay pending, so they're
+    /// rendered once it is rather than leaving a stale frame on screen.
+    pub fn should_redraw(
+        &mut self,
+        dirty: bool,
+        visibility: Visibility,
+        control_flow: &mut ControlFlow,
+    ) -> bool {
+        // Don't override an exit requested while handling the events
+        let mut set_control_flow = |flow| {
+            if *control_flow != ControlFlow::Exit {
+                *control_flow = flow;
+            }
+        };
+
+        match visibility {
+            Visibility::Focused => {}
+            Visibility::Occluded => {
+                set_control_flow(ControlFlow::Wait);
+                return false;
+            }
+            Visibility::Unfocused => {
+                if !dirty {
+                    set_control_flow(ControlFlow::Wait);
+                    return false;
+                }
+                let now = Instant::now();
+                if now < self.next_background_frame {
+                    set_control_flow(ControlFlow::WaitUntil(self.next_background_frame));
+                    return false;
+                }
+                self.next_background_frame = now + Duration::from_secs_f64(1.0 / BACKGROUND_RATE);
+                set_control_flow(ControlFlow::WaitUntil(self.next_background_frame));
+                return true;
+            }
+        }
+
+        match self.policy {
+            RenderPolicy::AlwaysRedraw => {
+                // The control flow sticks from one iteration to the next,
+                // and may still be a Wait from while the window was unfocused
+                set_control_flow(ControlFlow::Poll);
+                true
+            }
+            RenderPolicy::OnChange => {
+                // Keep polling while things change (e.g. during an animation),
+                // otherwise sleep until the next event
+                set_control_flow(if dirty {
+                    ControlFlow::Poll
+                } else {
+                    ControlFlow::Wait
+                });
+                dirty
+            }
+            RenderPolicy::FixedRate(interval) => {
+                let now = Instant::now();
+                let redraw = now >= self.next_frame;
+                if redraw {
+                    self.next_frame += interval;
+                    // Skip the missed frames instead of rendering them in a burst
+                    if self.next_frame < now {
+                        self.next_frame = now + interval;
+                    }
+                }
+                set_control_flow(ControlFlow::WaitUntil(self.next_frame));
+                redraw
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn always_redraw_polls_again_once_focused() {
+        let mut scheduler = RedrawScheduler::new(RenderPolicy::AlwaysRedraw);
+        let mut control_flow = ControlFlow::Poll;
+
+        assert!(!scheduler.should_redraw(false, Visibility::Unfocused, &mut control_flow));
+        assert_eq!(control_flow, ControlFlow::Wait);
+
+        assert!(scheduler.should_redraw(false, Visibility::Focused, &mut control_flow));
+        assert_eq!(control_flow, ControlFlow::Poll);
+    }
+
+    #[test]
+    fn exit_is_not_overridden() {
+        let mut scheduler = RedrawScheduler::new(RenderPolicy::AlwaysRedraw);
+        let mut control_flow = ControlFlow::Exit;
+
+        scheduler.should_redraw(true, Visibility::Focused, &mut control_flow);
+        assert_eq!(control_flow, ControlFlow::Exit);
+    }
+}
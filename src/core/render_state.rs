@@ -0,0 +1,69 @@
+use std::fmt;
+
+/// A shader file used to draw the current frame
+pub struct ActiveShader<'a> {
+    pub file: &'static str,
+    /// What the pipelines were built from, which differs from the file in
+    /// the binary once it's been hot-reloaded
+    pub source: &'a str,
+    pub entry_points: &'static [&'static str],
+}
+
+/// A snapshot of how frames are currently rendered, as readable text meant to
+/// be attached to bug reports: "it looks wrong on my machine" is a lot easier
+/// to act on knowing the adapter, surface and shaders that were in use.
+pub struct RenderStateReport<'a> {
+    pub adapter: wgpu::AdapterInfo,
+    pub features: wgpu::Features,
+    pub surface_config: &'a wgpu::SurfaceConfiguration,
+    pub sample_count: u32,
+    /// The size the scene is rendered at, before it's scaled to the surface
+    pub render_size: winit::dpi::PhysicalSize<u32>,
+    pub shaders: Vec<ActiveShader<'a>>,
+}
+
+impl fmt::Display for RenderStateReport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let adapter = &self.adapter;
+        let surface = self.surface_config;
+
+        writeln!(f, "Render state:")?;
+        writeln!(f, "  adapter:      {}", adapter.name)?;
+        writeln!(f, "  backend:      {:?}", adapter.backend)?;
+        writeln!(f, "  device type:  {:?}", adapter.device_type)?;
+        writeln!(
+            f,
+            "  driver:       {} {}",
+            adapter.driver, adapter.driver_info
+        )?;
+        writeln!(f, "  features:     {:?}", self.features)?;
+        writeln!(f, "  surface:      {}x{}", surface.width, surface.height)?;
+        writeln!(
+            f,
+            "  render size:  {}x{}",
+            self.render_size.width, self.render_size.height
+        )?;
+        writeln!(f, "  format:       {:?}", surface.format)?;
+        writeln!(f, "  view formats: {:?}", surface.view_formats)?;
+        writeln!(f, "  present mode: {:?}", surface.present_mode)?;
+        writeln!(f, "  alpha mode:   {:?}", surface.alpha_mode)?;
+        write!(f, "  MSAA samples: {}", self.sample_count)?;
+
+        for shader in &self.shaders {
+            write!(
+                f,
+                "\n  shader {} ({})",
+                shader.file,
+                shader.entry_points.join(", ")
+            )?;
+            // The bind group layouts are built to match these declarations
+            for line in shader.source.lines().map(str::trim) {
+                if line.starts_with("@group(") {
+                    write!(f, "\n    {line}")?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use rand::rngs::SmallRng;
+use rand::{Rng as _, SeedableRng};
+
+/// Random numbers for procedural content.
+/// Always seeded, either from the config or from the clock, and the seed is
+/// printed at startup: putting it in the config reproduces the same content,
+/// which is what you want when testing or filing a bug.
+pub struct Rng {
+    rng: SmallRng,
+}
+
+impl Rng {
+    /// Seed with 'seed', or with the current time when None
+    pub fn new(seed: Option<u64>) -> Self {
+        let seed = seed.unwrap_or_else(clock_seed);
+        println!("Random seed: {seed}");
+
+        Self {
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    /// True with the given 'probability', in the [0, 1] range
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.rng.gen_bool(probability)
+    }
+
+    /// A uniformly distributed point on the surface of the unit sphere.
+    /// Picking the angles at random would bunch the points up at the poles.
+    pub fn point_on_sphere(&mut self) -> [f32; 3] {
+        let z: f32 = self.rng.gen_range(-1.0..=1.0);
+        let angle: f32 = self.rng.gen_range(0.0..std::f32::consts::TAU);
+        let radius = (1.0 - z * z).sqrt();
+
+        [radius * angle.cos(), radius * angle.sin(), z]
+    }
+}
+
+/// A different seed every run, from the current time
+pub fn clock_seed() -> u64 {
+    // std's clock isn't available in a browser
+    instant::SystemTime::now()
+        .duration_since(instant::SystemTime::UNIX_EPOCH)
+        .map(|t| t.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_seed_gives_the_same_sequence() {
+        let mut a = Rng::new(Some(42));
+        let mut b = Rng::new(Some(42));
+
+        for _ in 0..100 {
+            assert_eq!(a.chance(0.5), b.chance(0.5));
+            assert_eq!(a.point_on_sphere(), b.point_on_sphere());
+        }
+    }
+
+    #[test]
+    fn points_on_sphere_have_unit_length() {
+        let mut rng = Rng::new(Some(7));
+
+        for _ in 0..1000 {
+            let [x, y, z] = rng.point_on_sphere();
+            let length = (x * x + y * y + z * z).sqrt();
+            assert!((length - 1.0).abs() < 1e-5, "length {length}");
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use std::time::Duration;
+
+use super::accumulation::{AccumulationSample, ACCUMULATION_FORMAT};
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+use super::upload::BufferWriter;
+
+// The camera circles the scene at this distance and height
+const ORBIT_RADIUS: f32 = 5.0;
+const ORBIT_HEIGHT: f32 = 2.0;
+// In radians per second
+const ORBIT_SPEED: f32 = 0.3;
+const CAMERA_TARGET: [f32; 3] = [0.0, 0.5, 0.0];
+
+/// Must match the `SdfUniforms` struct in sdf.wgsl
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SdfUniforms {
+    camera_position: [f32; 3],
+    time: f32,
+    camera_target: [f32; 3],
+    aspect: f32,
+    jitter: [f32; 2],
+    weight: f32,
+    _padding: f32,
+}
+
+/// The uniforms of one of the views of the scene
+struct Eye {
+    // Sideways shift from the center camera, negative for the left eye
+    offset: f32,
+    uniforms_buffer: Tracked<wgpu::Buffer>,
+    bind_group: wgpu::BindGroup,
+}
+
+/// A scene made of a sphere, a box and a plane, ray marched entirely in a
+/// fullscreen fragment shader instead of being rasterized from meshes.
+/// Each primitive is a signed distance function, which tells how far a point
+/// is from its surface: that's enough to march rays, shade with normals
+/// taken from the gradient, and cast soft shadows.
+///
+/// In stereo the scene is drawn twice, side by side, from two cameras
+/// 'ipd' apart: the left half of the window for the left eye and the right
+/// half for the right eye.
+pub struct SdfScene {
+    time: Duration,
+    // One eye in mono, two in stereo
+    eyes: Vec<Eye>,
+    // Size of the render target, set by prepare()
+    size: winit::dpi::PhysicalSize<u32>,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    // Blends samples into an AccumulationRenderer
+    accumulation_pipeline: wgpu::RenderPipeline,
+}
+
+impl SdfScene {
+    /// 'stereo_ipd' is the distance between the eyes, None to render in mono
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+        stereo_ipd: Option<f32>,
+    ) -> Self {
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("SDF Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+
+        let offsets = match stereo_ipd {
+            Some(ipd) => vec![-ipd * 0.5, ipd * 0.5],
+            None => vec![0.0],
+        };
+        let eyes = offsets
+            .into_iter()
+            .map(|offset| {
+                let uniforms_buffer = memory.create_buffer_init(
+                    device,
+                    &wgpu::util::BufferInitDescriptor {
+                        label: Some("SDF Uniforms Buffer"),
+                        contents: bytemuck::bytes_of(&uniforms(Duration::ZERO, 1.0, offset)),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    },
+                );
+                let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                    label: Some("SDF Bind Group"),
+                    layout: &bind_group_layout,
+                    entries: &[wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniforms_buffer.as_entire_binding(),
+                    }],
+                });
+
+                Eye {
+                    offset,
+                    uniforms_buffer,
+                    bind_group,
+                }
+            })
+            .collect();
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("SDF Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_pipeline(device, &pipeline_layout, format, sample_count);
+        // Each sample is written with its weight in alpha
+        let accumulation_pipeline = fullscreen::create_fullscreen_pipeline_with_target(
+            device,
+            "SDF Accumulation Pipeline",
+            Some(&pipeline_layout),
+            wgpu::ColorTargetState {
+                format: ACCUMULATION_FORMAT,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            },
+            1,
+            include_str!("sdf.wgsl"),
+            "fs_sdf",
+        );
+
+        Self {
+            time: Duration::ZERO,
+            eyes,
+            size: winit::dpi::PhysicalSize::new(1, 1),
+            pipeline_layout,
+            pipeline,
+            accumulation_pipeline,
+        }
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, format, sample_count);
+    }
+
+    /// Move the camera and the animated primitives forward by 'dt'
+    pub fn update(&mut self, dt: Duration) {
+        self.time += dt;
+    }
+
+    /// Position and target of the camera, between the eyes in stereo
+    pub fn camera(&self) -> [[f32; 3]; 2] {
+        let uniforms = uniforms(self.time, 1.0, 0.0);
+        [uniforms.camera_position, uniforms.camera_target]
+    }
+
+    /// Upload the uniforms for a target of 'size' pixels.
+    /// With a 'sample', they're for `draw_sample()` rather than `draw()`.
+    pub fn prepare(
+        &mut self,
+        writer: &mut BufferWriter,
+        size: winit::dpi::PhysicalSize<u32>,
+        sample: Option<AccumulationSample>,
+    ) {
+        self.size = size;
+        // In stereo each eye only gets its share of the width
+        let eye_width = size.width as f32 / self.eyes.len() as f32;
+        let height = size.height.max(1) as f32;
+        let aspect = eye_width / height;
+
+        let sample = sample.unwrap_or(AccumulationSample {
+            jitter: [0.0, 0.0],
+            weight: 1.0,
+        });
+        for eye in &self.eyes {
+            let uniforms = SdfUniforms {
+                // The shader works in UVs, which span the viewport of the eye
+                jitter: [sample.jitter[0] / eye_width, sample.jitter[1] / height],
+                weight: sample.weight,
+                ..uniforms(self.time, aspect, eye.offset)
+            };
+            writer.write(&eye.uniforms_buffer, 0, bytemuck::bytes_of(&uniforms));
+        }
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.draw_with(&self.pipeline, render_pass);
+    }
+
+    /// Blend the sample passed to `prepare()` into an accumulation pass
+    pub fn draw_sample<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        self.draw_with(&self.accumulation_pipeline, render_pass);
+    }
+
+    fn draw_with<'a>(
+        &'a self,
+        pipeline: &'a wgpu::RenderPipeline,
+        render_pass: &mut wgpu::RenderPass<'a>,
+    ) {
+        render_pass.set_pipeline(pipeline);
+
+        let (width, height) = (self.size.width as f32, self.size.height as f32);
+        let eye_width = width / self.eyes.len() as f32;
+        for (i, eye) in self.eyes.iter().enumerate() {
+            render_pass.set_viewport(i as f32 * eye_width, 0.0, eye_width, height, 0.0, 1.0);
+            render_pass.set_bind_group(0, &eye.bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        // The viewport sticks for the rest of the pass
+        render_pass.set_viewport(0.0, 0.0, width, height, 0.0, 1.0);
+    }
+}
+
+/// There's no camera in the app yet, so the scene has its own, orbiting
+/// around the origin and always looking at it.
+/// 'eye_offset' moves it sideways, keeping both eyes looking straight ahead
+/// in parallel rather than converging on the target.
+fn uniforms(time: Duration, aspect: f32, eye_offset: f32) -> SdfUniforms {
+    let time = time.as_secs_f32();
+    let angle = time * ORBIT_SPEED;
+    let position = [
+        angle.cos() * ORBIT_RADIUS,
+        ORBIT_HEIGHT,
+        angle.sin() * ORBIT_RADIUS,
+    ];
+
+    // Same as the camera basis in sdf.wgsl: right = forward x up, with Y up
+    let forward = [
+        CAMERA_TARGET[0] - position[0],
+        0.0,
+        CAMERA_TARGET[2] - position[2],
+    ];
+    let length = (forward[0] * forward[0] + forward[2] * forward[2]).sqrt();
+    let right = [-forward[2] / length, 0.0, forward[0] / length];
+    let shift = |point: [f32; 3]| {
+        [
+            point[0] + right[0] * eye_offset,
+            point[1],
+            point[2] + right[2] * eye_offset,
+        ]
+    };
+
+    SdfUniforms {
+        camera_position: shift(position),
+        time,
+        camera_target: shift(CAMERA_TARGET),
+        aspect,
+        jitter: [0.0, 0.0],
+        weight: 1.0,
+        _padding: 0.0,
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    fullscreen::create_fullscreen_pipeline(
+        device,
+        "SDF Pipeline",
+        Some(layout),
+        format,
+        sample_count,
+        include_str!("sdf.wgsl"),
+        "fs_sdf",
+    )
+}
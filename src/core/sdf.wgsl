@@ -0,0 +1,146 @@
+// Appended to fullscreen.wgsl: ray marches a small signed distance field scene.
+// Every pixel shoots a ray from the camera and steps along it by the distance
+// to the closest surface, which is always a safe step, until it hits something.
+
+struct SdfUniforms {
+    camera_position: vec3<f32>,
+    time: f32,
+    camera_target: vec3<f32>,
+    // Width over height of the render target
+    aspect: f32,
+    // Sub-pixel offset of the rays when accumulating samples, in UV units
+    jitter: vec2<f32>,
+    // Written to alpha, for the accumulation pipeline to blend with
+    weight: f32,
+    _padding: f32,
+};
+
+@group(0) @binding(0) var<uniform> scene: SdfUniforms;
+
+const MAX_STEPS: i32 = 128;
+const MAX_DISTANCE: f32 = 50.0;
+const HIT_DISTANCE: f32 = 0.001;
+// Vertical field of view of 60 degrees
+const FOCAL_LENGTH: f32 = 1.732;
+
+fn sd_sphere(p: vec3<f32>, radius: f32) -> f32 {
+    return length(p) - radius;
+}
+
+fn sd_box(p: vec3<f32>, half_size: vec3<f32>) -> f32 {
+    let q = abs(p) - half_size;
+    return length(max(q, vec3<f32>(0.0))) + min(max(q.x, max(q.y, q.z)), 0.0);
+}
+
+fn sd_plane(p: vec3<f32>, height: f32) -> f32 {
+    return p.y - height;
+}
+
+// Distance to the closest surface, and the id of the material of that surface
+fn scene_sdf(p: vec3<f32>) -> vec2<f32> {
+    let bounce = abs(sin(scene.time * 2.0)) * 0.5;
+    let sphere = sd_sphere(p - vec3<f32>(-1.2, 0.5 + bounce, 0.0), 0.5);
+
+    // Rotate the box around the Y axis
+    let angle = scene.time * 0.7;
+    let c = cos(angle);
+    let s = sin(angle);
+    let box_p = p - vec3<f32>(1.2, 0.5, 0.0);
+    let rotated = vec3<f32>(c * box_p.x - s * box_p.z, box_p.y, s * box_p.x + c * box_p.z);
+    let box = sd_box(rotated, vec3<f32>(0.4)) - 0.05;
+
+    let plane = sd_plane(p, 0.0);
+
+    var closest = vec2<f32>(plane, 0.0);
+    if (sphere < closest.x) {
+        closest = vec2<f32>(sphere, 1.0);
+    }
+    if (box < closest.x) {
+        closest = vec2<f32>(box, 2.0);
+    }
+    return closest;
+}
+
+// The gradient of the distance field points away from the surface
+fn scene_normal(p: vec3<f32>) -> vec3<f32> {
+    let e = vec2<f32>(0.001, 0.0);
+    return normalize(vec3<f32>(
+        scene_sdf(p + e.xyy).x - scene_sdf(p - e.xyy).x,
+        scene_sdf(p + e.yxy).x - scene_sdf(p - e.yxy).x,
+        scene_sdf(p + e.yyx).x - scene_sdf(p - e.yyx).x,
+    ));
+}
+
+// March towards the light: the closer the ray passes to an occluder,
+// relative to how far along it is, the darker the penumbra
+fn soft_shadow(origin: vec3<f32>, direction: vec3<f32>, hardness: f32) -> f32 {
+    var shadow = 1.0;
+    var t = 0.02;
+    for (var i = 0; i < 64 && t < 10.0; i++) {
+        let d = scene_sdf(origin + direction * t).x;
+        if (d < HIT_DISTANCE) {
+            return 0.0;
+        }
+        shadow = min(shadow, hardness * d / t);
+        t += clamp(d, 0.01, 0.5);
+    }
+    return clamp(shadow, 0.0, 1.0);
+}
+
+fn material_color(id: f32, p: vec3<f32>) -> vec3<f32> {
+    if (id < 0.5) {
+        // Checkerboard floor
+        let checker = (i32(floor(p.x)) + i32(floor(p.z))) & 1;
+        return mix(vec3<f32>(0.3), vec3<f32>(0.6), f32(checker));
+    }
+    if (id < 1.5) {
+        return vec3<f32>(0.9, 0.3, 0.2);
+    }
+    return vec3<f32>(0.2, 0.5, 0.9);
+}
+
+@fragment
+fn fs_sdf(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    // Build the camera basis, looking from camera_position at camera_target
+    let forward = normalize(scene.camera_target - scene.camera_position);
+    let right = normalize(cross(forward, vec3<f32>(0.0, 1.0, 0.0)));
+    let up = cross(right, forward);
+
+    // UVs go down the screen, but the camera's up goes up
+    let uv = in.uv + scene.jitter;
+    let ndc = vec2<f32>(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0);
+    let direction = normalize(
+        forward * FOCAL_LENGTH + right * ndc.x * scene.aspect + up * ndc.y
+    );
+
+    let sky = mix(vec3<f32>(0.7, 0.8, 1.0), vec3<f32>(0.3, 0.5, 0.9), max(direction.y, 0.0));
+
+    var t = 0.0;
+    var hit = vec2<f32>(-1.0);
+    for (var i = 0; i < MAX_STEPS && t < MAX_DISTANCE; i++) {
+        let d = scene_sdf(scene.camera_position + direction * t);
+        if (d.x < HIT_DISTANCE * t) {
+            hit = vec2<f32>(t, d.y);
+            break;
+        }
+        t += d.x;
+    }
+
+    if (hit.x < 0.0) {
+        return vec4<f32>(sky, scene.weight);
+    }
+
+    let p = scene.camera_position + direction * hit.x;
+    let normal = scene_normal(p);
+    let light_direction = normalize(vec3<f32>(0.6, 0.8, 0.4));
+
+    // Offset the shadow ray along the normal so it doesn't hit its own surface
+    let shadow = soft_shadow(p + normal * 0.01, light_direction, 8.0);
+    let diffuse = max(dot(normal, light_direction), 0.0) * shadow;
+    let ambient = 0.15 + 0.1 * normal.y;
+
+    var color = material_color(hit.y, p) * (diffuse + ambient);
+    // Fade into the sky with the distance
+    color = mix(color, sky, 1.0 - exp(-0.002 * hit.x * hit.x));
+    return vec4<f32>(color, scene.weight);
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+pub use naga::ShaderStage;
+
+/// What can go wrong when checking a shader before handing it to wgpu
+#[derive(Debug)]
+pub enum ShaderError {
+    Parse {
+        label: String,
+        message: String,
+    },
+    MissingEntryPoint {
+        label: String,
+        name: String,
+        stage: ShaderStage,
+        available: Vec<String>,
+    },
+    WrongStage {
+        label: String,
+        name: String,
+        expected: ShaderStage,
+        found: ShaderStage,
+    },
+    /// Rejected by wgpu, e.g. a type error naga's parser lets through
+    #[cfg(not(target_arch = "wasm32"))]
+    Validation {
+        label: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Parse { label, message } => write!(f, "Failed to parse '{label}': {message}"),
+            Self::MissingEntryPoint {
+                label,
+                name,
+                stage,
+                available,
+            } => write!(
+                f,
+                "'{label}' has no {stage:?} entry point named '{name}' (available: {})",
+                available.join(", ")
+            ),
+            Self::WrongStage {
+                label,
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Entry point '{name}' in '{label}' is a {found:?} shader, expected {expected:?}"
+            ),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Validation { label, message } => {
+                write!(f, "wgpu rejected '{label}': {message}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ShaderError {}
+
+/// Check that every '(name, stage)' pair is an entry point of the WGSL 'source'.
+/// A single module can contain any number of vertex, fragment and compute
+/// entry points: a pipeline picks the ones it uses by name via
+/// `VertexState.entry_point` and `FragmentState.entry_point`, but wgpu only
+/// tells us about a typo once the pipeline gets created, and not very clearly.
+pub fn check_entry_points(
+    label: &str,
+    source: &str,
+    entry_points: &[(&str, ShaderStage)],
+) -> Result<(), ShaderError> {
+    let module = naga::front::wgsl::parse_str(source).map_err(|e| ShaderError::Parse {
+        label: label.to_string(),
+        message: e.emit_to_string(source),
+    })?;
+
+    for &(name, stage) in entry_points {
+        match module.entry_points.iter().find(|ep| ep.name == name) {
+            Some(ep) if ep.stage == stage => {}
+            Some(ep) => {
+                return Err(ShaderError::WrongStage {
+                    label: label.to_string(),
+                    name: name.to_string(),
+                    expected: stage,
+                    found: ep.stage,
+                })
+            }
+            None => {
+                return Err(ShaderError::MissingEntryPoint {
+                    label: label.to_string(),
+                    name: name.to_string(),
+                    stage,
+                    available: module
+                        .entry_points
+                        .iter()
+                        .map(|ep| ep.name.clone())
+                        .collect(),
+                })
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Create a shader module from WGSL, after checking that it has the entry points
+/// the caller is going to build pipelines with.
+/// Our shaders are baked into the binary, so a mismatch is a bug and we panic
+/// with a readable message instead of wgpu's validation error.
+pub fn create_shader_module(
+    device: &wgpu::Device,
+    label: &str,
+    source: &str,
+    entry_points: &[(&str, ShaderStage)],
+) -> wgpu::ShaderModule {
+    if let Err(e) = check_entry_points(label, source, entry_points) {
+        panic!("{e}");
+    }
+
+    device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    })
+}
+
+/// Run 'create' and return what it made, or the first validation error it
+/// caused. Outside of an error scope, validation errors go to the uncaptured
+/// error handler, which panics: that's right for our baked-in shaders, not
+/// for one that's being edited while the app runs.
+/// This blocks until the device has checked everything, so it's native only:
+/// a browser reports the errors asynchronously, and can't be blocked on.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn capture_validation_errors<T>(
+    device: &wgpu::Device,
+    label: &str,
+    create: impl FnOnce() -> T,
+) -> Result<T, ShaderError> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let created = create();
+
+    match pollster::block_on(device.pop_error_scope()) {
+        None => Ok(created),
+        Some(error) => Err(ShaderError::Validation {
+            label: label.to_string(),
+            // The Display of the error is only "Validation Error",
+            // the description has the details
+            message: match error {
+                wgpu::Error::Validation { description, .. } => description,
+                error => error.to_string(),
+            },
+        }),
+    }
+}
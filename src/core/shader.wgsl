@@ -0,0 +1,69 @@
+// The hello world of the app: geometry with a color in each corner,
+// read from the vertex buffer, spinning around the center over time and
+// seen through the camera. It's drawn once per instance, each with its own
+// offset and rotation.
+// The colors tint a texture, which is a single white texel by default.
+
+// Padded to 16 bytes on the CPU side
+struct TimeUniforms {
+    // In seconds
+    elapsed: f32,
+};
+
+@group(0) @binding(0)
+var<uniform> time: TimeUniforms;
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+struct CameraUniforms {
+    view_projection: mat4x4<f32>,
+};
+
+@group(2) @binding(0)
+var<uniform> camera: CameraUniforms;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec3<f32>,
+    @location(2) tex_coords: vec2<f32>,
+};
+
+// Right after the locations of VertexInput
+struct InstanceInput {
+    @location(3) offset: vec3<f32>,
+    @location(4) rotation: f32,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec3<f32>,
+    @location(1) tex_coords: vec2<f32>,
+};
+
+// In radians per second
+const SPIN_SPEED: f32 = 0.5;
+
+@vertex
+fn vs_main(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    let angle = time.elapsed * SPIN_SPEED + instance.rotation;
+    let rotation = mat2x2<f32>(cos(angle), sin(angle), -sin(angle), cos(angle));
+
+    var out: VertexOutput;
+    let local_position = vec3<f32>(rotation * in.position.xy, in.position.z);
+    let world_position = vec4<f32>(local_position + instance.offset, 1.0);
+    out.position = camera.view_projection * world_position;
+    // Interpolated across each triangle by the rasterizer
+    out.color = in.color;
+    out.tex_coords = in.tex_coords;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Sampling the sRGB texture decodes its colors to linear
+    let texel = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    return vec4<f32>(in.color * texel.rgb, texel.a);
+}
@@ -0,0 +1,322 @@
+use super::depth;
+use super::memory::{MemoryRegistry, Tracked};
+use super::shader::{self, ShaderStage};
+use super::upload::BufferWriter;
+
+/// How many straight pieces each curve segment is approximated with
+const SAMPLES_PER_SEGMENT: usize = 16;
+
+/// Extra geometry on each side of a stroke, in pixels, where it fades out
+const FEATHER: f32 = 1.0;
+
+/// Room for a few curves before the vertex buffer has to grow
+const INITIAL_VERTEX_CAPACITY: usize = 4096;
+
+/// Sharp turns would make the miter joints explode, so they're capped
+/// at this many times the stroke width
+const MAX_MITER_SCALE: f32 = 4.0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct StrokeVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+    edge: f32,
+    half_widths: [f32; 2],
+}
+
+/// Draws smooth curves as antialiased strokes of any width.
+/// Each curve is a Catmull-Rom spline, which goes through all of its control
+/// points. It's sampled into a polyline on the CPU, and every point of the
+/// polyline gets pushed out on both sides along the normal of the curve:
+/// consecutive pieces share those points, so there are no gaps at the joints.
+/// Strokes are queued with `draw_spline()` during the frame, then uploaded
+/// by `prepare()` and drawn all at once by `draw()`.
+pub struct SplineRenderer {
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Tracked<wgpu::Buffer>,
+    // Where the vertex buffer is created again when it has to grow
+    memory: MemoryRegistry,
+    // In pixels until prepare() turns them into clip space
+    vertices: Vec<StrokeVertex>,
+    vertex_count: u32,
+}
+
+impl SplineRenderer {
+    pub fn new(
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Spline Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            pipeline: create_pipeline(device, &pipeline_layout, format, sample_count),
+            pipeline_layout,
+            vertex_buffer: create_vertex_buffer(
+                device,
+                memory,
+                INITIAL_VERTEX_CAPACITY * std::mem::size_of::<StrokeVertex>(),
+            ),
+            memory: memory.clone(),
+            vertices: Vec::new(),
+            vertex_count: 0,
+        }
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, format, sample_count);
+    }
+
+    /// Queue a stroke going through 'points', given in pixels from the top
+    /// left corner of the window. 'width' is in pixels too.
+    pub fn draw_spline(&mut self, points: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        self.draw_polyline(&sample_catmull_rom(points), width, color);
+    }
+
+    /// Like `draw_spline()`, but with straight lines between the points
+    pub fn draw_polyline(&mut self, polyline: &[[f32; 2]], width: f32, color: [f32; 4]) {
+        if polyline.len() < 2 {
+            return;
+        }
+
+        let half_width = width / 2.0;
+        let geometry_half_width = half_width + FEATHER;
+
+        // Both sides of the stroke at every point of the polyline
+        let sides: Vec<([f32; 2], [f32; 2])> = (0..polyline.len())
+            .map(|i| {
+                let mut point = polyline[i];
+                let normal = if i == 0 {
+                    // Square caps: push the ends out by half the width
+                    let direction = normalize(sub(polyline[1], polyline[0]));
+                    point = sub(point, scale(direction, half_width));
+                    perpendicular(direction)
+                } else if i == polyline.len() - 1 {
+                    let direction = normalize(sub(polyline[i], polyline[i - 1]));
+                    point = add(point, scale(direction, half_width));
+                    perpendicular(direction)
+                } else {
+                    miter(polyline[i - 1], polyline[i], polyline[i + 1])
+                };
+
+                let offset = scale(normal, geometry_half_width);
+                (add(point, offset), sub(point, offset))
+            })
+            .collect();
+
+        let vertex = |position, edge| StrokeVertex {
+            position,
+            color,
+            edge,
+            half_widths: [half_width, geometry_half_width],
+        };
+        for pair in sides.windows(2) {
+            let ((left0, right0), (left1, right1)) = (pair[0], pair[1]);
+            self.vertices.extend_from_slice(&[
+                vertex(left0, 1.0),
+                vertex(right0, -1.0),
+                vertex(left1, 1.0),
+                vertex(left1, 1.0),
+                vertex(right0, -1.0),
+                vertex(right1, -1.0),
+            ]);
+        }
+    }
+
+    /// Upload the strokes queued this frame, for a target of 'size' pixels
+    pub fn prepare(&mut self, writer: &mut BufferWriter, size: winit::dpi::PhysicalSize<u32>) {
+        let (width, height) = (size.width as f32, size.height as f32);
+        for vertex in &mut self.vertices {
+            let [x, y] = vertex.position;
+            // Pixels have y going down, clip space has it going up
+            vertex.position = [x / width * 2.0 - 1.0, 1.0 - y / height * 2.0];
+        }
+
+        let bytes: &[u8] = bytemuck::cast_slice(&self.vertices);
+        if bytes.len() as wgpu::BufferAddress > self.vertex_buffer.size() {
+            // Grow in powers of 2, so that it doesn't get recreated every frame
+            self.vertex_buffer =
+                create_vertex_buffer(writer.device, &self.memory, bytes.len().next_power_of_two());
+        }
+        writer.write(&self.vertex_buffer, 0, bytes);
+
+        self.vertex_count = self.vertices.len() as u32;
+        self.vertices.clear();
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        if self.vertex_count == 0 {
+            return;
+        }
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.draw(0..self.vertex_count, 0..1);
+    }
+}
+
+/// Sample the Catmull-Rom spline going through 'points' into a polyline.
+/// The first and last points are repeated, so that the curve reaches them.
+fn sample_catmull_rom(points: &[[f32; 2]]) -> Vec<[f32; 2]> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let point = |i: isize| points[i.clamp(0, points.len() as isize - 1) as usize];
+    let mut polyline = Vec::with_capacity((points.len() - 1) * SAMPLES_PER_SEGMENT + 1);
+
+    for segment in 0..points.len() as isize - 1 {
+        let (p0, p1, p2, p3) = (
+            point(segment - 1),
+            point(segment),
+            point(segment + 1),
+            point(segment + 2),
+        );
+        for sample in 0..SAMPLES_PER_SEGMENT {
+            let t = sample as f32 / SAMPLES_PER_SEGMENT as f32;
+            let (t2, t3) = (t * t, t * t * t);
+            polyline.push(std::array::from_fn(|i| {
+                0.5 * (2.0 * p1[i]
+                    + (p2[i] - p0[i]) * t
+                    + (2.0 * p0[i] - 5.0 * p1[i] + 4.0 * p2[i] - p3[i]) * t2
+                    + (3.0 * p1[i] - p0[i] - 3.0 * p2[i] + p3[i]) * t3)
+            }));
+        }
+    }
+    polyline.push(points[points.len() - 1]);
+
+    polyline
+}
+
+/// Direction to push 'point' out along, scaled so that the stroke keeps its
+/// width on both of the pieces meeting there
+fn miter(previous: [f32; 2], point: [f32; 2], next: [f32; 2]) -> [f32; 2] {
+    let normal_in = perpendicular(normalize(sub(point, previous)));
+    let normal_out = perpendicular(normalize(sub(next, point)));
+    let miter = normalize(add(normal_in, normal_out));
+
+    // A U-turn makes the two normals cancel out
+    let cos = dot(miter, normal_in);
+    if cos.abs() < 1.0 / MAX_MITER_SCALE {
+        return scale(normal_in, MAX_MITER_SCALE);
+    }
+    scale(miter, 1.0 / cos)
+}
+
+fn add(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] + b[0], a[1] + b[1]]
+}
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn scale(a: [f32; 2], s: f32) -> [f32; 2] {
+    [a[0] * s, a[1] * s]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn normalize(a: [f32; 2]) -> [f32; 2] {
+    let length = dot(a, a).sqrt();
+    if length == 0.0 {
+        return [0.0, 0.0];
+    }
+    scale(a, 1.0 / length)
+}
+
+fn perpendicular([x, y]: [f32; 2]) -> [f32; 2] {
+    [-y, x]
+}
+
+fn create_vertex_buffer(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    size: usize,
+) -> Tracked<wgpu::Buffer> {
+    memory.create_buffer(
+        device,
+        &wgpu::BufferDescriptor {
+            label: Some("Spline Vertex Buffer"),
+            size: size as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        },
+    )
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = shader::create_shader_module(
+        device,
+        "Spline Shader",
+        include_str!("spline.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+
+    let vertex_layout = wgpu::VertexBufferLayout {
+        array_stride: std::mem::size_of::<StrokeVertex>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![
+            0 => Float32x2,
+            1 => Float32x4,
+            2 => Float32,
+            3 => Float32x2
+        ],
+    };
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Spline Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[vertex_layout],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                // The edges of the strokes are partially transparent
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            // The strokes wind both ways depending on where the curve turns
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: depth::depth_ignored(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
@@ -0,0 +1,38 @@
+// Draws the strokes built by spline.rs. The positions are already in clip
+// space; 'edge' goes from -1 to 1 across the stroke, which is all the
+// fragment shader needs to fade out the last pixel on each side.
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) edge: f32,
+    // Half width of the stroke and of the geometry, which is a bit wider
+    // to leave room for the antialiasing, in pixels
+    @location(3) half_widths: vec2<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) edge: f32,
+    @location(2) half_widths: vec2<f32>,
+};
+
+@vertex
+fn vs_main(in: VertexInput) -> VertexOutput {
+    var out: VertexOutput;
+    out.position = vec4<f32>(in.position, 0.0, 1.0);
+    out.color = in.color;
+    out.edge = in.edge;
+    out.half_widths = in.half_widths;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Distance from the center of the stroke, in pixels
+    let distance = abs(in.edge) * in.half_widths.y;
+    // 1 inside the stroke, fading to 0 over one pixel at its border
+    let coverage = clamp(in.half_widths.x + 0.5 - distance, 0.0, 1.0);
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
@@ -0,0 +1,309 @@
+use super::config::AppConfig;
+use super::gpu::GpuContext;
+use super::memory::{MemoryRegistry, Tracked};
+
+/// How many images wgpu asks the swapchain for, whatever the present mode.
+/// The surface can still settle on another count within what it supports,
+/// which wgpu doesn't report back.
+const SWAPCHAIN_IMAGE_COUNT: u32 = 3;
+
+/// The part of the window that's rendered into, and how it's configured.
+/// Without a window, see offscreen(), every frame is rendered into a new
+/// texture instead, which is never shown.
+pub struct RenderSurface {
+    // It mustn't outlive the window it was created from.
+    // None when rendering offscreen.
+    surface: Option<wgpu::Surface>,
+    pub config: wgpu::SurfaceConfiguration,
+    // Size of the window, which is 0x0 while it's minimized,
+    // unlike the config which keeps its last renderable size
+    pub size: winit::dpi::PhysicalSize<u32>,
+    // Every present mode the surface supports
+    pub present_modes: Vec<wgpu::PresentMode>,
+    // Every format the surface supports, cycled through with F
+    pub formats: Vec<wgpu::TextureFormat>,
+}
+
+impl RenderSurface {
+    /// Configure 'surface' for a window of 'size', with the format and the
+    /// present mode of 'config' when the surface supports them
+    pub fn new(
+        surface: wgpu::Surface,
+        gpu: &GpuContext,
+        size: winit::dpi::PhysicalSize<u32>,
+        config: &AppConfig,
+    ) -> Self {
+        let surface_capabilities = surface.get_capabilities(&gpu.adapter);
+
+        eprintln!("Format supported by this surface:");
+        for surface_format in surface_capabilities.formats.iter() {
+            eprintln!("{:?}", surface_format);
+        }
+
+        let surface_format =
+            choose_surface_format(&surface_capabilities, config.surface_format, config.hdr);
+        eprintln!("Surface format chosen: {surface_format:?}");
+
+        // A surface configured with a width or height of 0 crashes the app,
+        // which can happen if the window starts minimized
+        let surface_config = wgpu::SurfaceConfiguration {
+            // 'RENDER_ATTACHMENTS' specifies that the texture will be used
+            // to write to the screen
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            present_mode: choose_present_mode(
+                &surface_capabilities.present_modes,
+                config.present_mode,
+            ),
+            // alpha_mode: surface_capabilities.alpha_modes[0],
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            // Lets us view the swapchain texture with the other encoding too,
+            // see view()
+            view_formats: surface_view_formats(&gpu.adapter, surface_format),
+        };
+
+        surface.configure(&gpu.device, &surface_config);
+
+        Self {
+            surface: Some(surface),
+            config: surface_config,
+            size,
+            present_modes: surface_capabilities.present_modes,
+            formats: surface_capabilities.formats,
+        }
+    }
+
+    /// A surface that isn't backed by a window, for rendering headlessly
+    /// (e.g. in tests). Its frames can be copied out, see acquire().
+    pub fn offscreen(size: winit::dpi::PhysicalSize<u32>, format: wgpu::TextureFormat) -> Self {
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            format,
+            width: size.width.max(1),
+            height: size.height.max(1),
+            // Nothing is presented, so there's nothing to wait for either
+            present_mode: wgpu::PresentMode::Fifo,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
+            view_formats: vec![],
+        };
+
+        Self {
+            surface: None,
+            config,
+            size,
+            present_modes: vec![wgpu::PresentMode::Fifo],
+            formats: vec![format],
+        }
+    }
+
+    /// Apply changes made to the config
+    pub fn configure(&self, device: &wgpu::Device) {
+        if let Some(surface) = &self.surface {
+            surface.configure(device, &self.config);
+        }
+    }
+
+    /// How many images the surface is made of, to estimate its size.
+    /// An offscreen surface has none: its frames are textures of their own,
+    /// tracked while they're alive, see acquire().
+    pub fn image_count(&self) -> u32 {
+        if self.surface.is_some() {
+            SWAPCHAIN_IMAGE_COUNT
+        } else {
+            0
+        }
+    }
+
+    /// The texture the next frame is rendered into
+    pub fn acquire(
+        &self,
+        device: &wgpu::Device,
+        memory: &MemoryRegistry,
+    ) -> Result<SurfaceFrame, wgpu::SurfaceError> {
+        let Some(surface) = &self.surface else {
+            // Like a swapchain texture, it's only rendered into once
+            let texture = memory.create_texture(
+                device,
+                &wgpu::TextureDescriptor {
+                    label: Some("Offscreen Surface Texture"),
+                    size: wgpu::Extent3d {
+                        width: self.config.width,
+                        height: self.config.height,
+                        depth_or_array_layers: 1,
+                    },
+                    mip_level_count: 1,
+                    sample_count: 1,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: self.config.format,
+                    usage: self.config.usage,
+                    view_formats: &self.config.view_formats,
+                },
+            );
+            return Ok(SurfaceFrame::Offscreen(texture));
+        };
+
+        surface.get_current_texture().map(SurfaceFrame::Window)
+    }
+
+    /// Follow the size of the window. Minimizing it resizes it to 0x0, which
+    /// the surface can't be configured with: it keeps its last size instead.
+    /// Returns whether the surface was reconfigured.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        new_size: winit::dpi::PhysicalSize<u32>,
+    ) -> bool {
+        self.size = new_size;
+        if !is_renderable(new_size) {
+            return false;
+        }
+
+        self.config.width = new_size.width;
+        self.config.height = new_size.height;
+        self.configure(device);
+        true
+    }
+
+    /// View the swapchain texture as sRGB (writes get gamma encoded)
+    /// or as linear (the values are stored as they are), regardless of
+    /// the format of the surface itself.
+    /// Falls back to the surface format if the other one isn't available.
+    pub fn view(&self, frame: &SurfaceFrame, srgb: bool) -> wgpu::TextureView {
+        let surface_format = self.config.format;
+        let mut format = if srgb {
+            surface_format.add_srgb_suffix()
+        } else {
+            surface_format.remove_srgb_suffix()
+        };
+        if format != surface_format && !self.config.view_formats.contains(&format) {
+            log::warn!("The surface can't be viewed as {format:?}, using {surface_format:?}");
+            format = surface_format;
+        }
+
+        frame.texture().create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Surface View"),
+            format: Some(format),
+            ..Default::default()
+        })
+    }
+}
+
+/// A texture acquired from a RenderSurface, to render a frame into
+pub enum SurfaceFrame {
+    Window(wgpu::SurfaceTexture),
+    Offscreen(Tracked<wgpu::Texture>),
+}
+
+impl SurfaceFrame {
+    pub fn texture(&self) -> &wgpu::Texture {
+        match self {
+            Self::Window(surface_texture) => &surface_texture.texture,
+            Self::Offscreen(texture) => texture,
+        }
+    }
+
+    /// Show the frame in the window. An offscreen frame is just dropped.
+    pub fn present(self) {
+        if let Self::Window(surface_texture) = self {
+            surface_texture.present();
+        }
+    }
+}
+
+/// Use the format requested in the config if the surface supports it,
+/// otherwise prefer an sRGB format so that colors are gamma corrected for us.
+///
+/// With 'hdr', Rgba16Float comes first when it's supported. Its values are
+/// linear like the ones shaders and clear colors work with, and the system
+/// shows them as such: (1, 1, 1) is the same white as on an sRGB surface, and
+/// anything above it is brighter. So nothing has to be gamma corrected by
+/// hand, unlike on a linear 8 bit format which is shown as if it was sRGB
+/// encoded, making colors look darker.
+fn choose_surface_format(
+    surface_capabilities: &wgpu::SurfaceCapabilities,
+    requested: Option<wgpu::TextureFormat>,
+    hdr: bool,
+) -> wgpu::TextureFormat {
+    let formats = &surface_capabilities.formats;
+
+    if let Some(requested) = requested {
+        if formats.contains(&requested) {
+            return requested;
+        }
+        log::warn!(
+            "The requested surface format {requested:?} is not supported, \
+             available formats are: {formats:?}"
+        );
+    }
+
+    if hdr {
+        let hdr_format = wgpu::TextureFormat::Rgba16Float;
+        if formats.contains(&hdr_format) {
+            return hdr_format;
+        }
+        log::warn!("HDR needs {hdr_format:?}, which the surface doesn't support: using SDR");
+    }
+
+    match formats.iter().copied().find(|f| f.is_srgb()) {
+        Some(format) => format,
+        None => {
+            // Without an sRGB swapchain the output is not gamma corrected,
+            // so colors will look darker or washed out
+            log::warn!(
+                "No sRGB surface format available, falling back to {:?}. \
+                 Available formats are: {formats:?}",
+                formats[0]
+            );
+            formats[0]
+        }
+    }
+}
+
+/// 'requested' if it's one of the 'supported' modes, otherwise Fifo,
+/// which every surface supports
+pub fn choose_present_mode(
+    supported: &[wgpu::PresentMode],
+    requested: wgpu::PresentMode,
+) -> wgpu::PresentMode {
+    if supported.contains(&requested) {
+        requested
+    } else {
+        log::warn!(
+            "Present mode {requested:?} is not supported, falling back to Fifo. \
+             Available modes are: {supported:?}"
+        );
+        wgpu::PresentMode::Fifo
+    }
+}
+
+/// The sRGB counterpart of a linear surface format or vice versa,
+/// when there is one and the adapter can create views in another format
+pub fn surface_view_formats(
+    adapter: &wgpu::Adapter,
+    format: wgpu::TextureFormat,
+) -> Vec<wgpu::TextureFormat> {
+    let other_format = if format.is_srgb() {
+        format.remove_srgb_suffix()
+    } else {
+        format.add_srgb_suffix()
+    };
+    if other_format == format {
+        return vec![];
+    }
+
+    let flags = adapter.get_downlevel_capabilities().flags;
+    if !flags.contains(wgpu::DownlevelFlags::SURFACE_VIEW_FORMATS) {
+        log::info!("The surface can only be viewed as {format:?}, not as {other_format:?}");
+        return vec![];
+    }
+
+    vec![other_format]
+}
+
+/// Whether a window of 'size' has any pixels to render, which it doesn't
+/// while minimized
+pub fn is_renderable(size: winit::dpi::PhysicalSize<u32>) -> bool {
+    size.width > 0 && size.height > 0
+}
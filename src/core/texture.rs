@@ -0,0 +1,513 @@
+use std::fmt;
+use std::path::Path;
+
+use super::compressed::{self, CompressedError, CompressedImage};
+use super::memory::{MemoryRegistry, Tracked};
+
+/// Image files store sRGB encoded colors. Sampling an sRGB texture decodes
+/// them to linear, and the sRGB surface encodes them back on write: with a
+/// linear format they would be encoded twice, and look washed out.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Test patterns are meant to show the texture coordinates,
+/// so they aren't blurred when they're scaled up
+const PATTERN_SAMPLER: SamplerDesc = SamplerDesc {
+    wrap_u: wgpu::AddressMode::Repeat,
+    wrap_v: wgpu::AddressMode::Repeat,
+    mag_filter: wgpu::FilterMode::Nearest,
+    min_filter: wgpu::FilterMode::Linear,
+};
+
+/// 3x5 pixel glyphs of the digits, one row per byte, used to number the
+/// cells of the UV grid
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+#[derive(Debug)]
+pub enum TextureError {
+    /// The file couldn't be read or decoded, e.g. a JPEG when the image
+    /// crate is built with PNG support only
+    Image(image::ImageError),
+    /// A DDS or KTX2 file that couldn't be read as it is
+    Compressed(CompressedError),
+    /// Wider or taller than the device supports
+    TooLarge { size: (u32, u32), max: u32 },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Image(e) => write!(f, "Failed to load the image: {e}"),
+            Self::Compressed(e) => write!(f, "Failed to load the compressed image: {e}"),
+            Self::TooLarge {
+                size: (width, height),
+                max,
+            } => write!(
+                f,
+                "The {width}x{height} image is larger than the {max}x{max} textures \
+                 the device supports"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Image(e) => Some(e),
+            Self::Compressed(e) => Some(e),
+            Self::TooLarge { .. } => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(e: image::ImageError) -> Self {
+        Self::Image(e)
+    }
+}
+
+impl From<CompressedError> for TextureError {
+    fn from(e: CompressedError) -> Self {
+        Self::Compressed(e)
+    }
+}
+
+/// How a texture is sampled: what's read past the [0, 1] UV range, and
+/// between the texels when it's scaled up ('mag_filter') or down
+/// ('min_filter', which also blends between the mip levels)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SamplerDesc {
+    pub wrap_u: wgpu::AddressMode,
+    pub wrap_v: wgpu::AddressMode,
+    pub mag_filter: wgpu::FilterMode,
+    pub min_filter: wgpu::FilterMode,
+}
+
+impl SamplerDesc {
+    /// Stretches the edge texels instead of wrapping around, for the images
+    /// that aren't meant to tile (UI elements, photos): with Repeat, linear
+    /// filtering blends the opposite edge into each border.
+    pub const CLAMPED: Self = Self {
+        wrap_u: wgpu::AddressMode::ClampToEdge,
+        wrap_v: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Linear,
+        min_filter: wgpu::FilterMode::Linear,
+    };
+
+    fn descriptor<'a>(&self, label: &'a str) -> wgpu::SamplerDescriptor<'a> {
+        wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: self.wrap_u,
+            address_mode_v: self.wrap_v,
+            mag_filter: self.mag_filter,
+            min_filter: self.min_filter,
+            mipmap_filter: self.min_filter,
+            ..Default::default()
+        }
+    }
+}
+
+/// Smooth and tiling
+impl Default for SamplerDesc {
+    fn default() -> Self {
+        Self {
+            wrap_u: wgpu::AddressMode::Repeat,
+            wrap_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+        }
+    }
+}
+
+/// A sampled 2D texture with its default view and sampler
+pub struct Texture {
+    pub texture: Tracked<wgpu::Texture>,
+    pub view: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+}
+
+impl Texture {
+    /// Load an image file in any format the image crate is built with (PNG
+    /// for now), sampled as 'sampler' says.
+    /// Any size up to the device limit works, powers of two or not.
+    /// DDS and KTX2 files go through `load_compressed()`.
+    pub fn from_path(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        path: &Path,
+        sampler: SamplerDesc,
+    ) -> Result<Self, TextureError> {
+        if compressed::is_compressed_path(path) {
+            return Self::load_compressed(device, queue, memory, path, sampler);
+        }
+
+        let image = image::open(path)?.into_rgba8();
+
+        let max = device.limits().max_texture_dimension_2d;
+        if image.width() > max || image.height() > max {
+            return Err(TextureError::TooLarge {
+                size: image.dimensions(),
+                max,
+            });
+        }
+
+        let label = path.display().to_string();
+
+        Ok(Self::upload(device, queue, memory, &label, &image, sampler))
+    }
+
+    /// Upload the blocks of a DDS (BCn) or KTX2 (BCn, ETC2 or ASTC) file with
+    /// all of its mip levels, without decompressing them on the CPU.
+    /// Each family of formats needs its own device feature: without it, the
+    /// PNG file next to it with the same name is loaded instead.
+    pub fn load_compressed(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        path: &Path,
+        sampler: SamplerDesc,
+    ) -> Result<Self, TextureError> {
+        let image = CompressedImage::open(path)?;
+
+        let missing_features = image.format.required_features() - device.features();
+        if !missing_features.is_empty() {
+            let fallback = path.with_extension("png");
+            log::warn!(
+                "{:?} needs {missing_features:?}, which the device doesn't have: \
+                 loading {fallback:?} instead",
+                image.format
+            );
+            return Self::from_path(device, queue, memory, &fallback, sampler);
+        }
+
+        let max = device.limits().max_texture_dimension_2d;
+        if image.size.0 > max || image.size.1 > max {
+            return Err(TextureError::TooLarge {
+                size: image.size,
+                max,
+            });
+        }
+
+        let label = path.display().to_string();
+        let size = wgpu::Extent3d {
+            width: image.size.0,
+            height: image.size.1,
+            depth_or_array_layers: 1,
+        };
+        // Files can have more levels than the size allows, down to 0x0
+        let mip_level_count =
+            (image.levels.len() as u32).min(size.max_mips(wgpu::TextureDimension::D2));
+        let texture = memory.create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some(&label),
+                size,
+                mip_level_count,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: image.format,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+        );
+
+        for (level, data) in image
+            .levels
+            .iter()
+            .take(mip_level_count as usize)
+            .enumerate()
+        {
+            let layout = compressed::level_layout(image.format, image.size, level as u32);
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: level as u32,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                data,
+                // In rows of blocks: 'bytes_per_row' is for a whole row of
+                // blocks, and there are a quarter as many rows as pixels
+                // with 4x4 blocks
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(layout.bytes_per_row),
+                    rows_per_image: Some(layout.rows),
+                },
+                layout.extent,
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&sampler.descriptor(&label));
+
+        Ok(Self {
+            texture,
+            view,
+            sampler,
+        })
+    }
+
+    /// Upload an sRGB image
+    pub fn from_image(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        label: &str,
+        image: &image::RgbaImage,
+        sampler: SamplerDesc,
+    ) -> Self {
+        Self::upload(device, queue, memory, label, image, sampler)
+    }
+
+    /// A single white texel, which leaves the colors it's multiplied with as
+    /// they are: it stands in for a texture when there's none to show
+    pub fn white(device: &wgpu::Device, queue: &wgpu::Queue, memory: &MemoryRegistry) -> Self {
+        let image = image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4]));
+        Self::from_image(
+            device,
+            queue,
+            memory,
+            "White Texture",
+            &image,
+            SamplerDesc::default(),
+        )
+    }
+
+    fn upload(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        label: &str,
+        image: &image::RgbaImage,
+        sampler: SamplerDesc,
+    ) -> Self {
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = memory.create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+        );
+
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            image,
+            // Unlike copies from a buffer, writes from the CPU don't need
+            // their rows aligned to 256 bytes: the pixels go in tightly packed
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(image.width() * 4),
+                rows_per_image: Some(image.height()),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&sampler.descriptor(label));
+
+        Self {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
+    /// The layout of `create_bind_group()`: the texture at binding 0 and its
+    /// sampler at binding 1, both read by fragment shaders
+    pub fn bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    pub fn create_bind_group(
+        &self,
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&self.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&self.sampler),
+                },
+            ],
+        })
+    }
+
+    /// A 'size' x 'size' checkerboard of 'squares' x 'squares' squares
+    // The test patterns are only drawn as texture array layers so far
+    #[allow(dead_code)]
+    pub fn checkerboard(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        size: u32,
+        color_a: [u8; 4],
+        color_b: [u8; 4],
+        squares: u32,
+    ) -> Self {
+        let image = checkerboard_image(size, color_a, color_b, squares);
+        Self::from_image(
+            device,
+            queue,
+            memory,
+            "Checkerboard Texture",
+            &image,
+            PATTERN_SAMPLER,
+        )
+    }
+
+    /// A 'size' x 'size' grid of 'cells' x 'cells' numbered cells, see `uv_grid_image()`
+    #[allow(dead_code)]
+    pub fn uv_grid(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        size: u32,
+        cells: u32,
+    ) -> Self {
+        let image = uv_grid_image(size, cells);
+        Self::from_image(
+            device,
+            queue,
+            memory,
+            "UV Grid Texture",
+            &image,
+            PATTERN_SAMPLER,
+        )
+    }
+}
+
+/// Alternating squares of 'color_a' and 'color_b', starting with 'color_a'
+/// in the top left corner. 'squares' is clamped to the size of the image.
+pub fn checkerboard_image(
+    size: u32,
+    color_a: [u8; 4],
+    color_b: [u8; 4],
+    squares: u32,
+) -> image::RgbaImage {
+    let square_size = (size / squares.clamp(1, size.max(1))).max(1);
+
+    image::RgbaImage::from_fn(size, size, |x, y| {
+        if (x / square_size + y / square_size).is_multiple_of(2) {
+            image::Rgba(color_a)
+        } else {
+            image::Rgba(color_b)
+        }
+    })
+}
+
+/// The classic UV test grid: every cell is tinted by its coordinates, red
+/// growing with U and green with V, and numbered from 0 in the top left
+/// corner, row by row. Flipped, stretched, or swapped texture coordinates
+/// then show up at a glance.
+pub fn uv_grid_image(size: u32, cells: u32) -> image::RgbaImage {
+    let cells = cells.clamp(1, size.max(1));
+    let cell_size = (size / cells).max(1);
+    // Scale the digits up with the cells, keeping a margin around them
+    let scale = (cell_size / 16).max(1);
+
+    let mut image = image::RgbaImage::from_fn(size, size, |x, y| {
+        let (column, row) = (
+            (x / cell_size).min(cells - 1),
+            (y / cell_size).min(cells - 1),
+        );
+        if x % cell_size == 0 || y % cell_size == 0 {
+            return image::Rgba([0, 0, 0, 255]);
+        }
+
+        let tint = |i: u32| (40 + (i * 2 + 1) * 180 / (cells * 2)) as u8;
+        image::Rgba([tint(column), tint(row), 90, 255])
+    });
+
+    for row in 0..cells {
+        for column in 0..cells {
+            let left = column * cell_size + 2 * scale;
+            let top = row * cell_size + 2 * scale;
+            draw_number(&mut image, row * cells + column, left, top, scale);
+        }
+    }
+
+    image
+}
+
+/// Write 'number' in white with its top left corner at ('left', 'top'),
+/// clipping whatever goes past the edges of the image
+fn draw_number(image: &mut image::RgbaImage, number: u32, left: u32, top: u32, scale: u32) {
+    for (i, digit) in number.to_string().bytes().enumerate() {
+        let glyph = DIGITS[(digit - b'0') as usize];
+        // 3 pixels per digit plus 1 of spacing
+        let glyph_left = left + i as u32 * 4 * scale;
+
+        for (glyph_y, bits) in glyph.iter().enumerate() {
+            for glyph_x in 0..3 {
+                if bits & (0b100 >> glyph_x) == 0 {
+                    continue;
+                }
+                for dy in 0..scale {
+                    for dx in 0..scale {
+                        let x = glyph_left + glyph_x * scale + dx;
+                        let y = top + glyph_y as u32 * scale + dy;
+                        if x < image.width() && y < image.height() {
+                            image.put_pixel(x, y, image::Rgba([255, 255, 255, 255]));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,347 @@
+use std::fmt;
+
+use super::depth;
+use super::memory::{MemoryRegistry, Tracked};
+use super::shader::{self, ShaderStage};
+use super::texture;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// Side of the procedural layers of the demo, in pixels
+const DEMO_LAYER_SIZE: u32 = 64;
+const DEMO_LAYERS: u32 = 4;
+
+/// Why a set of images can't be turned into a texture array
+#[derive(Debug)]
+pub enum TextureArrayError {
+    Empty,
+    /// Every layer of an array has the size of the first one
+    SizeMismatch {
+        layer: usize,
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    /// Every layer has to come from images in the same color format,
+    /// to catch e.g. a grayscale image mixed with color ones
+    FormatMismatch {
+        layer: usize,
+        expected: image::ColorType,
+        found: image::ColorType,
+    },
+}
+
+impl fmt::Display for TextureArrayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "A texture array needs at least one layer"),
+            Self::SizeMismatch {
+                layer,
+                expected: (expected_width, expected_height),
+                found: (width, height),
+            } => write!(
+                f,
+                "Layer {layer} is {width}x{height}, \
+                 but the first layer is {expected_width}x{expected_height}"
+            ),
+            Self::FormatMismatch {
+                layer,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Layer {layer} is {found:?}, but the first layer is {expected:?}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TextureArrayError {}
+
+/// Several same-size images stored as the layers of a single texture.
+/// A shader binds the whole array once and picks the layer with a plain
+/// index, instead of switching bind groups between separate textures.
+pub struct TextureArray {
+    texture: Tracked<wgpu::Texture>,
+    view: wgpu::TextureView,
+}
+
+impl TextureArray {
+    /// Upload 'layers' in order, layer 0 being the first image.
+    /// The images are converted to sRGB RGBA8.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        label: &str,
+        layers: &[image::DynamicImage],
+    ) -> Result<Self, TextureArrayError> {
+        let first = layers.first().ok_or(TextureArrayError::Empty)?;
+        let (width, height) = (first.width(), first.height());
+
+        for (layer, image) in layers.iter().enumerate().skip(1) {
+            if (image.width(), image.height()) != (width, height) {
+                return Err(TextureArrayError::SizeMismatch {
+                    layer,
+                    expected: (width, height),
+                    found: (image.width(), image.height()),
+                });
+            }
+            if image.color() != first.color() {
+                return Err(TextureArrayError::FormatMismatch {
+                    layer,
+                    expected: first.color(),
+                    found: image.color(),
+                });
+            }
+        }
+
+        let texture = memory.create_texture(
+            device,
+            &wgpu::TextureDescriptor {
+                label: Some(label),
+                size: wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: layers.len() as u32,
+                },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: FORMAT,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+                view_formats: &[],
+            },
+        );
+
+        for (layer, image) in layers.iter().enumerate() {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    // For arrays, z is the layer
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &image.to_rgba8(),
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: Some(height),
+                },
+                wgpu::Extent3d {
+                    width,
+                    height,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        // The dimension has to be explicit: with a single layer the default
+        // view would be a plain D2 one
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            dimension: Some(wgpu::TextureViewDimension::D2Array),
+            ..Default::default()
+        });
+
+        Ok(Self { texture, view })
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn layer_count(&self) -> u32 {
+        self.texture.depth_or_array_layers()
+    }
+}
+
+/// A row of quads sharing one bind group, each one showing a different
+/// layer of a texture array picked by its instance index
+pub struct TextureArrayDemo {
+    array: TextureArray,
+    bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl TextureArrayDemo {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let layers: Vec<_> = (0..DEMO_LAYERS).map(demo_layer).collect();
+        let array = TextureArray::new(device, queue, memory, "Demo Texture Array", &layers)
+            .expect("the demo layers all have the same size and format");
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Texture Array Sampler"),
+            // Keep the patterns crisp when scaled up
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Texture Array Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2Array,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Texture Array Bind Group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(array.view()),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Texture Array Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let pipeline = create_pipeline(
+            device,
+            &pipeline_layout,
+            format,
+            sample_count,
+            array.layer_count(),
+        );
+
+        Self {
+            array,
+            bind_group,
+            pipeline_layout,
+            pipeline,
+        }
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = create_pipeline(
+            device,
+            &self.pipeline_layout,
+            format,
+            sample_count,
+            self.array.layer_count(),
+        );
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        // One quad per layer, all in a single draw call
+        render_pass.draw(0..6, 0..self.array.layer_count());
+    }
+}
+
+/// A different test pattern for each layer: checkerboard, UV grid,
+/// stripes and dots
+fn demo_layer(layer: u32) -> image::DynamicImage {
+    let image = match layer % 4 {
+        0 => texture::checkerboard_image(DEMO_LAYER_SIZE, [230, 60, 50, 255], [57, 15, 12, 255], 8),
+        1 => texture::uv_grid_image(DEMO_LAYER_SIZE, 4),
+        pattern => image::RgbaImage::from_fn(DEMO_LAYER_SIZE, DEMO_LAYER_SIZE, |x, y| {
+            let on = if pattern == 2 {
+                (x + y) / 6 % 2 == 0
+            } else {
+                let (dx, dy) = (x as i32 % 16 - 8, y as i32 % 16 - 8);
+                dx * dx + dy * dy < 25
+            };
+            if on {
+                image::Rgba([50, 110, 230, 255])
+            } else {
+                image::Rgba([12, 27, 57, 255])
+            }
+        }),
+    };
+
+    image::DynamicImage::ImageRgba8(image)
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    layer_count: u32,
+) -> wgpu::RenderPipeline {
+    // textureNumLayers() would do, but the GL backend can't use it in vertex shaders
+    let shader = shader::create_shader_module(
+        device,
+        "Texture Array Shader",
+        &format!(
+            "const LAYER_COUNT: u32 = {layer_count}u;\n{}",
+            include_str!("texture_array.wgsl")
+        ),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Texture Array Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: depth::depth_ignored(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
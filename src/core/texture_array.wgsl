@@ -0,0 +1,54 @@
+// Draws one quad per layer of a texture array, in a row across the screen.
+// The instance index picks both the position of the quad and its layer.
+// LAYER_COUNT is declared by texture_array.rs.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    // Integers can't be interpolated, the whole quad uses the same layer
+    @location(1) @interpolate(flat) layer: u32,
+};
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+);
+
+const QUAD_SIZE = 0.4;
+const QUAD_SPACING = 0.45;
+
+@group(0) @binding(0) var layers: texture_2d_array<f32>;
+@group(0) @binding(1) var layers_sampler: sampler;
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex_index: u32,
+    @builtin(instance_index) instance_index: u32,
+) -> VertexOutput {
+    // Constant arrays can only be indexed through a variable in naga
+    var corners = CORNERS;
+    let corner = corners[vertex_index];
+    // Center the row of quads horizontally
+    let row_width = f32(LAYER_COUNT - 1u) * QUAD_SPACING + QUAD_SIZE;
+    let left = -row_width * 0.5 + f32(instance_index) * QUAD_SPACING;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        left + corner.x * QUAD_SIZE,
+        -QUAD_SIZE * 0.5 + corner.y * QUAD_SIZE,
+        0.0,
+        1.0,
+    );
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y);
+    out.layer = instance_index;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(layers, layers_sampler, in.uv, in.layer);
+}
@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use super::memory::{MemoryRegistry, Tracked};
+
+/// Must match the `TimeUniforms` struct in shader.wgsl.
+/// Uniform buffers follow WGSL's alignment rules, which are close to std140:
+/// the struct is rounded up to a multiple of 16 bytes, so the lone f32 is
+/// padded to a full 16 byte block. Anything added later has to follow the
+/// same rules, e.g. a vec3 or a vec4 starts on a 16 byte boundary.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TimeUniforms {
+    // In seconds
+    elapsed: f32,
+    _padding: [f32; 3],
+}
+
+impl TimeUniforms {
+    pub fn new(elapsed: Duration) -> Self {
+        Self {
+            elapsed: elapsed.as_secs_f32(),
+            _padding: [0.0; 3],
+        }
+    }
+}
+
+/// The bind group layout, uniform buffer and bind group of the time,
+/// bound at group 0 of the mesh pipeline. The time starts at zero.
+pub fn create_time_bindings(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+) -> (
+    wgpu::BindGroupLayout,
+    Tracked<wgpu::Buffer>,
+    wgpu::BindGroup,
+) {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Time Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<TimeUniforms>() as wgpu::BufferAddress
+                ),
+            },
+            count: None,
+        }],
+    });
+
+    // Written again every frame
+    let buffer = memory.create_buffer_init(
+        device,
+        &wgpu::util::BufferInitDescriptor {
+            label: Some("Time Uniforms Buffer"),
+            contents: bytemuck::bytes_of(&TimeUniforms::new(Duration::ZERO)),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        },
+    );
+
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Time Bind Group"),
+        layout: &bind_group_layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: buffer.as_entire_binding(),
+        }],
+    });
+
+    (bind_group_layout, buffer, bind_group)
+}
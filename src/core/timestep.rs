@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+/// Classic fixed timestep accumulator: real time is accumulated every frame
+/// and consumed in steps of a fixed size, so the simulation behaves the same
+/// regardless of the render rate.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_substeps: u32,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(step: Duration, max_substeps: u32) -> Self {
+        Self {
+            step,
+            max_substeps,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    /// Accumulate the time 'elapsed' since the last frame and
+    /// return how many fixed steps should be simulated this frame.
+    /// The caller measures the time, so that a replay can feed recorded frame times.
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed;
+
+        let mut steps = 0;
+        while self.accumulator >= self.step {
+            if steps == self.max_substeps {
+                // Too far behind, drop the backlog instead of catching up
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+            self.accumulator -= self.step;
+            steps += 1;
+        }
+
+        steps
+    }
+
+    /// Duration of a single fixed step
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+}
@@ -0,0 +1,52 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// The file gets flushed every this many frames, so a crash loses at most this many
+const FLUSH_INTERVAL: u64 = 60;
+
+/// Writes the duration of every frame to a CSV file (`--log-timings <path>`),
+/// to dig into the spikes of the frame time graph with a spreadsheet
+pub struct TimingsLog {
+    writer: BufWriter<File>,
+    frame: u64,
+}
+
+impl TimingsLog {
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,cpu_ms,gpu_ms")?;
+
+        Ok(Self { writer, frame: 0 })
+    }
+
+    /// Add a row for the last frame. 'gpu_time' is None when it wasn't measured,
+    /// which leaves the column empty.
+    pub fn record(
+        &mut self,
+        cpu_time: Duration,
+        gpu_time: Option<Duration>,
+    ) -> std::io::Result<()> {
+        let gpu_ms = gpu_time
+            .map(|time| format!("{:.3}", time.as_secs_f64() * 1000.0))
+            .unwrap_or_default();
+        writeln!(
+            self.writer,
+            "{},{:.3},{gpu_ms}",
+            self.frame,
+            cpu_time.as_secs_f64() * 1000.0
+        )?;
+
+        self.frame += 1;
+        if self.frame.is_multiple_of(FLUSH_INTERVAL) {
+            self.writer.flush()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,34 @@
+use wgpu::util::StagingBelt;
+
+/// Size of the staging buffers the belt allocates, big enough for all
+/// the per-frame data so that a single chunk is usually enough
+pub const STAGING_CHUNK_SIZE: wgpu::BufferAddress = 64 * 1024;
+
+/// Writes the per-frame data into GPU buffers, either through a staging belt
+/// or with `queue.write_buffer()` when there's none.
+/// With the belt, the data goes into reusable mapped staging buffers and the
+/// copies are recorded in the frame's encoder, instead of wgpu allocating
+/// a new staging area for every write.
+pub struct BufferWriter<'a> {
+    pub device: &'a wgpu::Device,
+    pub queue: &'a wgpu::Queue,
+    pub encoder: &'a mut wgpu::CommandEncoder,
+    pub staging_belt: Option<&'a mut StagingBelt>,
+}
+
+impl BufferWriter<'_> {
+    /// Copy 'data' into 'buffer' at 'offset'.
+    /// Like copies between buffers, the size has to be a multiple of 4 bytes.
+    pub fn write(&mut self, buffer: &wgpu::Buffer, offset: wgpu::BufferAddress, data: &[u8]) {
+        let Some(size) = wgpu::BufferSize::new(data.len() as wgpu::BufferAddress) else {
+            return;
+        };
+
+        match &mut self.staging_belt {
+            Some(staging_belt) => staging_belt
+                .write_buffer(self.encoder, buffer, offset, size, self.device)
+                .copy_from_slice(data),
+            None => self.queue.write_buffer(buffer, offset, data),
+        }
+    }
+}
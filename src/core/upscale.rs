@@ -0,0 +1,305 @@
+use super::fullscreen;
+use super::memory::{MemoryRegistry, Tracked};
+
+/// What decides the size the scene is rendered at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderResolution {
+    /// The size of the target times a scale, see scaled_size().
+    /// It's filtered linearly when it's scaled to the target.
+    Scaled(f32),
+    /// A fixed size, shown with nearest filtering at the largest integer
+    /// scale that fits in the target, see integer_viewport()
+    PixelPerfect(winit::dpi::PhysicalSize<u32>),
+}
+
+impl RenderResolution {
+    /// The size the scene is rendered at for a target of 'size', which
+    /// neither side of can go past 'max_dimension'
+    pub fn size(
+        self,
+        size: winit::dpi::PhysicalSize<u32>,
+        max_dimension: u32,
+    ) -> winit::dpi::PhysicalSize<u32> {
+        match self {
+            Self::Scaled(scale) => scaled_size(size, scale, max_dimension),
+            Self::PixelPerfect(base) => winit::dpi::PhysicalSize::new(
+                base.width.clamp(1, max_dimension),
+                base.height.clamp(1, max_dimension),
+            ),
+        }
+    }
+}
+
+/// The size the scene is rendered at, for a target of 'size' and a render
+/// scale of 'scale': below 1 it's upscaled to the target, above 1 it's
+/// supersampled. The scale is lowered if needed so that neither side goes
+/// past 'max_dimension', which keeps the aspect ratio of the target.
+pub fn scaled_size(
+    size: winit::dpi::PhysicalSize<u32>,
+    scale: f32,
+    max_dimension: u32,
+) -> winit::dpi::PhysicalSize<u32> {
+    let max_dimension = max_dimension as f32;
+    let scale = scale
+        .min(max_dimension / size.width.max(1) as f32)
+        .min(max_dimension / size.height.max(1) as f32);
+    let scale_side = |side: u32| ((side as f32 * scale).round() as u32).max(1);
+
+    winit::dpi::PhysicalSize::new(scale_side(size.width), scale_side(size.height))
+}
+
+/// Where a scene of 'base' pixels goes in a target of 'size', as the x, y,
+/// width and height of a viewport: scaled by the largest integer that fits,
+/// so that every pixel of the scene covers the same number of pixels of the
+/// target, and centered. What's left around it is letterboxed.
+/// A target smaller than the scene gets it scaled down to fit instead.
+pub fn integer_viewport(
+    size: winit::dpi::PhysicalSize<u32>,
+    base: winit::dpi::PhysicalSize<u32>,
+) -> [f32; 4] {
+    let (width, height) = (size.width as f32, size.height as f32);
+    let scale = (size.width / base.width.max(1)).min(size.height / base.height.max(1));
+    let (viewport_width, viewport_height) = if scale >= 1 {
+        ((base.width * scale) as f32, (base.height * scale) as f32)
+    } else {
+        let scale = (width / base.width as f32).min(height / base.height as f32);
+        (base.width as f32 * scale, base.height as f32 * scale)
+    };
+    // On whole pixels, for the scene pixels to line up with the target ones
+    let x = ((width - viewport_width) / 2.0).floor();
+    let y = ((height - viewport_height) / 2.0).floor();
+
+    [x, y, viewport_width, viewport_height]
+}
+
+/// The color target the scene is rendered into when it doesn't have the size
+/// of the surface, in the format of the surface so that the same pipelines
+/// draw into both
+pub fn create_scene_texture(
+    device: &wgpu::Device,
+    memory: &MemoryRegistry,
+    surface_config: &wgpu::SurfaceConfiguration,
+) -> (Tracked<wgpu::Texture>, wgpu::TextureView) {
+    let texture = memory.create_texture(
+        device,
+        &wgpu::TextureDescriptor {
+            label: Some("Scene Texture"),
+            size: wgpu::Extent3d {
+                width: surface_config.width,
+                height: surface_config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: surface_config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        },
+    );
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+    (texture, view)
+}
+
+/// Scales the scene texture to the surface, in a pass of its own at the end
+/// of the frame. Linear filtering blends the texels of a smaller scene, and
+/// averages 2x2 texels of a scene rendered at twice the size. Nearest
+/// filtering keeps the pixels of a pixel-perfect scene sharp.
+pub struct Upscaler {
+    bind_group_layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    linear_sampler: wgpu::Sampler,
+    nearest_sampler: wgpu::Sampler,
+}
+
+impl Upscaler {
+    /// 'format' is the one of the surface
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let (bind_group_layout, pipeline) = create_pipeline(device, format);
+        let create_sampler = |label, filter| {
+            device.create_sampler(&wgpu::SamplerDescriptor {
+                label: Some(label),
+                address_mode_u: wgpu::AddressMode::ClampToEdge,
+                address_mode_v: wgpu::AddressMode::ClampToEdge,
+                mag_filter: filter,
+                min_filter: filter,
+                ..Default::default()
+            })
+        };
+
+        Self {
+            bind_group_layout,
+            pipeline,
+            linear_sampler: create_sampler("Linear Upscale Sampler", wgpu::FilterMode::Linear),
+            nearest_sampler: create_sampler("Nearest Upscale Sampler", wgpu::FilterMode::Nearest),
+        }
+    }
+
+    /// The pipeline has to match the format of the surface
+    pub fn rebuild_pipeline(&mut self, device: &wgpu::Device, format: wgpu::TextureFormat) {
+        (self.bind_group_layout, self.pipeline) = create_pipeline(device, format);
+    }
+
+    /// Draw 'scene', rendered at 'resolution', into 'target' of 'size'.
+    /// It covers the target entirely, except for the letterboxing around
+    /// a pixel-perfect scene.
+    /// The bind group is created here, since the scene texture is recreated
+    /// with every resize.
+    pub fn encode(
+        &self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        scene: &wgpu::TextureView,
+        target: &wgpu::TextureView,
+        size: winit::dpi::PhysicalSize<u32>,
+        resolution: RenderResolution,
+    ) {
+        let (sampler, viewport) = match resolution {
+            RenderResolution::Scaled(_) => (&self.linear_sampler, None),
+            RenderResolution::PixelPerfect(base) => {
+                (&self.nearest_sampler, Some(integer_viewport(size, base)))
+            }
+        };
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Upscale Bind Group"),
+            layout: &self.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(scene),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upscale Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: target,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    // The color of the letterboxing
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        if let Some([x, y, width, height]) = viewport {
+            render_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        }
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    format: wgpu::TextureFormat,
+) -> (wgpu::BindGroupLayout, wgpu::RenderPipeline) {
+    let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Upscale Bind Group Layout"),
+        entries: &[
+            wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    multisampled: false,
+                },
+                count: None,
+            },
+            wgpu::BindGroupLayoutEntry {
+                binding: 1,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                count: None,
+            },
+        ],
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("Upscale Pipeline Layout"),
+        bind_group_layouts: &[&bind_group_layout],
+        push_constant_ranges: &[],
+    });
+    let pipeline = fullscreen::create_fullscreen_pipeline_with_target(
+        device,
+        "Upscale Pipeline",
+        Some(&layout),
+        wgpu::ColorTargetState {
+            format,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        },
+        1,
+        include_str!("upscale.wgsl"),
+        "fs_upscale",
+    );
+
+    (bind_group_layout, pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_sizes_stay_within_the_max_dimension() {
+        let size = winit::dpi::PhysicalSize::new(800, 600);
+        assert_eq!(scaled_size(size, 1.0, 8192), size);
+        assert_eq!(
+            scaled_size(size, 0.5, 8192),
+            winit::dpi::PhysicalSize::new(400, 300)
+        );
+        assert_eq!(
+            scaled_size(size, 2.0, 8192),
+            winit::dpi::PhysicalSize::new(1600, 1200)
+        );
+        // Lowered to 1.28, for the width to fit
+        assert_eq!(
+            scaled_size(size, 2.0, 1024),
+            winit::dpi::PhysicalSize::new(1024, 768)
+        );
+        // Never down to nothing
+        assert_eq!(
+            scaled_size(size, 0.0001, 8192),
+            winit::dpi::PhysicalSize::new(1, 1)
+        );
+    }
+
+    #[test]
+    fn pixel_perfect_scenes_are_scaled_by_whole_numbers() {
+        let base = winit::dpi::PhysicalSize::new(320, 180);
+        let size = |width, height| winit::dpi::PhysicalSize::new(width, height);
+
+        // 3x, with 20 pixels left on each side
+        assert_eq!(
+            integer_viewport(size(1000, 600), base),
+            [20.0, 30.0, 960.0, 540.0]
+        );
+        assert_eq!(
+            integer_viewport(size(320, 180), base),
+            [0.0, 0.0, 320.0, 180.0]
+        );
+        // The height only fits it once
+        assert_eq!(
+            integer_viewport(size(1920, 359), base),
+            [800.0, 89.0, 320.0, 180.0]
+        );
+        // Too small for it, scaled down to 0.5
+        assert_eq!(
+            integer_viewport(size(160, 100), base),
+            [0.0, 5.0, 160.0, 90.0]
+        );
+
+        let resolution = RenderResolution::PixelPerfect(base);
+        assert_eq!(resolution.size(size(1000, 600), 8192), base);
+        assert_eq!(resolution.size(size(1000, 600), 256), size(256, 180));
+    }
+}
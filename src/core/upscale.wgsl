@@ -0,0 +1,12 @@
+// Appended to fullscreen.wgsl: shows the scene rendered at the render scale
+// in the target it's scaled to, filtered by the sampler of upscale.rs
+
+@group(0) @binding(0)
+var scene: texture_2d<f32>;
+@group(0) @binding(1)
+var scene_sampler: sampler;
+
+@fragment
+fn fs_upscale(in: FullscreenOutput) -> @location(0) vec4<f32> {
+    return textureSample(scene, scene_sampler, in.uv);
+}
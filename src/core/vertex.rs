@@ -0,0 +1,175 @@
+/// Largest grid of instances, 10000 copies of the quad
+pub const MAX_GRID_SIZE: u32 = 100;
+
+// Distance between the centers of neighboring instances
+const GRID_SPACING: f32 = 1.25;
+
+/// A corner of the geometry drawn by shader.wgsl.
+/// Must match the `VertexInput` struct there.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    // In world space, seen through the camera
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    // (0, 0) is the top left corner of the texture
+    pub tex_coords: [f32; 2],
+}
+
+impl Vertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+
+    /// How the vertices are laid out in the vertex buffer
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// A square around the origin, with a color in each corner
+pub const QUAD_VERTICES: &[Vertex] = &[
+    // Top left
+    Vertex {
+        position: [-0.5, 0.5, 0.0],
+        color: [1.0, 0.0, 0.0],
+        tex_coords: [0.0, 0.0],
+    },
+    // Bottom left
+    Vertex {
+        position: [-0.5, -0.5, 0.0],
+        color: [0.0, 1.0, 0.0],
+        tex_coords: [0.0, 1.0],
+    },
+    // Bottom right
+    Vertex {
+        position: [0.5, -0.5, 0.0],
+        color: [0.0, 0.0, 1.0],
+        tex_coords: [1.0, 1.0],
+    },
+    // Top right
+    Vertex {
+        position: [0.5, 0.5, 0.0],
+        color: [1.0, 1.0, 0.0],
+        tex_coords: [1.0, 0.0],
+    },
+];
+
+/// The two triangles of the quad, sharing the diagonal vertices.
+/// Both are counter-clockwise, which is the front face of the pipeline.
+pub const QUAD_INDICES: &[u16] = &[0, 1, 2, 0, 2, 3];
+
+/// A copy of the geometry: the per-instance data, read once per instance
+/// rather than once per vertex.
+/// Must match the `InstanceInput` struct in shader.wgsl.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Instance {
+    // Added to the positions of the vertices, in world space
+    pub offset: [f32; 3],
+    // Around the Z axis, in radians, on top of the spinning
+    pub rotation: f32,
+}
+
+impl Instance {
+    // Both buffers feed the same vertex shader, so the instance attributes
+    // go right after the vertex ones: their locations can't collide
+    const FIRST_LOCATION: u32 = Vertex::ATTRIBUTES.len() as u32;
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] = [
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32x3,
+            offset: 0,
+            shader_location: Self::FIRST_LOCATION,
+        },
+        wgpu::VertexAttribute {
+            format: wgpu::VertexFormat::Float32,
+            offset: wgpu::VertexFormat::Float32x3.size(),
+            shader_location: Self::FIRST_LOCATION + 1,
+        },
+    ];
+
+    /// How the instances are laid out in the instance buffer
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            // Moves on to the next instance, not to the next vertex
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// A sphere holding every copy of 'vertices' placed by 'instances', whatever
+/// their rotation around Z, as (center, radius). It's centered on the middle
+/// of the instances, which is tight enough to frame the grid with.
+pub fn bounding_sphere(vertices: &[Vertex], instances: &[Instance]) -> ([f32; 3], f32) {
+    let length = |[x, y, z]: [f32; 3]| (x * x + y * y + z * z).sqrt();
+    let mesh_radius = vertices
+        .iter()
+        .map(|vertex| length(vertex.position))
+        .fold(0.0, f32::max);
+    if instances.is_empty() {
+        return ([0.0; 3], mesh_radius);
+    }
+
+    let (min, max) =
+        instances
+            .iter()
+            .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), instance| {
+                (
+                    std::array::from_fn(|i| min[i].min(instance.offset[i])),
+                    std::array::from_fn(|i| max[i].max(instance.offset[i])),
+                )
+            });
+    let center: [f32; 3] = std::array::from_fn(|i| (min[i] + max[i]) * 0.5);
+    let radius = instances
+        .iter()
+        .map(|instance| length(std::array::from_fn(|i| instance.offset[i] - center[i])))
+        .fold(0.0, f32::max);
+
+    (center, radius + mesh_radius)
+}
+
+/// A 'size' x 'size' grid of instances centered on the origin, in the XY
+/// plane, each one turned a bit more than the previous one.
+/// 'size' is clamped to [1, MAX_GRID_SIZE].
+pub fn grid_instances(size: u32) -> Vec<Instance> {
+    let size = size.clamp(1, MAX_GRID_SIZE);
+    let center = (size - 1) as f32 * 0.5;
+
+    (0..size)
+        .flat_map(|row| (0..size).map(move |column| (row, column)))
+        .enumerate()
+        .map(|(i, (row, column))| Instance {
+            offset: [
+                (column as f32 - center) * GRID_SPACING,
+                (center - row as f32) * GRID_SPACING,
+                0.0,
+            ],
+            rotation: i as f32 * 0.1,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounding_sphere_holds_the_whole_grid() {
+        let quad_radius = 0.5_f32.sqrt();
+
+        let (center, radius) = bounding_sphere(QUAD_VERTICES, &grid_instances(1));
+        assert_eq!(center, [0.0; 3]);
+        assert!((radius - quad_radius).abs() < 1e-6);
+
+        // The corner instances are the farthest from the center
+        let (center, radius) = bounding_sphere(QUAD_VERTICES, &grid_instances(3));
+        assert_eq!(center, [0.0; 3]);
+        let corner = GRID_SPACING * 2.0_f32.sqrt();
+        assert!((radius - (corner + quad_radius)).abs() < 1e-6, "{radius}");
+    }
+}
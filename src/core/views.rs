@@ -0,0 +1,203 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use winit::event::VirtualKeyCode;
+
+use super::camera::Camera;
+
+#[derive(Debug)]
+pub enum ViewError {
+    /// Nothing was saved in the slot yet
+    EmptySlot(u8),
+    Io(PathBuf, std::io::Error),
+    /// The file isn't a camera saved by save(), e.g. after editing it by hand
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ViewError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptySlot(slot) => write!(f, "No view saved in slot {slot}"),
+            Self::Io(path, e) => write!(f, "Failed to access {path:?}: {e}"),
+            Self::Parse(path, e) => write!(f, "{path:?} isn't a saved view: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ViewError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::EmptySlot(_) => None,
+            Self::Io(_, e) => Some(e),
+            Self::Parse(_, e) => Some(e),
+        }
+    }
+}
+
+/// A camera of the scene, cycled through with Page Up and Page Down
+#[derive(Debug, Clone)]
+pub struct Viewpoint {
+    pub name: String,
+    pub camera: Camera,
+}
+
+impl Viewpoint {
+    /// Looking from 'eye' at 'target', with Y up and the default field of view
+    pub fn new(name: &str, eye: [f32; 3], target: [f32; 3]) -> Self {
+        Self {
+            name: name.to_string(),
+            camera: Camera {
+                eye,
+                target,
+                ..Camera::new(1.0)
+            },
+        }
+    }
+}
+
+/// What there is to see in the default scene
+pub fn default_viewpoints() -> Vec<Viewpoint> {
+    vec![
+        Viewpoint::new("Front", [0.0, 0.0, 2.0], [0.0; 3]),
+        Viewpoint::new("Above", [0.0, 3.0, 1.5], [0.0, -0.75, 0.0]),
+        Viewpoint::new("Side", [3.0, 0.5, 0.5], [0.0; 3]),
+    ]
+}
+
+/// The viewpoints, with the one the camera was last sent to.
+/// The camera is free to move in the meantime: the next one is still
+/// relative to the last one picked.
+#[derive(Debug)]
+pub struct Viewpoints {
+    list: Vec<Viewpoint>,
+    // None until one is picked, which leaves the camera where it starts
+    current: Option<usize>,
+}
+
+impl Viewpoints {
+    pub fn new(list: Vec<Viewpoint>) -> Self {
+        Self {
+            list,
+            current: None,
+        }
+    }
+
+    /// The one after the current one, wrapping around.
+    /// None when there are no viewpoints.
+    pub fn next(&mut self) -> Option<&Viewpoint> {
+        let len = self.list.len();
+        self.step(|current| current.map_or(0, |i| (i + 1) % len))
+    }
+
+    /// The one before the current one, wrapping around.
+    /// None when there are no viewpoints.
+    pub fn prev(&mut self) -> Option<&Viewpoint> {
+        let len = self.list.len();
+        self.step(|current| current.map_or(len - 1, |i| (i + len - 1) % len))
+    }
+
+    fn step(&mut self, index: impl FnOnce(Option<usize>) -> usize) -> Option<&Viewpoint> {
+        if self.list.is_empty() {
+            return None;
+        }
+        let index = index(self.current);
+        self.current = Some(index);
+
+        self.list.get(index)
+    }
+}
+
+/// The slot of a digit key, from 0 to 9
+pub fn key_slot(key: VirtualKeyCode) -> Option<u8> {
+    let slot = match key {
+        VirtualKeyCode::Key0 => 0,
+        VirtualKeyCode::Key1 => 1,
+        VirtualKeyCode::Key2 => 2,
+        VirtualKeyCode::Key3 => 3,
+        VirtualKeyCode::Key4 => 4,
+        VirtualKeyCode::Key5 => 5,
+        VirtualKeyCode::Key6 => 6,
+        VirtualKeyCode::Key7 => 7,
+        VirtualKeyCode::Key8 => 8,
+        VirtualKeyCode::Key9 => 9,
+        _ => return None,
+    };
+
+    Some(slot)
+}
+
+/// The file of 'slot' in 'dir', e.g. view_1.toml
+pub fn slot_path(dir: &Path, slot: u8) -> PathBuf {
+    dir.join(format!("view_{slot}.toml"))
+}
+
+/// Write 'camera' into its slot, creating 'dir' if needed
+pub fn save(dir: &Path, slot: u8, camera: &Camera) -> Result<PathBuf, ViewError> {
+    let path = slot_path(dir, slot);
+    let contents = toml::to_string(camera).expect("a camera is only made of numbers");
+    std::fs::create_dir_all(dir).map_err(|e| ViewError::Io(dir.to_path_buf(), e))?;
+    std::fs::write(&path, contents).map_err(|e| ViewError::Io(path.clone(), e))?;
+
+    Ok(path)
+}
+
+/// The camera saved in 'slot', with an aspect ratio of 0 that's up to the
+/// caller to set
+pub fn load(dir: &Path, slot: u8) -> Result<Camera, ViewError> {
+    let path = slot_path(dir, slot);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err(ViewError::EmptySlot(slot))
+        }
+        Err(e) => return Err(ViewError::Io(path, e)),
+    };
+
+    toml::from_str(&contents).map_err(|e| ViewError::Parse(path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewpoints_wrap_around() {
+        let mut viewpoints = Viewpoints::new(vec![
+            Viewpoint::new("a", [1.0; 3], [0.0; 3]),
+            Viewpoint::new("b", [2.0; 3], [0.0; 3]),
+        ]);
+        assert_eq!(viewpoints.next().map(|v| v.name.as_str()), Some("a"));
+        assert_eq!(viewpoints.next().map(|v| v.name.as_str()), Some("b"));
+        assert_eq!(viewpoints.next().map(|v| v.name.as_str()), Some("a"));
+        assert_eq!(viewpoints.prev().map(|v| v.name.as_str()), Some("b"));
+
+        // Going back first starts from the end
+        let mut viewpoints = Viewpoints::new(default_viewpoints());
+        assert_eq!(viewpoints.prev().map(|v| v.name.as_str()), Some("Side"));
+
+        let mut viewpoints = Viewpoints::new(Vec::new());
+        assert!(viewpoints.next().is_none());
+        assert!(viewpoints.prev().is_none());
+    }
+
+    #[test]
+    fn saved_views_load_back() {
+        let dir = std::env::temp_dir().join(format!("webgpu-101-views-{}", std::process::id()));
+        let mut camera = Camera::new(1.0);
+        camera.eye = [4.0, 1.0, -2.0];
+        camera.fovy = 60.0;
+
+        assert!(matches!(load(&dir, 3), Err(ViewError::EmptySlot(3))));
+
+        let path = save(&dir, 3, &camera).unwrap();
+        assert_eq!(path, slot_path(&dir, 3));
+        let loaded = load(&dir, 3).unwrap();
+        assert_eq!(loaded.eye, camera.eye);
+        assert_eq!(loaded.fovy, camera.fovy);
+
+        std::fs::write(&path, "eye = 1").unwrap();
+        assert!(matches!(load(&dir, 3), Err(ViewError::Parse(..))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,27 @@
+use winit::platform::web::WindowExtWebSys;
+use winit::window::Window;
+
+/// The web counterpart of `env_logger::init()`. There are no environment
+/// variables in a browser, so the level is fixed to warnings and errors:
+/// wgpu is very chatty below that.
+/// Panics are printed to the console too, with their message, where they
+/// would otherwise only show up as an opaque "unreachable" trap.
+pub fn init_logging() {
+    console_error_panic_hook::set_once();
+    if let Err(e) = console_log::init_with_level(log::Level::Warn) {
+        web_sys::console::error_1(&format!("Failed to set up logging: {e}").into());
+    }
+}
+
+/// winit creates a canvas for the window, but it's up to us to put it in the
+/// page. Without a document or a body to put it in, there's nothing to show
+/// anything into, so that's treated as a setup bug.
+pub fn append_canvas_to_body(window: &Window) {
+    let body = web_sys::window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.body())
+        .expect("the app runs in a page with a body");
+
+    body.append_child(&window.canvas())
+        .expect("the canvas can be appended to the body");
+}
@@ -0,0 +1,137 @@
+use super::depth;
+use super::memory::MemoryRegistry;
+use super::shader::{self, ShaderStage};
+use super::texture::{self, SamplerDesc, Texture};
+
+/// Side of the two textures, in pixels
+const DEMO_TEXTURE_SIZE: u32 = 32;
+
+/// Two quads showing their texture past its edges, from -0.5 to 1.5 in UV
+/// space: a tiling texture which repeats, crisp with nearest filtering, and
+/// a UI texture clamped to its edges, smoothly filtered.
+pub struct WrappingDemo {
+    tiling_bind_group: wgpu::BindGroup,
+    clamped_bind_group: wgpu::BindGroup,
+    pipeline_layout: wgpu::PipelineLayout,
+    pipeline: wgpu::RenderPipeline,
+}
+
+impl WrappingDemo {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        memory: &MemoryRegistry,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) -> Self {
+        let tiling = Texture::from_image(
+            device,
+            queue,
+            memory,
+            "Tiling Texture",
+            &texture::checkerboard_image(
+                DEMO_TEXTURE_SIZE,
+                [230, 160, 40, 255],
+                [70, 40, 10, 255],
+                4,
+            ),
+            SamplerDesc {
+                mag_filter: wgpu::FilterMode::Nearest,
+                ..Default::default()
+            },
+        );
+        // Past its edges, the border is stretched out instead of the opposite
+        // side showing up
+        let clamped = Texture::from_image(
+            device,
+            queue,
+            memory,
+            "Clamped Texture",
+            &texture::uv_grid_image(DEMO_TEXTURE_SIZE, 2),
+            SamplerDesc::CLAMPED,
+        );
+
+        let bind_group_layout = Texture::bind_group_layout(device);
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Wrapping Pipeline Layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        Self {
+            tiling_bind_group: tiling.create_bind_group(device, &bind_group_layout),
+            clamped_bind_group: clamped.create_bind_group(device, &bind_group_layout),
+            pipeline: create_pipeline(device, &pipeline_layout, format, sample_count),
+            pipeline_layout,
+        }
+    }
+
+    /// The pipeline has to match the render target,
+    /// so it's rebuilt when the MSAA sample count changes
+    pub fn rebuild_pipeline(
+        &mut self,
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        sample_count: u32,
+    ) {
+        self.pipeline = create_pipeline(device, &self.pipeline_layout, format, sample_count);
+    }
+
+    pub fn draw<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_pipeline(&self.pipeline);
+        // The vertex index tells the shader which side the quad is on: the
+        // instance index would do too, but the GL backend ignores the first
+        // instance of draws
+        render_pass.set_bind_group(0, &self.tiling_bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+        render_pass.set_bind_group(0, &self.clamped_bind_group, &[]);
+        render_pass.draw(6..12, 0..1);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> wgpu::RenderPipeline {
+    let shader = shader::create_shader_module(
+        device,
+        "Wrapping Shader",
+        include_str!("wrapping.wgsl"),
+        &[
+            ("vs_main", ShaderStage::Vertex),
+            ("fs_main", ShaderStage::Fragment),
+        ],
+    );
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Wrapping Pipeline"),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            cull_mode: None,
+            ..Default::default()
+        },
+        depth_stencil: depth::depth_ignored(),
+        multisample: wgpu::MultisampleState {
+            count: sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
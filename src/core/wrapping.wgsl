@@ -0,0 +1,46 @@
+// Draws a quad on the left of the screen for the vertices 0 to 5, on the right
+// for the vertices 6 to 11, each showing twice its texture across: from -0.5 to 1.5 in UV
+// space, so that what the sampler does past the edges shows all around.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+const CORNERS = array<vec2<f32>, 6>(
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 0.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+);
+
+const QUAD_SIZE = 0.8;
+const QUAD_GAP = 0.1;
+
+@group(0) @binding(0) var t_diffuse: texture_2d<f32>;
+@group(0) @binding(1) var s_diffuse: sampler;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    // Constant arrays can only be indexed through a variable in naga
+    var corners = CORNERS;
+    let corner = corners[vertex_index % 6u];
+    let left = select(-QUAD_SIZE - QUAD_GAP * 0.5, QUAD_GAP * 0.5, vertex_index >= 6u);
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        left + corner.x * QUAD_SIZE,
+        -QUAD_SIZE * 0.5 + corner.y * QUAD_SIZE,
+        0.0,
+        1.0,
+    );
+    out.uv = vec2<f32>(corner.x, 1.0 - corner.y) * 2.0 - 0.5;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(t_diffuse, s_diffuse, in.uv);
+}
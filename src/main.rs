@@ -1,8 +1,22 @@
 mod core;
 
+#[cfg(not(target_arch = "wasm32"))]
 #[pollster::main]
 async fn main() {
     println!("Hello, WebGPU world!");
 
-    core::run().await;
+    // The error has already been logged
+    if core::run().await.is_err() {
+        std::process::exit(1);
+    }
+}
+
+// A browser can't be blocked on: the setup runs on its own event loop,
+// and main() returns right away
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    wasm_bindgen_futures::spawn_local(async {
+        // The error has already been logged, and there's no process to exit
+        let _ = core::run().await;
+    });
 }